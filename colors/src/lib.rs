@@ -254,6 +254,30 @@ pub fn hex_to_color_wgpu(s: &str) -> ColorWGPU {
         .to_wgpu()
 }
 
+/// Relative luminance of a sRGB color from 0 (black) to 1 (white), as WCAG
+/// defines it. Alpha is ignored.
+fn relative_luminance(color: ColorArray) -> f32 {
+    let linear = |channel: f32| {
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color[0]) + 0.7152 * linear(color[1]) + 0.0722 * linear(color[2])
+}
+
+/// WCAG contrast ratio of `fg` drawn over `bg`, from 1 (same color) to 21
+/// (black and white). A translucent `fg` is blended over `bg` first, the
+/// alpha of `bg` is ignored.
+pub fn contrast_ratio(fg: ColorArray, bg: ColorArray) -> f32 {
+    let alpha = fg[3].clamp(0.0, 1.0);
+    let blend = |channel: usize| fg[channel] * alpha + bg[channel] * (1.0 - alpha);
+    let fg = relative_luminance([blend(0), blend(1), blend(2), 1.0]);
+    let bg = relative_luminance(bg);
+    (fg.max(bg) + 0.05) / (fg.min(bg) + 0.05)
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord)]
 pub enum NamedColor {
     /// Black.
@@ -662,4 +686,66 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let black = [0.0, 0.0, 0.0, 1.0];
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let close = |ratio: f32, expected: f32| (ratio - expected).abs() < 0.01;
+
+        assert!(close(contrast_ratio(black, white), 21.0));
+        assert!(close(contrast_ratio(white, black), 21.0));
+        assert!(close(contrast_ratio(white, white), 1.0));
+        // #767676 is the lightest gray that passes AA on white.
+        assert!(close(
+            contrast_ratio(hex_to_color_arr("#767676"), white),
+            4.54
+        ));
+        assert!(close(
+            contrast_ratio(hex_to_color_arr("#FF0000"), white),
+            4.0
+        ));
+
+        // A transparent foreground is the background.
+        assert!(close(contrast_ratio([0.0, 0.0, 0.0, 0.0], white), 1.0));
+    }
+
+    #[test]
+    fn test_256_palette() {
+        use crate::term::{List, TermColors};
+
+        let list = List::from(&TermColors::default());
+        // 16 + 36 * 5, the brightest red of the cube.
+        assert_eq!(list[196], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(list[16], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(list[231], [1.0, 1.0, 1.0, 1.0]);
+        // Gray ramp from 8 to 238.
+        assert_eq!(ColorRgb::from_color_arr(list[232]).r, 8);
+        assert_eq!(ColorRgb::from_color_arr(list[255]).r, 238);
+    }
+
+    #[test]
+    fn test_nearest_256() {
+        use crate::term::{nearest_256, List, TermColors};
+
+        // Exact cube and gray ramp colors map to themselves.
+        let list = List::from(&TermColors::default());
+        for index in 16..256 {
+            let rgb = ColorRgb::from_color_arr(list[index]);
+            assert_eq!(nearest_256(rgb) as usize, index);
+        }
+
+        let orange = ColorRgb {
+            r: 250,
+            g: 130,
+            b: 10,
+        };
+        assert_eq!(nearest_256(orange), 16 + 36 * 5 + 6 * 2);
+        let gray = ColorRgb {
+            r: 100,
+            g: 101,
+            b: 99,
+        };
+        assert_eq!(nearest_256(gray), 241);
+    }
 }
@@ -166,6 +166,57 @@ impl List {
     }
 }
 
+/// Levels of each channel in the 6x6x6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Index of the closest color in the 256 palette cube or gray ramp (16 to 255),
+/// the first 16 colors are left out since they are configurable.
+pub fn nearest_256(rgb: ColorRgb) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs())
+            .unwrap_or_default()
+    };
+    let (r, g, b) = (level(rgb.r), level(rgb.g), level(rgb.b));
+    let cube = ColorRgb {
+        r: CUBE_LEVELS[r],
+        g: CUBE_LEVELS[g],
+        b: CUBE_LEVELS[b],
+    };
+
+    let average = (rgb.r as u32 + rgb.g as u32 + rgb.b as u32) / 3;
+    let gray_step = (average.saturating_sub(8) + 5) / 10;
+    let gray_step = std::cmp::min(gray_step, 23) as u8;
+    let gray_value = gray_step * 10 + 8;
+    let gray = ColorRgb {
+        r: gray_value,
+        g: gray_value,
+        b: gray_value,
+    };
+
+    if rgb_distance(rgb, gray) < rgb_distance(rgb, cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+/// Index of the closest color in `palette`.
+pub fn nearest(palette: &[ColorArray], color: ColorArray) -> usize {
+    let rgb = ColorRgb::from_color_arr(color);
+    (0..palette.len())
+        .min_by_key(|&i| rgb_distance(rgb, ColorRgb::from_color_arr(palette[i])))
+        .unwrap_or_default()
+}
+
+#[inline]
+fn rgb_distance(a: ColorRgb, b: ColorRgb) -> u32 {
+    let r = a.r as i32 - b.r as i32;
+    let g = a.g as i32 - b.g as i32;
+    let b = a.b as i32 - b.b as i32;
+    (r * r + g * g + b * b) as u32
+}
+
 impl Index<usize> for List {
     type Output = ColorArray;
 
@@ -0,0 +1,20 @@
+use serde::Deserialize;
+
+#[derive(Default, Debug, Deserialize, PartialEq, Clone)]
+pub struct Bindings {
+    #[serde(default = "Vec::new")]
+    pub keys: Vec<KeyBinding>,
+}
+
+/// Key binding defined in the configuration file, e.g:
+/// `{ key = "plus", with = "control | shift", action = "IncreaseFontSize" }`
+#[derive(Default, Debug, Deserialize, PartialEq, Clone)]
+pub struct KeyBinding {
+    pub key: String,
+    #[serde(default = "String::new")]
+    pub with: String,
+    #[serde(default = "String::new")]
+    pub action: String,
+    #[serde(default = "String::new")]
+    pub esc: String,
+}
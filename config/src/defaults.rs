@@ -16,6 +16,10 @@ pub fn default_option_as_alt() -> String {
     String::from("None")
 }
 
+pub fn default_backspace_sends_delete() -> bool {
+    true
+}
+
 pub fn default_log_level() -> String {
     String::from("OFF")
 }
@@ -28,6 +32,18 @@ pub fn default_cursor() -> char {
     '▇'
 }
 
+pub fn default_cursor_blink_interval() -> u64 {
+    500
+}
+
+pub fn default_min_redraw_interval() -> u64 {
+    16
+}
+
+pub fn default_bar_cursor_width() -> f32 {
+    2.0
+}
+
 pub fn default_theme() -> String {
     String::from("")
 }
@@ -35,3 +51,59 @@ pub fn default_theme() -> String {
 pub fn default_font_size() -> f32 {
     16.0
 }
+
+pub fn default_synthesize_bold() -> bool {
+    true
+}
+
+pub fn default_synthesize_italic() -> bool {
+    true
+}
+
+pub fn default_bold_strength() -> f32 {
+    1.0 / 48.0
+}
+
+pub fn default_italic_slant() -> f32 {
+    12.0
+}
+
+pub fn default_snap_to_pixel() -> bool {
+    true
+}
+
+pub fn default_background_bleed() -> f32 {
+    0.5
+}
+
+pub fn default_visual_bell_color() -> colors::ColorArray {
+    [1.0, 1.0, 1.0, 1.0]
+}
+
+pub fn default_visual_bell_opacity() -> f32 {
+    0.3
+}
+
+pub fn default_visual_bell_duration() -> u64 {
+    150
+}
+
+pub fn default_visual_bell_fade() -> bool {
+    true
+}
+
+pub fn default_indent_guides_interval() -> u16 {
+    4
+}
+
+pub fn default_indent_guides_color() -> colors::ColorArray {
+    [0.5, 0.5, 0.5, 0.3]
+}
+
+pub fn default_max_string_length() -> usize {
+    0x10_0000
+}
+
+pub fn default_max_line_length() -> usize {
+    10_000
+}
@@ -1,6 +1,8 @@
-mod defaults;
+pub mod bindings;
+pub mod defaults;
+use crate::bindings::Bindings;
 use crate::defaults::*;
-use colors::Colors;
+use colors::{ColorArray, Colors};
 use log::warn;
 use serde::Deserialize;
 use std::default::Default;
@@ -12,14 +14,162 @@ pub enum Performance {
     Low,
 }
 
+/// Line endings used for pasted text.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum PasteNewline {
+    Keep,
+    Lf,
+    #[default]
+    Cr,
+}
+
+/// Width of East Asian ambiguous characters.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+/// What the form feed character (FF) does.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum FormFeed {
+    #[default]
+    LineFeed,
+    Clear,
+    Ignore,
+}
+
+/// How C0 control characters without an action of their own are shown.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum ControlPictureStyle {
+    #[default]
+    Hidden,
+    Caret,
+    ControlPictures,
+}
+
+/// What happens to a line once it reaches `max-line-length`.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum LongLines {
+    #[default]
+    Wrap,
+    Truncate,
+}
+
+/// How frames are presented, modes the display doesn't support fall back
+/// to `Vsync`.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum PresentMode {
+    #[default]
+    Vsync,
+    NoVsync,
+    Mailbox,
+    Immediate,
+}
+
+/// What the viewport follows when the terminal is resized.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum ResizeAnchor {
+    #[default]
+    Top,
+    Cursor,
+    Bottom,
+}
+
+/// Colors available when rendering, the terminal keeps true colors.
+#[derive(Default, Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum ColorDepth {
+    Monochrome,
+    Ansi16,
+    Ansi256,
+    #[default]
+    TrueColor,
+}
+
+/// Flash drawn over the terminal when the bell rings.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+pub struct VisualBell {
+    #[serde(
+        default = "default_visual_bell_color",
+        deserialize_with = "colors::deserialize_to_arr"
+    )]
+    pub color: ColorArray,
+    #[serde(default = "default_visual_bell_opacity")]
+    pub opacity: f32,
+    /// Flash duration in milliseconds.
+    #[serde(default = "default_visual_bell_duration")]
+    pub duration: u64,
+    #[serde(default = "default_visual_bell_fade")]
+    pub fade: bool,
+}
+
+/// Vertical lines drawn every `interval` columns of the indentation.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+pub struct IndentGuides {
+    #[serde(default = "default_indent_guides_interval")]
+    pub interval: u16,
+    #[serde(
+        default = "default_indent_guides_color",
+        deserialize_with = "colors::deserialize_to_arr"
+    )]
+    pub color: ColorArray,
+}
+
+/// Light or dark appearance of the system, it picks between `light-theme`
+/// and `dark-theme`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Style {
     #[serde(rename = "font-size", default = "default_font_size")]
     pub font_size: f32,
     #[serde(default = "default_theme")]
     pub theme: String,
+    #[serde(default = "String::default", rename = "light-theme")]
+    pub light_theme: String,
+    #[serde(default = "String::default", rename = "dark-theme")]
+    pub dark_theme: String,
     #[serde(default = "default_font")]
     pub font: String,
+    #[serde(default = "Vec::default", rename = "font-fallbacks")]
+    pub font_fallbacks: Vec<String>,
+    #[serde(default = "Vec::default", rename = "font-features")]
+    pub font_features: Vec<String>,
+    #[serde(default = "default_synthesize_bold", rename = "synthesize-bold")]
+    pub synthesize_bold: bool,
+    #[serde(default = "default_bold_strength", rename = "bold-strength")]
+    pub bold_strength: f32,
+    #[serde(default = "default_synthesize_italic", rename = "synthesize-italic")]
+    pub synthesize_italic: bool,
+    #[serde(default = "default_italic_slant", rename = "italic-slant")]
+    pub italic_slant: f32,
+    #[serde(default = "Option::default", rename = "baseline-offset")]
+    pub baseline_offset: Option<f32>,
+    #[serde(default = "default_snap_to_pixel", rename = "snap-to-pixel")]
+    pub snap_to_pixel: bool,
+    #[serde(default = "Option::default", rename = "wrap-indicator")]
+    pub wrap_indicator: Option<char>,
+    #[serde(default = "Option::default", rename = "tab-leader")]
+    pub tab_leader: Option<char>,
+    #[serde(default = "Option::default", rename = "indent-guides")]
+    pub indent_guides: Option<IndentGuides>,
+    #[serde(default = "Option::default", rename = "missing-glyph-replacement")]
+    pub missing_glyph_replacement: Option<char>,
+    #[serde(default = "bool::default", rename = "fill-cursor-line")]
+    pub fill_cursor_line: bool,
+    #[serde(default = "default_background_bleed", rename = "background-bleed")]
+    pub background_bleed: f32,
+    #[serde(default = "ColorDepth::default", rename = "color-depth")]
+    pub color_depth: ColorDepth,
+    #[serde(default = "Vec::default", rename = "force-text-font-ranges")]
+    pub force_text_font_ranges: Vec<[u32; 2]>,
+    #[serde(default = "bool::default", rename = "search-spotlight")]
+    pub search_spotlight: bool,
 }
 
 impl Default for Style {
@@ -27,15 +177,51 @@ impl Default for Style {
         Style {
             font_size: default_font_size(),
             theme: default_theme(),
+            light_theme: String::default(),
+            dark_theme: String::default(),
             font: default_font(),
+            font_fallbacks: vec![],
+            font_features: vec![],
+            synthesize_bold: default_synthesize_bold(),
+            bold_strength: default_bold_strength(),
+            synthesize_italic: default_synthesize_italic(),
+            italic_slant: default_italic_slant(),
+            baseline_offset: None,
+            snap_to_pixel: default_snap_to_pixel(),
+            wrap_indicator: None,
+            tab_leader: None,
+            indent_guides: None,
+            missing_glyph_replacement: None,
+            fill_cursor_line: false,
+            background_bleed: default_background_bleed(),
+            color_depth: ColorDepth::default(),
+            force_text_font_ranges: vec![],
+            search_spotlight: false,
         }
     }
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Deserialize)]
 pub struct Advanced {
     #[serde(default = "bool::default", rename = "disable-render-when-unfocused")]
     pub disable_render_when_unfocused: bool,
+    #[serde(default = "default_max_string_length", rename = "max-string-length")]
+    pub max_string_length: usize,
+    #[serde(default = "default_max_line_length", rename = "max-line-length")]
+    pub max_line_length: usize,
+    #[serde(default = "LongLines::default", rename = "long-lines")]
+    pub long_lines: LongLines,
+}
+
+impl Default for Advanced {
+    fn default() -> Advanced {
+        Advanced {
+            disable_render_when_unfocused: false,
+            max_string_length: default_max_string_length(),
+            max_line_length: default_max_line_length(),
+            long_lines: LongLines::default(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Deserialize)]
@@ -44,6 +230,8 @@ pub struct Developer {
     pub enable_fps_counter: bool,
     #[serde(default = "default_log_level", rename = "log-level")]
     pub log_level: String,
+    #[serde(default = "Option::default", rename = "ready-marker")]
+    pub ready_marker: Option<String>,
 }
 
 impl Default for Developer {
@@ -51,6 +239,7 @@ impl Default for Developer {
         Developer {
             log_level: default_log_level(),
             enable_fps_counter: false,
+            ready_marker: None,
         }
     }
 }
@@ -59,24 +248,72 @@ impl Default for Developer {
 pub struct Config {
     #[serde(default = "Performance::default")]
     pub performance: Performance,
+    #[serde(default = "PresentMode::default", rename = "present-mode")]
+    pub present_mode: PresentMode,
+    #[serde(
+        default = "default_min_redraw_interval",
+        rename = "min-redraw-interval"
+    )]
+    pub min_redraw_interval: u64,
     #[serde(default = "default_width")]
     pub width: u16,
     #[serde(default = "default_height")]
     pub height: u16,
     #[serde(default = "default_cursor")]
     pub cursor: char,
+    #[serde(
+        default = "default_cursor_blink_interval",
+        rename = "cursor-blink-interval"
+    )]
+    pub cursor_blink_interval: u64,
+    #[serde(default = "default_bar_cursor_width", rename = "bar-cursor-width")]
+    pub bar_cursor_width: f32,
+    #[serde(default = "Option::default", rename = "underline-cursor-height")]
+    pub underline_cursor_height: Option<f32>,
     #[serde(default = "default_env_vars", rename = "env-vars")]
     pub env_vars: Vec<String>,
     #[serde(default = "default_option_as_alt", rename = "option-as-alt")]
     pub option_as_alt: String,
+    #[serde(
+        default = "default_backspace_sends_delete",
+        rename = "backspace-sends-delete"
+    )]
+    pub backspace_sends_delete: bool,
+    #[serde(default = "PasteNewline::default", rename = "paste-newline")]
+    pub paste_newline: PasteNewline,
+    #[serde(default = "AmbiguousWidth::default", rename = "ambiguous-width")]
+    pub ambiguous_width: AmbiguousWidth,
+    #[serde(default = "FormFeed::default", rename = "form-feed")]
+    pub form_feed: FormFeed,
+    #[serde(
+        default = "ControlPictureStyle::default",
+        rename = "control-picture-style"
+    )]
+    pub control_picture_style: ControlPictureStyle,
+    #[serde(default = "ResizeAnchor::default", rename = "resize-anchor")]
+    pub resize_anchor: ResizeAnchor,
+    #[serde(default = "Option::default", rename = "wrap-column")]
+    pub wrap_column: Option<usize>,
+    #[serde(default = "bool::default", rename = "smooth-resize")]
+    pub smooth_resize: bool,
+    #[serde(default = "Option::default", rename = "visual-bell")]
+    pub visual_bell: Option<VisualBell>,
     #[serde(default = "Style::default")]
     pub style: Style,
     #[serde(default = "Colors::default")]
     pub colors: Colors,
+    /// Colors of `style.light-theme`, loaded with the config.
+    #[serde(skip)]
+    pub light_colors: Option<Colors>,
+    /// Colors of `style.dark-theme`, loaded with the config.
+    #[serde(skip)]
+    pub dark_colors: Option<Colors>,
     #[serde(default = "Advanced::default")]
     pub advanced: Advanced,
     #[serde(default = "Developer::default")]
     pub developer: Developer,
+    #[serde(default = "Bindings::default")]
+    pub bindings: Bindings,
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -109,18 +346,7 @@ impl Config {
             let content = std::fs::read_to_string(path).unwrap();
             match toml::from_str::<Config>(&content) {
                 Ok(mut decoded) => {
-                    let theme = &decoded.style.theme;
-                    if theme.is_empty() {
-                        return Ok(decoded);
-                    }
-
-                    let path = format!("/tmp/{theme}.toml");
-                    if let Ok(loaded_theme) = Config::load_theme(&path) {
-                        decoded.colors = loaded_theme.colors;
-                    } else {
-                        warn!("failed to load theme: {}", theme);
-                    }
-
+                    decoded.load_themes("/tmp");
                     Ok(decoded)
                 }
                 Err(err_message) => Err(format!("error parsing: {:?}", err_message)),
@@ -130,6 +356,42 @@ impl Config {
         }
     }
 
+    /// Colors of the theme `name` in `themes_dir`, `None` when no theme is
+    /// set or it can't be loaded.
+    fn theme_colors(themes_dir: &str, name: &str) -> Option<Colors> {
+        if name.is_empty() {
+            return None;
+        }
+
+        let path = format!("{themes_dir}/{name}.toml");
+        match Config::load_theme(&path) {
+            Ok(loaded_theme) => Some(loaded_theme.colors),
+            Err(_) => {
+                warn!("failed to load theme: {}", name);
+                None
+            }
+        }
+    }
+
+    /// Replaces the colors by the ones of the configured themes.
+    fn load_themes(&mut self, themes_dir: &str) {
+        if let Some(colors) = Config::theme_colors(themes_dir, &self.style.theme) {
+            self.colors = colors;
+        }
+        self.light_colors = Config::theme_colors(themes_dir, &self.style.light_theme);
+        self.dark_colors = Config::theme_colors(themes_dir, &self.style.dark_theme);
+    }
+
+    /// Colors for `appearance`, the ones of `theme` (or `[colors]`) when no
+    /// theme is set for it.
+    pub fn colors_for(&self, appearance: Appearance) -> Colors {
+        let colors = match appearance {
+            Appearance::Light => self.light_colors,
+            Appearance::Dark => self.dark_colors,
+        };
+        colors.unwrap_or(self.colors)
+    }
+
     fn load_theme(path: &str) -> Result<Theme, String> {
         if std::path::Path::new(&path).exists() {
             let content = std::fs::read_to_string(path).unwrap();
@@ -149,18 +411,7 @@ impl Config {
             let content = std::fs::read_to_string(path).unwrap();
             match toml::from_str::<Config>(&content) {
                 Ok(mut decoded) => {
-                    let theme = &decoded.style.theme;
-                    if theme.is_empty() {
-                        return decoded;
-                    }
-
-                    let path = format!("{base_dir}/.config/rio/themes/{theme}.toml");
-                    if let Ok(loaded_theme) = Config::load_theme(&path) {
-                        decoded.colors = loaded_theme.colors;
-                    } else {
-                        warn!("failed to load theme: {}", theme);
-                    }
-
+                    decoded.load_themes(&format!("{base_dir}/.config/rio/themes"));
                     decoded
                 }
                 Err(err_message) => {
@@ -179,18 +430,31 @@ impl Default for Config {
         Config {
             env_vars: default_env_vars(),
             performance: Performance::default(),
+            present_mode: PresentMode::default(),
+            min_redraw_interval: default_min_redraw_interval(),
             width: default_width(),
             height: default_height(),
             cursor: default_cursor(),
+            cursor_blink_interval: default_cursor_blink_interval(),
+            bar_cursor_width: default_bar_cursor_width(),
+            underline_cursor_height: None,
             option_as_alt: default_option_as_alt(),
+            backspace_sends_delete: default_backspace_sends_delete(),
+            paste_newline: PasteNewline::default(),
+            ambiguous_width: AmbiguousWidth::default(),
+            form_feed: FormFeed::default(),
+            control_picture_style: ControlPictureStyle::default(),
+            resize_anchor: ResizeAnchor::default(),
+            wrap_column: None,
+            smooth_resize: false,
+            visual_bell: None,
             colors: Colors::default(),
-            style: Style {
-                font_size: default_font_size(),
-                theme: default_theme(),
-                font: default_font(),
-            },
+            light_colors: None,
+            dark_colors: None,
+            style: Style::default(),
             advanced: Advanced::default(),
             developer: Developer::default(),
+            bindings: Bindings::default(),
         }
     }
 }
@@ -242,23 +506,67 @@ mod tests {
         );
 
         assert_eq!(result.performance, Performance::default());
+        assert_eq!(
+            result.cursor_blink_interval,
+            default_cursor_blink_interval()
+        );
+        assert_eq!(result.bar_cursor_width, default_bar_cursor_width());
+        assert_eq!(result.underline_cursor_height, None);
+        assert_eq!(result.paste_newline, PasteNewline::Cr);
         assert_eq!(result.width, default_width());
         assert_eq!(result.height, default_height());
+        assert!(result.backspace_sends_delete);
+        assert_eq!(result.ambiguous_width, AmbiguousWidth::Narrow);
+        assert_eq!(result.form_feed, FormFeed::LineFeed);
+        assert_eq!(result.control_picture_style, ControlPictureStyle::Hidden);
+        assert_eq!(result.resize_anchor, ResizeAnchor::Top);
+        assert_eq!(result.wrap_column, None);
+        assert_eq!(result.present_mode, PresentMode::Vsync);
+        assert_eq!(result.min_redraw_interval, default_min_redraw_interval());
+        assert!(!result.smooth_resize);
+        assert_eq!(result.visual_bell, None);
 
         // Style
         assert_eq!(result.style.font, default_font());
         assert_eq!(result.style.font_size, default_font_size());
         assert_eq!(result.style.theme, default_theme());
+        assert_eq!(result.style.baseline_offset, None);
+        assert!(result.style.snap_to_pixel);
+        assert_eq!(result.style.wrap_indicator, None);
+        assert_eq!(result.style.tab_leader, None);
+        assert_eq!(result.style.indent_guides, None);
+        assert_eq!(result.style.missing_glyph_replacement, None);
+        assert!(!result.style.fill_cursor_line);
+        assert_eq!(result.style.background_bleed, default_background_bleed());
+        assert_eq!(result.style.color_depth, ColorDepth::TrueColor);
+        assert!(result.style.force_text_font_ranges.is_empty());
+        assert!(result.style.font_fallbacks.is_empty());
+        assert!(result.style.font_features.is_empty());
+        assert!(!result.style.search_spotlight);
+        assert!(result.style.synthesize_bold);
+        assert_eq!(result.style.bold_strength, default_bold_strength());
+        assert!(result.style.synthesize_italic);
+        assert_eq!(result.style.italic_slant, default_italic_slant());
 
         // Colors
         assert_eq!(result.colors, Colors::default());
 
         // Advanced
         assert!(!result.advanced.disable_render_when_unfocused);
+        assert_eq!(
+            result.advanced.max_string_length,
+            default_max_string_length()
+        );
+        assert_eq!(result.advanced.max_line_length, default_max_line_length());
+        assert_eq!(result.advanced.long_lines, LongLines::Wrap);
 
         // Developer
         assert_eq!(result.developer.log_level, default_log_level());
         assert!(!result.developer.enable_fps_counter);
+        assert_eq!(result.developer.ready_marker, None);
+
+        // Bindings
+        assert_eq!(result.bindings, Bindings::default());
     }
 
     #[test]
@@ -341,6 +649,135 @@ mod tests {
         assert_eq!(result.colors.cursor, colors::defaults::cursor());
     }
 
+    #[test]
+    fn test_change_paste_newline() {
+        let result = create_temporary_config(
+            "change-paste-newline",
+            r#"
+            paste-newline = "Keep"
+        "#,
+        );
+
+        assert_eq!(result.paste_newline, PasteNewline::Keep);
+    }
+
+    #[test]
+    fn test_change_form_feed() {
+        let result = create_temporary_config(
+            "change-form-feed",
+            r#"
+            form-feed = "Clear"
+        "#,
+        );
+
+        assert_eq!(result.form_feed, FormFeed::Clear);
+    }
+
+    #[test]
+    fn test_change_control_picture_style() {
+        let result = create_temporary_config(
+            "change-control-picture-style",
+            r#"
+            control-picture-style = "ControlPictures"
+        "#,
+        );
+
+        assert_eq!(
+            result.control_picture_style,
+            ControlPictureStyle::ControlPictures
+        );
+    }
+
+    #[test]
+    fn test_change_resize_anchor() {
+        let result = create_temporary_config(
+            "change-resize-anchor",
+            r#"
+            resize-anchor = "Cursor"
+        "#,
+        );
+
+        assert_eq!(result.resize_anchor, ResizeAnchor::Cursor);
+    }
+
+    #[test]
+    fn test_wrap_column() {
+        let result = create_temporary_config(
+            "wrap-column",
+            r#"
+            wrap-column = 80
+        "#,
+        );
+
+        assert_eq!(result.wrap_column, Some(80));
+    }
+
+    #[test]
+    fn test_change_present_mode() {
+        let result = create_temporary_config(
+            "change-present-mode",
+            r#"
+            present-mode = "Mailbox"
+        "#,
+        );
+
+        assert_eq!(result.present_mode, PresentMode::Mailbox);
+    }
+
+    #[test]
+    fn test_change_min_redraw_interval() {
+        let result = create_temporary_config(
+            "change-min-redraw-interval",
+            r#"
+            min-redraw-interval = 33
+        "#,
+        );
+
+        assert_eq!(result.min_redraw_interval, 33);
+    }
+
+    #[test]
+    fn test_enable_smooth_resize() {
+        let result = create_temporary_config(
+            "enable-smooth-resize",
+            r#"
+            smooth-resize = true
+        "#,
+        );
+
+        assert!(result.smooth_resize);
+    }
+
+    #[test]
+    fn test_change_ambiguous_width() {
+        let result = create_temporary_config(
+            "change-ambiguous-width",
+            r#"
+            ambiguous-width = "Wide"
+        "#,
+        );
+
+        assert_eq!(result.ambiguous_width, AmbiguousWidth::Wide);
+    }
+
+    #[test]
+    fn test_enable_visual_bell() {
+        let result = create_temporary_config(
+            "enable-visual-bell",
+            r#"
+            [visual-bell]
+            color = '#FF0000'
+            duration = 300
+        "#,
+        );
+
+        let visual_bell = result.visual_bell.unwrap();
+        assert_eq!(visual_bell.color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(visual_bell.opacity, default_visual_bell_opacity());
+        assert_eq!(visual_bell.duration, 300);
+        assert!(visual_bell.fade);
+    }
+
     #[test]
     fn test_change_config_perfomance() {
         let result = create_temporary_config(
@@ -389,6 +826,34 @@ mod tests {
         assert_eq!(result.colors.cursor, colors::defaults::cursor());
     }
 
+    #[test]
+    fn test_change_bar_cursor_width() {
+        let result = create_temporary_config(
+            "change-bar-cursor-width",
+            r#"
+            cursor = '|'
+            bar-cursor-width = 3.5
+        "#,
+        );
+
+        assert_eq!(result.cursor, '|');
+        assert_eq!(result.bar_cursor_width, 3.5);
+    }
+
+    #[test]
+    fn test_change_underline_cursor_height() {
+        let result = create_temporary_config(
+            "change-underline-cursor-height",
+            r#"
+            cursor = '_'
+            underline-cursor-height = 3
+        "#,
+        );
+
+        assert_eq!(result.cursor, '_');
+        assert_eq!(result.underline_cursor_height, Some(3.0));
+    }
+
     #[test]
     fn test_change_config_cursor() {
         let result = create_temporary_config(
@@ -413,6 +878,261 @@ mod tests {
         assert_eq!(result.colors.cursor, colors::defaults::cursor());
     }
 
+    #[test]
+    fn test_change_baseline_offset() {
+        let result = create_temporary_config(
+            "change-baseline-offset",
+            r#"
+            [style]
+            baseline-offset = -2.5
+        "#,
+        );
+
+        assert_eq!(result.style.baseline_offset, Some(-2.5));
+    }
+
+    #[test]
+    fn test_disable_snap_to_pixel() {
+        let result = create_temporary_config(
+            "disable-snap-to-pixel",
+            r#"
+            [style]
+            snap-to-pixel = false
+        "#,
+        );
+
+        assert!(!result.style.snap_to_pixel);
+    }
+
+    #[test]
+    fn test_change_wrap_indicator() {
+        let result = create_temporary_config(
+            "change-wrap-indicator",
+            r#"
+            [style]
+            wrap-indicator = "↵"
+        "#,
+        );
+
+        assert_eq!(result.style.wrap_indicator, Some('↵'));
+    }
+
+    #[test]
+    fn test_change_tab_leader() {
+        let result = create_temporary_config(
+            "change-tab-leader",
+            r#"
+            [style]
+            tab-leader = "."
+        "#,
+        );
+
+        assert_eq!(result.style.tab_leader, Some('.'));
+    }
+
+    #[test]
+    fn test_enable_indent_guides() {
+        let result = create_temporary_config(
+            "enable-indent-guides",
+            r#"
+            [style.indent-guides]
+            color = '#FF0000'
+        "#,
+        );
+
+        let indent_guides = result.style.indent_guides.unwrap();
+        assert_eq!(indent_guides.interval, default_indent_guides_interval());
+        assert_eq!(indent_guides.color, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_change_missing_glyph_replacement() {
+        let result = create_temporary_config(
+            "change-missing-glyph-replacement",
+            r#"
+            [style]
+            missing-glyph-replacement = "␦"
+        "#,
+        );
+
+        assert_eq!(result.style.missing_glyph_replacement, Some('␦'));
+    }
+
+    #[test]
+    fn test_enable_fill_cursor_line() {
+        let result = create_temporary_config(
+            "enable-fill-cursor-line",
+            r#"
+            [style]
+            fill-cursor-line = true
+        "#,
+        );
+
+        assert!(result.style.fill_cursor_line);
+    }
+
+    #[test]
+    fn test_disable_background_bleed() {
+        let result = create_temporary_config(
+            "disable-background-bleed",
+            r#"
+            [style]
+            background-bleed = 0.0
+        "#,
+        );
+
+        assert_eq!(result.style.background_bleed, 0.0);
+    }
+
+    #[test]
+    fn test_font_fallbacks() {
+        let result = create_temporary_config(
+            "font-fallbacks",
+            r#"
+            [style]
+            font-fallbacks = ["Noto Sans CJK JP", "Noto Sans Arabic"]
+        "#,
+        );
+
+        assert_eq!(
+            result.style.font_fallbacks,
+            vec![
+                String::from("Noto Sans CJK JP"),
+                String::from("Noto Sans Arabic")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_font_features() {
+        let result = create_temporary_config(
+            "font-features",
+            r#"
+            [style]
+            font-features = ["zero", "ss02"]
+        "#,
+        );
+
+        assert_eq!(result.style.font_features, vec!["zero", "ss02"]);
+    }
+
+    #[test]
+    fn test_force_text_font_ranges() {
+        let result = create_temporary_config(
+            "force-text-font-ranges",
+            r#"
+            [style]
+            force-text-font-ranges = [[0x2190, 0x21FF], [0x2500, 0x257F]]
+        "#,
+        );
+
+        assert_eq!(
+            result.style.force_text_font_ranges,
+            vec![[0x2190, 0x21FF], [0x2500, 0x257F]]
+        );
+    }
+
+    #[test]
+    fn test_change_color_depth() {
+        let result = create_temporary_config(
+            "change-color-depth",
+            r#"
+            [style]
+            color-depth = "Ansi16"
+        "#,
+        );
+
+        assert_eq!(result.style.color_depth, ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_disable_synthesize_bold() {
+        let result = create_temporary_config(
+            "disable-synthesize-bold",
+            r#"
+            [style]
+            synthesize-bold = false
+        "#,
+        );
+
+        assert!(!result.style.synthesize_bold);
+    }
+
+    #[test]
+    fn test_change_bold_strength() {
+        let result = create_temporary_config(
+            "change-bold-strength",
+            r#"
+            [style]
+            bold-strength = 0.03
+        "#,
+        );
+
+        assert_eq!(result.style.bold_strength, 0.03);
+    }
+
+    #[test]
+    fn test_change_italic_synthesis() {
+        let result = create_temporary_config(
+            "change-italic-synthesis",
+            r#"
+            [style]
+            synthesize-italic = false
+            italic-slant = 14.0
+        "#,
+        );
+
+        assert!(!result.style.synthesize_italic);
+        assert_eq!(result.style.italic_slant, 14.0);
+    }
+
+    #[test]
+    fn test_enable_search_spotlight() {
+        let result = create_temporary_config(
+            "enable-search-spotlight",
+            r#"
+            [style]
+            search-spotlight = true
+        "#,
+        );
+
+        assert!(result.style.search_spotlight);
+    }
+
+    #[test]
+    fn test_bindings() {
+        let result = create_temporary_config(
+            "bindings",
+            r#"
+            [bindings]
+            keys = [
+                { key = "plus", with = "control", action = "IncreaseFontSize" },
+                { key = "q", with = "super | shift", esc = "\u001b" },
+            ]
+        "#,
+        );
+
+        assert_eq!(result.bindings.keys.len(), 2);
+        assert_eq!(result.bindings.keys[0].key, "plus");
+        assert_eq!(result.bindings.keys[0].with, "control");
+        assert_eq!(result.bindings.keys[0].action, "IncreaseFontSize");
+        assert_eq!(result.bindings.keys[1].with, "super | shift");
+        assert_eq!(result.bindings.keys[1].action, "");
+        assert_eq!(result.bindings.keys[1].esc, "\u{1b}");
+    }
+
+    #[test]
+    fn test_change_backspace_sends_delete() {
+        let result = create_temporary_config(
+            "change-backspace-sends-delete",
+            r#"
+            backspace-sends-delete = false
+        "#,
+        );
+
+        assert!(!result.backspace_sends_delete);
+    }
+
     #[test]
     fn test_change_option_as_alt() {
         let result = create_temporary_config(
@@ -541,6 +1261,52 @@ mod tests {
         assert_eq!(result.colors.background.0, hex_to_color_arr("#2B3E50"));
     }
 
+    #[test]
+    fn test_light_and_dark_themes() {
+        create_temporary_theme(
+            "rio-test-light",
+            r#"
+            [colors]
+            background       = '#FFFFFF'
+            foreground       = '#000000'
+        "#,
+        );
+        create_temporary_theme(
+            "rio-test-dark",
+            r#"
+            [colors]
+            background       = '#000000'
+            foreground       = '#FFFFFF'
+        "#,
+        );
+
+        let result = create_temporary_config(
+            "light-and-dark-themes",
+            r#"
+            [style]
+            light-theme = "rio-test-light"
+            dark-theme = "rio-test-dark"
+        "#,
+        );
+
+        assert_eq!(result.colors.background, colors::defaults::background());
+        let light = result.colors_for(Appearance::Light);
+        assert_eq!(light.background.0, hex_to_color_arr("#FFFFFF"));
+        assert_eq!(light.foreground, hex_to_color_arr("#000000"));
+        let dark = result.colors_for(Appearance::Dark);
+        assert_eq!(dark.background.0, hex_to_color_arr("#000000"));
+
+        // Without a theme for an appearance the usual colors are kept.
+        let result = create_temporary_config(
+            "light-theme-only",
+            r#"
+            [style]
+            light-theme = "rio-test-light"
+        "#,
+        );
+        assert_eq!(result.colors_for(Appearance::Dark), result.colors);
+    }
+
     #[test]
     fn test_change_one_color() {
         let result = create_temporary_config(
@@ -647,6 +1413,9 @@ mod tests {
 
             [advanced]
             disable-render-when-unfocused = true
+            max-string-length = 4096
+            max-line-length = 2000
+            long-lines = "Truncate"
         "#,
         );
 
@@ -655,6 +1424,9 @@ mod tests {
         assert_eq!(result.height, default_height());
         // Advanced
         assert!(result.advanced.disable_render_when_unfocused);
+        assert_eq!(result.advanced.max_string_length, 4096);
+        assert_eq!(result.advanced.max_line_length, 2000);
+        assert_eq!(result.advanced.long_lines, LongLines::Truncate);
 
         // Colors
         assert_eq!(result.colors.background, colors::defaults::background());
@@ -673,6 +1445,7 @@ mod tests {
             [developer]
             enable-fps-counter = true
             log-level = "INFO"
+            ready-marker = "\u001b[0n"
         "#,
         );
 
@@ -682,6 +1455,7 @@ mod tests {
         // Developer
         assert_eq!(result.developer.log_level, String::from("INFO"));
         assert!(result.developer.enable_fps_counter);
+        assert_eq!(result.developer.ready_marker, Some(String::from("\x1b[0n")));
 
         // Colors
         assert_eq!(result.colors.background, colors::defaults::background());
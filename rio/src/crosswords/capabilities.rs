@@ -0,0 +1,71 @@
+// XTGETTCAP replies
+// https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Device-Control-functions
+
+/// Value of a terminfo capability.
+pub enum Capability {
+    Boolean,
+    String(&'static str),
+}
+
+/// Capabilities reported to applications, named as in terminfo/termcap. The
+/// backspace key sends DEL when `backspace_sends_delete`, BS otherwise.
+pub fn capability(name: &str, backspace_sends_delete: bool) -> Option<Capability> {
+    let value = match name {
+        "Tc" | "RGB" => return Some(Capability::Boolean),
+        "Co" | "colors" => "256",
+        "kbs" if backspace_sends_delete => "\x7f",
+        "kbs" => "\x08",
+        "kcuu1" | "ku" => "\x1bOA",
+        "kcud1" | "kd" => "\x1bOB",
+        "kcuf1" | "kr" => "\x1bOC",
+        "kcub1" | "kl" => "\x1bOD",
+        "khome" | "kh" => "\x1bOH",
+        "kend" | "@7" => "\x1bOF",
+        "kich1" | "kI" => "\x1b[2~",
+        "kdch1" | "kD" => "\x1b[3~",
+        "kpp" | "kP" => "\x1b[5~",
+        "knp" | "kN" => "\x1b[6~",
+        _ => return None,
+    };
+
+    Some(Capability::String(value))
+}
+
+/// Reply for a capability query, `name` is written back hex encoded.
+pub fn xtgettcap_reply(name: &str, backspace_sends_delete: bool) -> String {
+    let hex_name = hex_encode(name.as_bytes());
+    match capability(name, backspace_sends_delete) {
+        Some(Capability::String(value)) => {
+            format!(
+                "\x1bP1+r{}={}\x1b\\",
+                hex_name,
+                hex_encode(value.as_bytes())
+            )
+        }
+        Some(Capability::Boolean) => format!("\x1bP1+r{hex_name}\x1b\\"),
+        None => format!("\x1bP0+r{hex_name}\x1b\\"),
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_xtgettcap_reply() {
+        // "Co" => "256"
+        assert_eq!(xtgettcap_reply("Co", true), "\x1bP1+r436F=323536\x1b\\");
+        assert_eq!(xtgettcap_reply("Tc", true), "\x1bP1+r5463\x1b\\");
+        assert_eq!(xtgettcap_reply("rio", true), "\x1bP0+r72696F\x1b\\");
+    }
+
+    #[test]
+    fn test_backspace_follows_the_config() {
+        assert_eq!(xtgettcap_reply("kbs", true), "\x1bP1+r6B6273=7F\x1b\\");
+        assert_eq!(xtgettcap_reply("kbs", false), "\x1bP1+r6B6273=08\x1b\\");
+    }
+}
@@ -0,0 +1,140 @@
+// Grid contents as text with SGR escapes
+// https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Functions-using-CSI-_-ordered-by-the-final-character_s_
+
+use crate::crosswords::grid::row::Row;
+use crate::crosswords::grid::GridSquare;
+use crate::crosswords::square::{Flags, Square};
+use colors::{AnsiColor, NamedColor};
+use std::fmt::Write;
+
+/// Attributes reproduced by the exported escapes.
+const STYLE_FLAGS: Flags = Flags::BOLD
+    .union(Flags::DIM)
+    .union(Flags::ITALIC)
+    .union(Flags::ALL_UNDERLINES)
+    .union(Flags::INVERSE)
+    .union(Flags::HIDDEN)
+    .union(Flags::STRIKEOUT);
+
+/// Style of a square, as written by SGR.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Style {
+    fg: AnsiColor,
+    bg: AnsiColor,
+    flags: Flags,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            fg: AnsiColor::Named(NamedColor::Foreground),
+            bg: AnsiColor::Named(NamedColor::Background),
+            flags: Flags::empty(),
+        }
+    }
+}
+
+impl Style {
+    fn of(square: &Square) -> Style {
+        Style {
+            fg: square.fg,
+            bg: square.bg,
+            flags: square.flags & STYLE_FLAGS,
+        }
+    }
+
+    /// SGR sequence that resets the pen and sets this style.
+    fn sgr(&self) -> String {
+        let mut sgr = String::from("\x1b[0");
+        let attributes = [
+            (Flags::BOLD, "1"),
+            (Flags::DIM, "2"),
+            (Flags::ITALIC, "3"),
+            (Flags::UNDERLINE, "4"),
+            (Flags::DOUBLE_UNDERLINE, "4:2"),
+            (Flags::UNDERCURL, "4:3"),
+            (Flags::DOTTED_UNDERLINE, "4:4"),
+            (Flags::DASHED_UNDERLINE, "4:5"),
+            (Flags::INVERSE, "7"),
+            (Flags::HIDDEN, "8"),
+            (Flags::STRIKEOUT, "9"),
+        ];
+        for (flag, attribute) in attributes {
+            if self.flags.contains(flag) {
+                sgr.push(';');
+                sgr.push_str(attribute);
+            }
+        }
+
+        push_color(&mut sgr, self.fg, 30);
+        push_color(&mut sgr, self.bg, 40);
+        sgr.push('m');
+        sgr
+    }
+}
+
+/// Appends `color` as a foreground (`base` 30) or background (`base` 40)
+/// attribute, default colors are covered by the reset.
+fn push_color(sgr: &mut String, color: AnsiColor, base: u8) {
+    let _ = match color {
+        AnsiColor::Named(named) => match named as usize {
+            index @ 0..=7 => write!(sgr, ";{}", base as usize + index),
+            index @ 8..=15 => write!(sgr, ";{}", base as usize + 60 + index - 8),
+            _ => Ok(()),
+        },
+        AnsiColor::Indexed(index) => write!(sgr, ";{};5;{}", base + 8, index),
+        AnsiColor::Spec(rgb) => {
+            write!(sgr, ";{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b)
+        }
+    };
+}
+
+/// The row continues on the next one (soft wrap).
+#[inline]
+pub fn is_wrapped(row: &Row<Square>) -> bool {
+    row.is_wrapped()
+}
+
+/// Appends the squares of `row` to `text`, escapes are only written when the
+/// style changes from `style`. Trailing blank squares are left out.
+pub fn push_row(text: &mut String, row: &Row<Square>, style: &mut Style) {
+    let length = if let Some(column) = row.wrap_column() {
+        column.0 + 1
+    } else {
+        row[..]
+            .iter()
+            .rposition(|square| !square.is_empty())
+            .map_or(0, |index| index + 1)
+    };
+
+    for square in &row[..][..length] {
+        if square
+            .flags
+            .intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+        {
+            continue;
+        }
+
+        let square_style = Style::of(square);
+        if square_style != *style {
+            text.push_str(&square_style.sgr());
+            *style = square_style;
+        }
+
+        // Tabs are already expanded in the grid.
+        text.push(if square.c == '\t' { ' ' } else { square.c });
+        for c in square.zerowidth().into_iter().flatten() {
+            text.push(*c);
+        }
+    }
+}
+
+/// Ends a line, the style is reset first so the new line isn't painted with
+/// the current background.
+pub fn push_newline(text: &mut String, style: &mut Style) {
+    if *style != Style::default() {
+        text.push_str("\x1b[0m");
+        *style = Style::default();
+    }
+    text.push_str("\r\n");
+}
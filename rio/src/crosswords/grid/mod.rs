@@ -18,11 +18,8 @@ use storage::Storage;
 #[derive(Debug, Copy, Clone)]
 pub enum Scroll {
     Delta(i32),
-    #[allow(unused)]
     PageUp,
-    #[allow(unused)]
     PageDown,
-    #[allow(unused)]
     Top,
     Bottom,
 }
@@ -187,8 +184,12 @@ impl<T: GridSquare + Default + PartialEq + Clone> Grid<T> {
         T: ResetDiscriminant<D>,
         D: PartialEq,
     {
-        // When rotating the entire region with fixed lines at the top, just reset everything.
-        if region.end - region.start <= positions && region.start != 0 {
+        // Only lines scrolled off a full screen region go to the scrollback.
+        let is_full_screen =
+            region.start == 0 && region.end.0 as usize == self.screen_lines();
+
+        // When rotating the entire region with fixed lines around it, just reset everything.
+        if region.end - region.start <= positions && !is_full_screen {
             for i in (region.start.0..region.end.0).map(Line::from) {
                 self.raw[i].reset(&self.cursor.template);
             }
@@ -196,6 +197,21 @@ impl<T: GridSquare + Default + PartialEq + Clone> Grid<T> {
             return;
         }
 
+        if !is_full_screen {
+            // Subregion rotation.
+            let range = region.start.0..(region.end - positions).0;
+            for line in range.map(Line::from) {
+                self.raw.swap(line, line + positions);
+            }
+
+            let range = (region.end - positions).0..region.end.0;
+            for line in range.map(Line::from) {
+                self.raw[line].reset(&self.cursor.template);
+            }
+
+            return;
+        }
+
         // Update display offset when not pinned to active area.
         if self.display_offset != 0 {
             self.display_offset =
@@ -205,18 +221,6 @@ impl<T: GridSquare + Default + PartialEq + Clone> Grid<T> {
         // Create scrollback for the new lines.
         self.increase_scroll_limit(positions);
 
-        // Swap the lines fixed at the top to their target positions after rotation.
-        //
-        // Since we've made sure that the rotation will never rotate away the entire region, we
-        // know that the position of the fixed lines before the rotation must already be
-        // visible.
-        //
-        // We need to start from the bottom, to make sure the fixed lines aren't swapped with each
-        // other.
-        for i in (0..region.start.0).rev().map(Line::from) {
-            self.raw.swap(i, i + positions);
-        }
-
         // Rotate the entire line buffer upward.
         self.raw.rotate(-(positions as isize));
 
@@ -225,11 +229,6 @@ impl<T: GridSquare + Default + PartialEq + Clone> Grid<T> {
         for i in ((screen_lines - positions)..screen_lines).map(Line::from) {
             self.raw[i].reset(&self.cursor.template);
         }
-
-        // Swap the fixed lines at the bottom back into position.
-        for i in (region.end.0..(screen_lines as i32)).rev().map(Line::from) {
-            self.raw.swap(i, i - positions);
-        }
     }
 
     pub fn clear_viewport<D>(&mut self)
@@ -32,6 +32,33 @@ impl<T: GridSquare + Default + PartialEq + Clone> Grid<T> {
         self.cursor.template = template;
     }
 
+    /// Like `resize`, for rows that wrap at `wrap_column` when it's narrower
+    /// than the grid. They are reflowed from the old to the new wrap column,
+    /// the squares past it stay empty.
+    pub fn resize_wrapped<D>(
+        &mut self,
+        reflow: bool,
+        lines: usize,
+        columns: usize,
+        wrap_column: Option<usize>,
+    ) where
+        T: ResetDiscriminant<D>,
+        D: PartialEq,
+    {
+        let wraps_at =
+            |columns: usize| wrap_column.map_or(columns, |wrap| wrap.min(columns));
+        let (old_wrap, new_wrap) = (wraps_at(self.columns), wraps_at(columns));
+        if !reflow || (old_wrap == self.columns && new_wrap == columns) {
+            return self.resize(reflow, lines, columns);
+        }
+
+        // Without the empty squares past the wrap column, rows wrap at their
+        // last column as reflow expects.
+        self.resize(true, lines, old_wrap);
+        self.resize(true, lines, new_wrap);
+        self.resize(false, lines, columns);
+    }
+
     /// Add lines to the visible area.
     ///
     /// Rio keeps the cursor at the bottom of the terminal as long as there
@@ -294,10 +321,27 @@ impl<T: GridSquare + Default + PartialEq + Clone> Grid<T> {
                     }
                 };
 
-                // Insert spacer if a wide char would be wrapped into the last column.
-                if row.len() >= columns
+                // A single column can't hold a wide char, it is kept in one
+                // square and its spacer is dropped.
+                if columns < 2 && row[Column(0)].flags().contains(Flags::WIDE_CHAR) {
+                    row[Column(0)].flags_mut().remove(Flags::WIDE_CHAR);
+                    if wrapped
+                        .first()
+                        .map_or(false, |c| c.flags().contains(Flags::WIDE_CHAR_SPACER))
+                    {
+                        wrapped.remove(0);
+
+                        // Keep the cursor over the same square.
+                        let cursor_buffer_line =
+                            self.lines - self.cursor.pos.row.0 as usize - 1;
+                        if i == cursor_buffer_line && self.cursor.pos.col > columns {
+                            self.cursor.pos.col -= 1;
+                        }
+                    }
+                } else if row.len() >= columns
                     && row[Column(columns - 1)].flags().contains(Flags::WIDE_CHAR)
                 {
+                    // Insert spacer if a wide char would be wrapped into the last column.
                     let mut spacer = T::default();
                     spacer.flags_mut().insert(Flags::LEADING_WIDE_CHAR_SPACER);
 
@@ -1,4 +1,5 @@
 use crate::crosswords::grid::GridSquare;
+use crate::crosswords::square::Flags;
 use crate::crosswords::square::ResetDiscriminant;
 use crate::crosswords::Column;
 use core::cmp::min;
@@ -7,6 +8,14 @@ use std::ops::{Index, IndexMut, Range, RangeFrom, RangeFull, RangeTo, RangeToInc
 use std::{ptr, slice};
 
 /// A row in the grid.
+///
+/// Blank cells are stored like any other: the renderer, selection and search
+/// take references into `inner`, which a run-length encoded row couldn't give
+/// without expanding. On a mostly blank 200x50 grid (see the
+/// `blank_grid_storage` benchmark) runs would save ~230 KB and ~2 µs of a
+/// ~10 µs pass over the cells per frame, too little to give up references
+/// into the row. Resets and clears of sparse rows are bounded by `occ`
+/// instead.
 #[derive(Default, Clone, Debug)]
 pub struct Row<T> {
     pub inner: Vec<T>,
@@ -166,6 +175,25 @@ impl<T> Row<T> {
     }
 }
 
+impl<T: GridSquare> Row<T> {
+    /// Column the row continues on the next one from (soft wrap). It's the
+    /// last column unless lines wrap at a narrower one, the squares past it
+    /// are left empty.
+    pub fn wrap_column(&self) -> Option<Column> {
+        let index = self.inner.iter().rposition(|square| !square.is_empty())?;
+        self.inner[index]
+            .flags()
+            .contains(Flags::WRAPLINE)
+            .then_some(Column(index))
+    }
+
+    /// The row continues on the next one (soft wrap).
+    #[inline]
+    pub fn is_wrapped(&self) -> bool {
+        self.wrap_column().is_some()
+    }
+}
+
 impl<'a, T> IntoIterator for &'a Row<T> {
     type IntoIter = slice::Iter<'a, T>;
     type Item = &'a T;
@@ -362,3 +362,71 @@ fn wrap_cell(c: char) -> Square {
     cell.flags.insert(Flags::WRAPLINE);
     cell
 }
+
+/// Compares the dense rows with a run-length encoding of their blank cells
+/// on a mostly blank 200x50 grid, the numbers are in the `Row` docs.
+///
+/// cargo test --release -p rio blank_grid_storage -- --ignored --nocapture
+#[test]
+#[ignore]
+fn blank_grid_storage() {
+    use std::time::Instant;
+
+    const ITERATIONS: u32 = 1000;
+    let (columns, lines) = (200, 50);
+    let mut grid = Grid::<Square>::new(lines, columns, 0);
+    // A few short lines of output, like a shell prompt, on a blank screen.
+    for line in (0..lines).step_by(5) {
+        for (column, c) in "user@host:~/rio$ ls".chars().enumerate() {
+            grid[Line(line as i32)][Column(column)] = cell(c);
+        }
+    }
+
+    // Runs of equal cells of each row.
+    let runs: Vec<Vec<(Square, usize)>> = (0..lines)
+        .map(|line| {
+            let mut runs: Vec<(Square, usize)> = vec![];
+            for square in &grid[Line(line as i32)][..] {
+                match runs.last_mut() {
+                    Some((last, count)) if last == square => *count += 1,
+                    _ => runs.push((square.clone(), 1)),
+                }
+            }
+            runs
+        })
+        .collect();
+
+    let dense_bytes = lines * columns * std::mem::size_of::<Square>();
+    let run_bytes =
+        runs.iter().map(Vec::len).sum::<usize>() * std::mem::size_of::<(Square, usize)>();
+
+    // The renderer draws every column, blank ones included (backgrounds,
+    // cursor), so runs are expanded back into cells.
+    let start = Instant::now();
+    let mut drawn = 0;
+    for _ in 0..ITERATIONS {
+        for line in 0..lines {
+            for square in &grid[Line(line as i32)][..] {
+                drawn += std::hint::black_box(square).c as usize;
+            }
+        }
+    }
+    let dense = start.elapsed() / ITERATIONS;
+
+    let start = Instant::now();
+    let mut expanded = 0;
+    for _ in 0..ITERATIONS {
+        for row in &runs {
+            for (square, count) in row {
+                for _ in 0..*count {
+                    expanded += std::hint::black_box(square).c as usize;
+                }
+            }
+        }
+    }
+    let run_length = start.elapsed() / ITERATIONS;
+    assert_eq!(drawn, expanded);
+
+    println!("dense: {dense_bytes} bytes, {dense:?} per pass");
+    println!("run-length: {run_bytes} bytes, {run_length:?} per pass");
+}
@@ -13,6 +13,8 @@
 */
 
 pub mod attr;
+mod capabilities;
+mod export;
 pub mod grid;
 pub mod pos;
 pub mod square;
@@ -32,6 +34,8 @@ use bitflags::bitflags;
 use colors::term::List;
 use colors::term::TermColors;
 use colors::{AnsiColor, ColorRgb};
+use config::defaults::default_max_line_length;
+use config::{AmbiguousWidth, ControlPictureStyle, FormFeed, LongLines, ResizeAnchor};
 use grid::row::Row;
 use log::{debug, info, warn};
 use pos::{
@@ -41,8 +45,10 @@ use square::{LineLength, Square};
 use std::mem;
 use std::ops::{Index, IndexMut, Range};
 use std::option::Option;
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::sync::Arc;
+use std::time::Duration;
 use unicode_width::UnicodeWidthChar;
 use vi_mode::{ViModeCursor, ViMotion};
 
@@ -284,8 +290,27 @@ where
     #[allow(dead_code)]
     colors: List,
     title: Option<String>,
+    custom_title: Option<String>,
+    current_directory: Option<PathBuf>,
+    wrap_column: Option<usize>,
+    ambiguous_width: AmbiguousWidth,
+    resize_anchor: ResizeAnchor,
+    form_feed: FormFeed,
+    backspace_sends_delete: bool,
+    control_picture_style: ControlPictureStyle,
+    max_line_length: usize,
+    long_lines: LongLines,
+    /// Columns of the current line in the rows above the cursor.
+    line_length: usize,
+    line_truncated: bool,
+    /// The current line was already broken past its maximum length.
+    line_broken: bool,
     damage: TermDamageState,
     pub cursor_shape: CursorShape,
+    cursor_blink_interval: Option<Duration>,
+    default_cursor_blink_interval: Duration,
+    caret: Option<(Pos, f32)>,
+    pub is_focused: bool,
 }
 
 impl<U: EventListener> Crosswords<U> {
@@ -309,6 +334,19 @@ impl<U: EventListener> Crosswords<U> {
             event_proxy,
             colors,
             title: None,
+            custom_title: None,
+            current_directory: None,
+            wrap_column: None,
+            ambiguous_width: AmbiguousWidth::default(),
+            resize_anchor: ResizeAnchor::default(),
+            form_feed: FormFeed::default(),
+            backspace_sends_delete: true,
+            control_picture_style: ControlPictureStyle::default(),
+            max_line_length: default_max_line_length(),
+            long_lines: LongLines::default(),
+            line_length: 0,
+            line_truncated: false,
+            line_broken: false,
             tabs: TabStops::new(cols),
             mode: Mode::SHOW_CURSOR
                 | Mode::LINE_WRAP
@@ -316,6 +354,10 @@ impl<U: EventListener> Crosswords<U> {
                 | Mode::URGENCY_HINTS,
             damage: TermDamageState::new(cols, rows),
             cursor_shape: CursorShape::Block,
+            cursor_blink_interval: None,
+            default_cursor_blink_interval: Duration::from_millis(500),
+            caret: None,
+            is_focused: true,
         }
     }
 
@@ -377,9 +419,26 @@ impl<U: EventListener> Crosswords<U> {
         delta = std::cmp::min(std::cmp::max(delta, min_delta), history_size as i32);
         self.vi_mode_cursor.pos.row += delta;
 
+        let display_offset = self.grid.display_offset() as i32;
+        let cursor_row = self.grid.cursor.pos.row.0 + display_offset;
+
         let is_alt = self.mode.contains(Mode::ALT_SCREEN);
-        self.grid.resize(!is_alt, num_lines, num_cols);
-        self.inactive_grid.resize(is_alt, num_lines, num_cols);
+        self.grid
+            .resize_wrapped(!is_alt, num_lines, num_cols, self.wrap_column);
+        self.inactive_grid
+            .resize_wrapped(is_alt, num_lines, num_cols, self.wrap_column);
+
+        // Move the viewport after reflow, history limits how far it can go.
+        match self.resize_anchor {
+            ResizeAnchor::Top => (),
+            ResizeAnchor::Cursor => {
+                let row = std::cmp::min(cursor_row, num_lines as i32 - 1);
+                let offset = row - self.grid.cursor.pos.row.0;
+                let delta = offset - self.grid.display_offset() as i32;
+                self.grid.scroll_display(Scroll::Delta(delta));
+            }
+            ResizeAnchor::Bottom => self.grid.scroll_display(Scroll::Bottom),
+        }
 
         // Invalidate selection and tabs only when necessary.
         if old_cols != num_cols {
@@ -409,6 +468,26 @@ impl<U: EventListener> Crosswords<U> {
         self.damage.resize(num_cols, num_lines);
     }
 
+    /// Updates the focus state, returns the focus report to write back to the
+    /// pty when focus reporting (?1004) is enabled and the focus has changed.
+    pub fn set_focused(&mut self, focused: bool) -> Option<&'static [u8]> {
+        if self.is_focused == focused {
+            return None;
+        }
+
+        self.is_focused = focused;
+
+        if !self.mode.contains(Mode::FOCUS_IN_OUT) {
+            return None;
+        }
+
+        if focused {
+            Some(b"\x1b[I")
+        } else {
+            Some(b"\x1b[O")
+        }
+    }
+
     /// Toggle the vi mode.
     #[inline]
     #[allow(unused)]
@@ -529,10 +608,23 @@ impl<U: EventListener> Crosswords<U> {
             return;
         }
 
-        self.grid
-            .cursor_cell()
-            .flags
-            .insert(square::Flags::WRAPLINE);
+        // Past the maximum the line is broken, as if a newline was printed.
+        let mut line_length = self.line_length + self.wrap_columns();
+        if line_length < self.max_line_length {
+            self.grid
+                .cursor_cell()
+                .flags
+                .insert(square::Flags::WRAPLINE);
+        } else {
+            if !self.line_broken {
+                info!(
+                    "Line longer than {} columns, wrapping it",
+                    self.max_line_length
+                );
+                self.line_broken = true;
+            }
+            line_length = 0;
+        }
 
         if self.grid.cursor.pos.row + 1 >= self.scroll_region.end {
             self.linefeed();
@@ -540,6 +632,7 @@ impl<U: EventListener> Crosswords<U> {
             self.damage_cursor();
             self.grid.cursor.pos.row += 1;
         }
+        self.line_length = line_length;
 
         self.grid.cursor.pos.col = Column(0);
         self.grid.cursor.should_wrap = false;
@@ -819,6 +912,148 @@ impl<U: EventListener> Crosswords<U> {
         self.mode.clone()
     }
 
+    /// Wraps lines at `column` instead of the right edge when it's smaller
+    /// than the grid, the remaining columns are left blank.
+    pub fn set_wrap_column(&mut self, column: Option<usize>) {
+        self.wrap_column = column.filter(|column| *column > 0);
+    }
+
+    /// Number of columns used before lines wrap.
+    #[inline]
+    fn wrap_columns(&self) -> usize {
+        let columns = self.grid.columns();
+        self.wrap_column
+            .map_or(columns, |column| column.min(columns))
+    }
+
+    /// What the viewport follows when the terminal is resized.
+    pub fn set_resize_anchor(&mut self, resize_anchor: ResizeAnchor) {
+        self.resize_anchor = resize_anchor;
+    }
+
+    /// Maximum length of a line without newlines and what happens past it.
+    pub fn set_max_line_length(&mut self, max_line_length: usize, long_lines: LongLines) {
+        self.max_line_length = max_line_length.max(1);
+        self.long_lines = long_lines;
+    }
+
+    /// The line reached its maximum length and the rest of it is dropped.
+    fn truncates_line(&mut self) -> bool {
+        if self.long_lines != LongLines::Truncate
+            || self.line_length + self.wrap_columns() < self.max_line_length
+        {
+            return false;
+        }
+
+        if !self.line_truncated {
+            info!(
+                "Line longer than {} columns, truncating it",
+                self.max_line_length
+            );
+            self.line_truncated = true;
+        }
+        true
+    }
+
+    /// A new line starts, it no longer continues the rows above.
+    #[inline]
+    fn reset_line_length(&mut self) {
+        self.line_length = 0;
+        self.line_truncated = false;
+        self.line_broken = false;
+    }
+
+    /// What the form feed character does.
+    pub fn set_form_feed(&mut self, form_feed: FormFeed) {
+        self.form_feed = form_feed;
+    }
+
+    /// The backspace key sends DEL (`true`) or BS, as reported to
+    /// applications asking for the `kbs` capability.
+    pub fn set_backspace_sends_delete(&mut self, backspace_sends_delete: bool) {
+        self.backspace_sends_delete = backspace_sends_delete;
+    }
+
+    /// How control characters without an action of their own are shown.
+    pub fn set_control_picture_style(&mut self, style: ControlPictureStyle) {
+        self.control_picture_style = style;
+    }
+
+    /// Width of East Asian ambiguous characters (e.g. Greek or some box
+    /// drawing), wide is what CJK locales expect.
+    pub fn set_ambiguous_width(&mut self, ambiguous_width: AmbiguousWidth) {
+        self.ambiguous_width = ambiguous_width;
+    }
+
+    /// Interval used when an application turns on cursor blinking.
+    pub fn set_default_cursor_blink_interval(&mut self, interval: Duration) {
+        self.default_cursor_blink_interval = interval;
+        if self.cursor_blink_interval.is_some() {
+            self.cursor_blink_interval = Some(interval);
+        }
+    }
+
+    /// Time the cursor stays shown or hidden, `None` when it doesn't blink.
+    #[inline]
+    pub fn cursor_blink_interval(&self) -> Option<Duration> {
+        self.cursor_blink_interval
+    }
+
+    /// Pins a title that takes precedence over the one set with OSC, `None`
+    /// goes back to the OSC title.
+    #[allow(dead_code)]
+    pub fn set_custom_title(&mut self, title: Option<String>) {
+        self.custom_title = title;
+    }
+
+    /// Title to display for this terminal, the custom title if any or the
+    /// last one set with OSC.
+    #[allow(dead_code)]
+    pub fn title(&self) -> Option<&str> {
+        self.custom_title.as_deref().or(self.title.as_deref())
+    }
+
+    /// Working directory last reported by the shell with OSC 7, e.g. to open
+    /// new tabs in the same directory.
+    #[allow(dead_code)]
+    pub fn current_directory(&self) -> Option<&Path> {
+        self.current_directory.as_deref()
+    }
+
+    /// Cursor position as (line, column) in the viewport, lines move down
+    /// as the display is scrolled back so they can go past the last line.
+    #[allow(dead_code)]
+    pub fn cursor_position(&self) -> (u16, u16) {
+        let pos = self.grid.cursor.pos;
+        let line = pos.row.0 + self.grid.display_offset() as i32;
+        (line as u16, pos.col.0 as u16)
+    }
+
+    /// Cursor position as (line, column) in the terminal screen, ignoring
+    /// the scrollback.
+    #[allow(dead_code)]
+    pub fn logical_cursor_position(&self) -> (u16, u16) {
+        let pos = self.grid.cursor.pos;
+        (pos.row.0 as u16, pos.col.0 as u16)
+    }
+
+    /// Attributes applied to the next printed characters.
+    pub fn current_pen(&self) -> square::Pen {
+        let template = &self.grid.cursor.template;
+        square::Pen {
+            fg: template.fg,
+            bg: template.bg,
+            underline_color: template.underline_color(),
+            flags: template.flags,
+        }
+    }
+
+    /// Whether each column is a tab stop, to draw the cells tabs skip over.
+    #[inline]
+    pub fn tab_stops(&self) -> Vec<bool> {
+        self.tabs.tabs.clone()
+    }
+
     #[inline]
     pub fn cursor(&mut self) -> CursorState {
         let mut content = self.cursor_shape;
@@ -843,7 +1078,44 @@ impl<U: EventListener> Crosswords<U> {
             content = CursorShape::Hidden;
         }
 
-        CursorState { pos, content }
+        let column_offset = match self.caret {
+            Some((caret_pos, offset)) if !vi_mode && caret_pos == pos => offset,
+            _ => 0.0,
+        };
+
+        CursorState {
+            pos,
+            content,
+            column_offset,
+        }
+    }
+
+    /// Whether the square at `line`/`column` is the trailing spacer of a wide char.
+    #[inline]
+    fn is_wide_char_spacer(&self, line: Line, column: Column) -> bool {
+        self.grid[line][column]
+            .flags
+            .contains(square::Flags::WIDE_CHAR_SPACER)
+    }
+
+    /// Move the cursor to a fractional column, the beam cursor is then
+    /// rendered as a caret at that x position (e.g. between ligature halves).
+    ///
+    /// The offset is dropped as soon as the cursor moves away from the column.
+    #[allow(dead_code)]
+    pub fn set_fractional_cursor_column(&mut self, column: f32) {
+        let column = column.clamp(0.0, self.grid.last_column().0 as f32);
+        self.damage_cursor();
+        self.grid.cursor.pos.col = Column(column.trunc() as usize);
+        self.grid.cursor.should_wrap = false;
+        self.damage_cursor();
+
+        let offset = column.fract();
+        self.caret = if offset > 0.0 {
+            Some((self.grid.cursor.pos, offset))
+        } else {
+            None
+        };
     }
 
     pub fn swap_alt(&mut self) {
@@ -921,6 +1193,31 @@ impl<U: EventListener> Crosswords<U> {
         res.strip_suffix('\n').map(str::to_owned).unwrap_or(res)
     }
 
+    /// Visible screen, and the scrollback when `scrollback` is true, as text
+    /// with SGR escapes for colors and attributes. Escapes are only written
+    /// when the style changes and the text ends with a reset, so feeding it
+    /// back to a terminal of the same size reproduces the screen.
+    #[allow(dead_code)]
+    pub fn screen_ansi(&self, scrollback: bool) -> String {
+        let start = if scrollback {
+            self.grid.topmost_line()
+        } else {
+            Line(0)
+        };
+
+        let mut text = String::new();
+        let mut style = export::Style::default();
+        for line in (start.0..=self.grid.bottommost_line().0).map(Line::from) {
+            if line != start && !export::is_wrapped(&self.grid[line - 1i32]) {
+                export::push_newline(&mut text, &mut style);
+            }
+            export::push_row(&mut text, &self.grid[line], &mut style);
+        }
+
+        text.push_str("\x1b[0m");
+        text
+    }
+
     /// Convert a single line in the grid to a String.
     fn line_to_string(
         &self,
@@ -981,8 +1278,8 @@ impl<U: EventListener> Crosswords<U> {
         }
 
         // If wide char is not part of the selection, but leading spacer is, include it.
-        if line_length == self.grid.columns()
-            && line_length.0 >= 2
+        if line_length.0 >= 2
+            && grid_line.wrap_column() == Some(line_length - 1)
             && grid_line[line_length - 1]
                 .flags
                 .contains(square::Flags::LEADING_WIDE_CHAR_SPACER)
@@ -997,9 +1294,7 @@ impl<U: EventListener> Crosswords<U> {
     /// Find the beginning of the current line across linewraps.
     pub fn row_search_left(&self, mut point: Pos) -> Pos {
         while point.row > self.grid.topmost_line()
-            && self.grid[point.row - 1i32][self.grid.last_column()]
-                .flags
-                .contains(square::Flags::WRAPLINE)
+            && self.grid[point.row - 1i32].is_wrapped()
         {
             point.row -= 1;
         }
@@ -1012,9 +1307,7 @@ impl<U: EventListener> Crosswords<U> {
     /// Find the end of the current line across linewraps.
     pub fn row_search_right(&self, mut point: Pos) -> Pos {
         while point.row + 1 < self.grid.screen_lines()
-            && self.grid[point.row][self.grid.last_column()]
-                .flags
-                .contains(square::Flags::WRAPLINE)
+            && self.grid[point.row].is_wrapped()
         {
             point.row += 1;
         }
@@ -1067,14 +1360,14 @@ impl<U: EventListener> Handler for Crosswords<U> {
             AnsiMode::AlternateScroll => self.mode.insert(Mode::ALTERNATE_SCROLL),
             AnsiMode::LineWrap => self.mode.insert(Mode::LINE_WRAP),
             AnsiMode::LineFeedNewLine => self.mode.insert(Mode::LINE_FEED_NEW_LINE),
-            AnsiMode::Origin => self.mode.insert(Mode::ORIGIN),
+            AnsiMode::Origin => {
+                self.mode.insert(Mode::ORIGIN);
+                // DECOM homes the cursor to the top of the scrolling region.
+                self.goto(Line(0), Column(0));
+            }
             AnsiMode::Column => self.deccolm(),
             AnsiMode::Insert => self.mode.insert(Mode::INSERT),
-            AnsiMode::BlinkingCursor => {
-                // let style = self.grid.cursor_style.get_or_insert(self.default_cursor_style);
-                // style.blinking = true;
-                // self.event_proxy.send_event(Event::CursorBlinkingChange);
-            }
+            AnsiMode::BlinkingCursor => self.set_cursor_blinking(true),
         }
     }
 
@@ -1127,17 +1420,16 @@ impl<U: EventListener> Handler for Crosswords<U> {
             AnsiMode::AlternateScroll => self.mode.remove(Mode::ALTERNATE_SCROLL),
             AnsiMode::LineWrap => self.mode.remove(Mode::LINE_WRAP),
             AnsiMode::LineFeedNewLine => self.mode.remove(Mode::LINE_FEED_NEW_LINE),
-            AnsiMode::Origin => self.mode.remove(Mode::ORIGIN),
+            AnsiMode::Origin => {
+                self.mode.remove(Mode::ORIGIN);
+                self.goto(Line(0), Column(0));
+            }
             AnsiMode::Column => self.deccolm(),
             AnsiMode::Insert => {
                 self.mode.remove(Mode::INSERT);
                 self.mark_fully_damaged();
             }
-            AnsiMode::BlinkingCursor => {
-                // let style = self.cursor_style.get_or_insert(self.default_cursor_style);
-                // style.blinking = false;
-                // self.event_proxy.send_event(Event::CursorBlinkingChange);
-            }
+            AnsiMode::BlinkingCursor => self.set_cursor_blinking(false),
         }
     }
 
@@ -1155,6 +1447,7 @@ impl<U: EventListener> Handler for Crosswords<U> {
         self.grid.cursor.pos.col = std::cmp::min(col, self.grid.last_column());
         self.damage_cursor();
         self.grid.cursor.should_wrap = false;
+        self.reset_line_length();
     }
 
     #[inline]
@@ -1164,9 +1457,18 @@ impl<U: EventListener> Handler for Crosswords<U> {
 
     #[inline]
     fn move_forward(&mut self, cols: Column) {
-        let last_column =
+        let mut last_column =
             std::cmp::min(self.grid.cursor.pos.col + cols, self.grid.last_column());
 
+        // Never land on the spacer half of a wide char.
+        if self.is_wide_char_spacer(self.grid.cursor.pos.row, last_column) {
+            if last_column < self.grid.last_column() {
+                last_column += 1;
+            } else {
+                last_column -= 1;
+            }
+        }
+
         let cursor_line = self.grid.cursor.pos.row.0 as usize;
         self.damage
             .damage_line(cursor_line, self.grid.cursor.pos.col.0, last_column.0);
@@ -1177,7 +1479,12 @@ impl<U: EventListener> Handler for Crosswords<U> {
 
     #[inline]
     fn move_backward(&mut self, cols: Column) {
-        let column = self.grid.cursor.pos.col.saturating_sub(cols.0);
+        let mut column = self.grid.cursor.pos.col.saturating_sub(cols.0);
+
+        // Never land on the spacer half of a wide char.
+        if self.is_wide_char_spacer(self.grid.cursor.pos.row, Column(column)) {
+            column -= 1;
+        }
 
         let cursor_line = self.grid.cursor.pos.row.0 as usize;
         self.damage
@@ -1187,6 +1494,29 @@ impl<U: EventListener> Handler for Crosswords<U> {
         self.grid.cursor.should_wrap = false;
     }
 
+    #[inline]
+    fn move_forward_tabs(&mut self, count: u16) {
+        self.damage_cursor();
+
+        let old_col = self.grid.cursor.pos.col.0;
+        let last_column = self.grid.last_column();
+        for _ in 0..count {
+            let mut col = self.grid.cursor.pos.col;
+            while col < last_column {
+                col += 1;
+                if self.tabs[col] {
+                    break;
+                }
+            }
+            self.grid.cursor.pos.col = col;
+        }
+        self.grid.cursor.should_wrap = false;
+
+        let line = self.grid.cursor.pos.row.0 as usize;
+        self.damage
+            .damage_line(line, old_col, self.grid.cursor.pos.col.0);
+    }
+
     #[inline]
     fn move_backward_tabs(&mut self, count: u16) {
         self.damage_cursor();
@@ -1215,7 +1545,12 @@ impl<U: EventListener> Handler for Crosswords<U> {
 
     #[inline]
     fn goto_col(&mut self, col: Column) {
-        self.goto(self.grid.cursor.pos.row, col)
+        // The row is already absolute, going through `goto` would offset it
+        // again by the top margin in origin mode.
+        self.damage_cursor();
+        self.grid.cursor.pos.col = std::cmp::min(col, self.grid.last_column());
+        self.damage_cursor();
+        self.grid.cursor.should_wrap = false;
     }
 
     #[inline]
@@ -1264,6 +1599,8 @@ impl<U: EventListener> Handler for Crosswords<U> {
 
         if lines > 0 && self.scroll_region.contains(&origin) {
             self.scroll_up_relative(origin, lines);
+            // Like xterm, the cursor goes back to the first column.
+            self.carriage_return();
         }
     }
 
@@ -1271,12 +1608,24 @@ impl<U: EventListener> Handler for Crosswords<U> {
     fn erase_chars(&mut self, count: Column) {
         let cursor = &self.grid.cursor;
 
-        let start = cursor.pos.col;
-        let end = std::cmp::min(start + count, Column(self.grid.columns()));
+        let line = cursor.pos.row;
+        let mut start = cursor.pos.col;
+        let mut end = std::cmp::min(start + count, Column(self.grid.columns()));
+
+        // Wide chars and their spacers are always erased together.
+        if self.is_wide_char_spacer(line, start) {
+            start -= 1;
+        }
+        if end < self.grid.columns()
+            && self.grid[line][end - 1]
+                .flags
+                .contains(square::Flags::WIDE_CHAR)
+        {
+            end += 1;
+        }
 
         // Cleared cells have current background color set.
         let bg = self.grid.cursor.template.bg;
-        let line = cursor.pos.row;
         self.damage.damage_line(line.0 as usize, start.0, end.0);
         let row = &mut self.grid[line];
         for cell in &mut row[start..end] {
@@ -1325,6 +1674,8 @@ impl<U: EventListener> Handler for Crosswords<U> {
         let origin = self.grid.cursor.pos.row;
         if self.scroll_region.contains(&origin) {
             self.scroll_down_relative(origin, lines);
+            // Like xterm, the cursor goes back to the first column.
+            self.carriage_return();
         }
     }
 
@@ -1446,12 +1797,30 @@ impl<U: EventListener> Handler for Crosswords<U> {
         // title
     }
 
+    fn set_current_directory(&mut self, path: PathBuf) {
+        self.current_directory = Some(path);
+    }
+
     fn set_cursor_shape(&mut self, shape: CursorShape) {
         self.cursor_shape = shape;
     }
 
+    /// Both `CSI ? 12 h/l` and `DECSCUSR` end up here, whichever comes last
+    /// wins.
+    fn set_cursor_blinking(&mut self, blinking: bool) {
+        let interval = blinking.then_some(self.default_cursor_blink_interval);
+        if interval != self.cursor_blink_interval {
+            self.cursor_blink_interval = interval;
+            self.event_proxy.send_event(RioEvent::CursorBlinkingChange);
+        }
+    }
+
     fn input(&mut self, c: char) {
-        let width = match c.width() {
+        let width = match self.ambiguous_width {
+            AmbiguousWidth::Narrow => c.width(),
+            AmbiguousWidth::Wide => c.width_cjk(),
+        };
+        let width = match width {
             Some(width) => width,
             None => return,
         };
@@ -1478,10 +1847,15 @@ impl<U: EventListener> Handler for Crosswords<U> {
         }
 
         if self.grid.cursor.should_wrap {
+            if self.truncates_line() {
+                return;
+            }
             self.wrapline();
         }
 
-        let columns = self.grid.columns();
+        let columns = self.wrap_columns();
+        // Wide chars never fit a single column, they are cut to one square.
+        let width = if columns < 2 { 1 } else { width };
         if self.mode.contains(Mode::INSERT) && self.grid.cursor.pos.col + width < columns
         {
             let line = self.grid.cursor.pos.row;
@@ -1498,6 +1872,10 @@ impl<U: EventListener> Handler for Crosswords<U> {
         } else {
             if self.grid.cursor.pos.col + 1 >= columns {
                 if self.mode.contains(Mode::LINE_WRAP) {
+                    if self.truncates_line() {
+                        return;
+                    }
+
                     // Insert placeholder before wide char if glyph does not fit in this row.
                     self.grid
                         .cursor
@@ -1567,8 +1945,14 @@ impl<U: EventListener> Handler for Crosswords<U> {
             let line = self.grid.cursor.pos.row.0 as usize;
             let column = self.grid.cursor.pos.col.0;
             self.grid.cursor.pos.col -= 1;
+            if self
+                .is_wide_char_spacer(self.grid.cursor.pos.row, self.grid.cursor.pos.col)
+            {
+                self.grid.cursor.pos.col -= 1;
+            }
             self.grid.cursor.should_wrap = false;
-            self.damage.damage_line(line, column - 1, column);
+            self.damage
+                .damage_line(line, self.grid.cursor.pos.col.0, column);
         }
     }
 
@@ -1663,6 +2047,7 @@ impl<U: EventListener> Handler for Crosswords<U> {
     }
 
     fn linefeed(&mut self) {
+        self.reset_line_length();
         let next = self.grid.cursor.pos.row + 1;
         if next == self.scroll_region.end {
             self.scroll_up_relative(self.scroll_region.start, 1);
@@ -1673,30 +2058,53 @@ impl<U: EventListener> Handler for Crosswords<U> {
         }
     }
 
-    /// Set the indexed color value.
-    #[inline]
-    fn set_color(&mut self, _index: usize, _color: ColorRgb) {
-        // Damage terminal if the color changed and it's not the cursor.
-        // if index != NamedColor::Cursor as usize && self.colors[index] != Some(color) {
-        // self.mark_fully_damaged();
-        // }
+    fn form_feed(&mut self) {
+        match self.form_feed {
+            FormFeed::LineFeed => self.linefeed(),
+            FormFeed::Clear => {
+                self.clear_screen(ClearMode::All);
+                self.goto(Line(0), Column(0));
+            }
+            FormFeed::Ignore => (),
+        }
+    }
+
+    fn control_char(&mut self, byte: u8) {
+        if byte >= 0x20 || self.control_picture_style == ControlPictureStyle::Hidden {
+            warn!("[unhandled] execute byte={byte:02x}");
+            return;
+        }
 
-        // self.colors[index] = Some(color);
+        match self.control_picture_style {
+            ControlPictureStyle::Caret => {
+                self.input('^');
+                self.input((byte ^ 0x40) as char);
+            }
+            // Control Pictures start at U+2400 in the order of C0.
+            _ => {
+                if let Some(c) = char::from_u32(0x2400 + byte as u32) {
+                    self.input(c);
+                }
+            }
+        }
     }
 
+    /// Set the indexed color value.
     #[inline]
-    fn reset_color(&mut self, _index: usize) {
-        // Damage terminal if the color changed and it's not the cursor.
-        // if index != NamedColor::Cursor as usize && self.colors[index].is_some() {
-        // self.mark_fully_damaged();
-        // }
+    fn set_color(&mut self, index: usize, color: ColorRgb) {
+        self.event_proxy
+            .send_event(RioEvent::ColorChange(index, Some(color)));
+    }
 
-        // self.colors[index] = None;
+    #[inline]
+    fn reset_color(&mut self, index: usize) {
+        self.event_proxy
+            .send_event(RioEvent::ColorChange(index, None));
     }
 
     #[inline]
     fn bell(&mut self) {
-        warn!("[unimplemented] Bell");
+        self.event_proxy.send_event(RioEvent::Bell);
     }
 
     #[inline]
@@ -1761,6 +2169,7 @@ impl<U: EventListener> Handler for Crosswords<U> {
             .damage_line(row, new_col, self.grid.cursor.pos.col.0);
         self.grid.cursor.pos.col = Column(new_col);
         self.grid.cursor.should_wrap = false;
+        self.reset_line_length();
     }
 
     #[inline]
@@ -1833,6 +2242,12 @@ impl<U: EventListener> Handler for Crosswords<U> {
         info!("text_area_size_chars {:?}", text);
         self.event_proxy.send_event(RioEvent::PtyWrite(text));
     }
+
+    #[inline]
+    fn report_capability(&mut self, name: &str) {
+        let text = capabilities::xtgettcap_reply(name, self.backspace_sends_delete);
+        self.event_proxy.send_event(RioEvent::PtyWrite(text));
+    }
 }
 
 /// Terminal test helpers.
@@ -1876,6 +2291,14 @@ mod tests {
     use crate::crosswords::test::CrosswordsSize;
     use crate::event::VoidListener;
 
+    /// Processes `bytes` through a new parser.
+    fn feed<U: EventListener>(term: &mut Crosswords<U>, bytes: &[u8]) {
+        let mut parser = crate::performer::handler::ParserProcessor::new();
+        for byte in bytes {
+            parser.advance(term, *byte);
+        }
+    }
+
     #[test]
     fn scroll_up() {
         let mut cw = Crosswords::new(1, 10, VoidListener {});
@@ -2126,4 +2549,738 @@ mod tests {
             Some(String::from("\na\"\na\"\na"))
         );
     }
+
+    #[test]
+    fn fractional_cursor_column_places_caret_between_cells() {
+        let mut cw: Crosswords<VoidListener> = Crosswords::new(10, 3, VoidListener {});
+        cw.set_fractional_cursor_column(2.5);
+
+        let cursor = cw.cursor();
+        assert_eq!(cursor.pos.col, Column(2));
+        assert_eq!(cursor.column_offset, 0.5);
+
+        // Whole columns don't produce a caret offset.
+        cw.set_fractional_cursor_column(3.0);
+        assert_eq!(cw.cursor().pos.col, Column(3));
+        assert_eq!(cw.cursor().column_offset, 0.0);
+
+        // Moving the cursor away drops the offset.
+        cw.set_fractional_cursor_column(4.25);
+        cw.move_forward(Column(1));
+        assert_eq!(cw.cursor().pos.col, Column(5));
+        assert_eq!(cw.cursor().column_offset, 0.0);
+    }
+
+    #[test]
+    fn cursor_movement_skips_wide_char_spacer() {
+        let mut cw: Crosswords<VoidListener> = Crosswords::new(10, 3, VoidListener {});
+        cw.input('a');
+        cw.input('字');
+        assert_eq!(cw.grid.cursor.pos.col, Column(3));
+
+        cw.move_backward(Column(1));
+        assert_eq!(cw.grid.cursor.pos.col, Column(1));
+
+        cw.move_forward(Column(1));
+        assert_eq!(cw.grid.cursor.pos.col, Column(3));
+
+        cw.goto_col(Column(3));
+        cw.backspace();
+        assert_eq!(cw.grid.cursor.pos.col, Column(1));
+    }
+
+    #[test]
+    fn erase_wide_char_spacer_clears_both_halves() {
+        let mut cw: Crosswords<VoidListener> = Crosswords::new(10, 3, VoidListener {});
+        cw.input('字');
+        cw.input('b');
+
+        cw.goto_col(Column(1));
+        cw.erase_chars(Column(1));
+        assert_eq!(cw.grid[Line(0)][Column(0)].c, ' ');
+        assert!(!cw.grid[Line(0)][Column(0)]
+            .flags
+            .contains(square::Flags::WIDE_CHAR));
+        assert!(!cw.grid[Line(0)][Column(1)]
+            .flags
+            .contains(square::Flags::WIDE_CHAR_SPACER));
+        assert_eq!(cw.grid[Line(0)][Column(2)].c, 'b');
+
+        // Erasing only the leading half also clears the spacer.
+        cw.goto_col(Column(0));
+        cw.input('字');
+        cw.goto_col(Column(0));
+        cw.erase_chars(Column(1));
+        assert!(!cw.grid[Line(0)][Column(1)]
+            .flags
+            .contains(square::Flags::WIDE_CHAR_SPACER));
+        assert_eq!(cw.grid[Line(0)][Column(2)].c, 'b');
+    }
+
+    #[test]
+    fn cursor_tabulation_moves_across_tab_stops() {
+        let mut cw: Crosswords<VoidListener> = Crosswords::new(20, 3, VoidListener {});
+
+        // Default tab stops are every 8 columns
+        cw.move_forward_tabs(1);
+        assert_eq!(cw.grid.cursor.pos.col, Column(8));
+        cw.move_forward_tabs(1);
+        assert_eq!(cw.grid.cursor.pos.col, Column(16));
+
+        // Stops at the right margin
+        cw.move_forward_tabs(3);
+        assert_eq!(cw.grid.cursor.pos.col, Column(19));
+
+        cw.move_backward_tabs(2);
+        assert_eq!(cw.grid.cursor.pos.col, Column(8));
+
+        // Stops at the left margin
+        cw.move_backward_tabs(5);
+        assert_eq!(cw.grid.cursor.pos.col, Column(0));
+
+        cw.goto_col(Column(3));
+        cw.move_forward_tabs(2);
+        assert_eq!(cw.grid.cursor.pos.col, Column(16));
+
+        // Nothing is written to the grid
+        assert_eq!(cw.grid[Line(0)][Column(3)].c, ' ');
+    }
+
+    #[test]
+    fn focus_reports_only_when_mode_is_enabled() {
+        let mut cw = Crosswords::new(10, 5, VoidListener {});
+
+        // Focus changes are not reported by default.
+        assert_eq!(cw.set_focused(false), None);
+        assert_eq!(cw.set_focused(true), None);
+
+        cw.set_mode(AnsiMode::ReportFocusInOut);
+        assert_eq!(cw.set_focused(false), Some(&b"\x1b[O"[..]));
+        // Same state again is not reported twice.
+        assert_eq!(cw.set_focused(false), None);
+        assert_eq!(cw.set_focused(true), Some(&b"\x1b[I"[..]));
+
+        cw.unset_mode(AnsiMode::ReportFocusInOut);
+        assert_eq!(cw.set_focused(false), None);
+        assert!(!cw.is_focused);
+    }
+
+    #[test]
+    fn cursor_position_and_pen_follow_terminal_state() {
+        let mut cw = Crosswords::new(10, 5, VoidListener {});
+
+        // Fill the screen and push two lines to the scrollback.
+        for _ in 0..6 {
+            cw.linefeed();
+        }
+        cw.goto(Line(2), Column(3));
+        assert_eq!(cw.cursor_position(), (2, 3));
+        assert_eq!(cw.logical_cursor_position(), (2, 3));
+
+        cw.scroll_display(Scroll::Delta(2));
+        assert_eq!(cw.cursor_position(), (4, 3));
+        assert_eq!(cw.logical_cursor_position(), (2, 3));
+
+        assert_eq!(cw.current_pen().flags, square::Flags::empty());
+        cw.terminal_attribute(Attr::Bold);
+        cw.terminal_attribute(Attr::Foreground(AnsiColor::Named(NamedColor::Red)));
+
+        let pen = cw.current_pen();
+        assert_eq!(pen.flags, square::Flags::BOLD);
+        assert_eq!(pen.fg, AnsiColor::Named(NamedColor::Red));
+        assert_eq!(pen.bg, AnsiColor::Named(NamedColor::Background));
+        assert_eq!(pen.underline_color, None);
+
+        cw.terminal_attribute(Attr::Reset);
+        assert_eq!(cw.current_pen().flags, square::Flags::empty());
+    }
+
+    #[test]
+    fn wide_char_at_last_column_wraps_to_next_line() {
+        let mut cw = Crosswords::new(5, 3, VoidListener {});
+
+        cw.goto(Line(0), Column(4));
+        cw.input('中');
+
+        // Last column keeps a leading spacer and the line is marked as wrapped.
+        let spacer = &cw.grid[Line(0)][Column(4)];
+        assert_eq!(spacer.c, ' ');
+        assert!(spacer
+            .flags
+            .contains(square::Flags::LEADING_WIDE_CHAR_SPACER | square::Flags::WRAPLINE));
+
+        // Both halves of the wide char are on the next line.
+        assert_eq!(cw.grid[Line(1)][Column(0)].c, '中');
+        assert!(cw.grid[Line(1)][Column(0)]
+            .flags
+            .contains(square::Flags::WIDE_CHAR));
+        assert!(cw.grid[Line(1)][Column(1)]
+            .flags
+            .contains(square::Flags::WIDE_CHAR_SPACER));
+        assert_eq!(cw.grid.cursor.pos, Pos::new(Line(1), Column(2)));
+
+        // Without autowrap the wide char is dropped instead of being split.
+        let mut cw = Crosswords::new(5, 3, VoidListener {});
+        cw.unset_mode(AnsiMode::LineWrap);
+        cw.goto(Line(0), Column(4));
+        cw.input('中');
+        assert_eq!(cw.grid[Line(0)][Column(4)].c, ' ');
+        assert_eq!(cw.grid[Line(1)][Column(0)].c, ' ');
+    }
+
+    #[test]
+    fn selection_across_scrollback_is_contiguous() {
+        let mut term = Crosswords::new(5, 3, VoidListener {});
+        for line in ["one", "two", "three", "four"] {
+            for c in line.chars() {
+                term.input(c);
+            }
+            term.carriage_return();
+            term.linefeed();
+        }
+
+        // "one" and "two" went to the scrollback, "three" and "four" are visible.
+        assert_eq!(term.grid.history_size(), 2);
+        term.scroll_display(Scroll::Delta(1));
+
+        term.selection = Some(Selection::new(
+            SelectionType::Simple,
+            Pos::new(Line(-1), Column(0)),
+            Side::Left,
+        ));
+        if let Some(s) = term.selection.as_mut() {
+            s.update(Pos::new(Line(1), Column(4)), Side::Right);
+        }
+
+        assert_eq!(
+            term.selection_to_string(),
+            Some(String::from("two\nthree\nfour"))
+        );
+    }
+
+    #[test]
+    fn custom_title_survives_osc_title_changes() {
+        let mut term = Crosswords::new(5, 3, VoidListener {});
+        assert_eq!(term.title(), None);
+
+        term.set_title(Some(String::from("vim")));
+        assert_eq!(term.title(), Some("vim"));
+
+        term.set_custom_title(Some(String::from("editor")));
+        term.set_title(Some(String::from("htop")));
+        assert_eq!(term.title(), Some("editor"));
+
+        // Clearing the custom title goes back to the latest OSC title.
+        term.set_custom_title(None);
+        assert_eq!(term.title(), Some("htop"));
+    }
+
+    #[test]
+    fn erase_saved_lines_keeps_visible_screen() {
+        let mut term = Crosswords::new(5, 2, VoidListener {});
+        feed(&mut term, b"one\r\ntwo\r\nthree");
+        assert_eq!(term.grid.history_size(), 1);
+        term.scroll_display(Scroll::Delta(1));
+
+        feed(&mut term, b"\x1b[3J");
+
+        assert_eq!(term.grid.history_size(), 0);
+        assert_eq!(term.grid.display_offset(), 0);
+        assert_eq!(term.grid[Line(0)][Column(0)].c, 't');
+        assert_eq!(term.grid[Line(1)][Column(2)].c, 'r');
+
+        // Clearing the visible screen moves it to the scrollback instead.
+        feed(&mut term, b"\r\nfour\x1b[2J");
+        assert!(term.grid.history_size() > 0);
+        assert_eq!(term.grid[Line(0)][Column(0)].c, ' ');
+        assert_eq!(term.grid[Line(1)][Column(0)].c, ' ');
+    }
+
+    #[test]
+    fn origin_mode_addresses_cursor_inside_scrolling_region() {
+        let mut term = Crosswords::new(10, 6, VoidListener {});
+
+        // DECSTBM homes the cursor, absolute while origin mode is off.
+        feed(&mut term, b"\x1b[3;3H\x1b[2;4r");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(0), Column(0)));
+        feed(&mut term, b"\x1b[5;2H");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(4), Column(1)));
+
+        // DECOM homes the cursor to the top margin.
+        feed(&mut term, b"\x1b[?6h");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(1), Column(0)));
+
+        // CUP is relative to the top margin and clamped to the region.
+        feed(&mut term, b"\x1b[2;3H");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(2)));
+        feed(&mut term, b"\x1b[6;1H");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(3), Column(0)));
+
+        // Back to absolute addressing.
+        feed(&mut term, b"\x1b[?6l");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(0), Column(0)));
+        feed(&mut term, b"\x1b[6;1H");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(5), Column(0)));
+    }
+
+    #[test]
+    fn absolute_column_and_line_are_clamped() {
+        let mut term = Crosswords::new(10, 6, VoidListener {});
+
+        // CHA and VPA are 1-based and keep the other coordinate.
+        feed(&mut term, b"\x1b[3;3H\x1b[5G");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(4)));
+        feed(&mut term, b"\x1b[4d");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(3), Column(4)));
+
+        // A missing or zero parameter means 1.
+        feed(&mut term, b"\x1b[G\x1b[0d");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(0), Column(0)));
+
+        // Out of range values are clamped to the last column and line.
+        feed(&mut term, b"\x1b[99G\x1b[99d");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(5), Column(9)));
+
+        // In origin mode VPA is relative to the scrolling region, CHA keeps
+        // the row where it is.
+        feed(&mut term, b"\x1b[2;4r\x1b[?6h\x1b[2d");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(0)));
+        feed(&mut term, b"\x1b[3G");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(2)));
+        feed(&mut term, b"\x1b[99d");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(3), Column(2)));
+    }
+
+    #[test]
+    fn oversized_osc_is_discarded() {
+        let mut term = Crosswords::new(10, 2, VoidListener {});
+        let mut parser =
+            crate::performer::handler::ParserProcessor::with_max_string_length(16);
+        let mut feed = |term: &mut Crosswords<VoidListener>, bytes: &[u8]| {
+            for byte in bytes {
+                parser.advance(term, *byte);
+            }
+        };
+
+        // The title is dropped along with the rest of the string, text after
+        // its terminator is parsed as usual.
+        let oversized = |terminator: &[u8]| {
+            let mut osc = b"\x1b]0;".to_vec();
+            osc.extend(std::iter::repeat(b'x').take(64));
+            osc.extend(terminator);
+            osc
+        };
+        feed(&mut term, &oversized(b"\x07ok"));
+        assert_eq!(term.title(), None);
+        assert_eq!(term.grid[Line(0)][Column(0)].c, 'o');
+        assert_eq!(term.grid[Line(0)][Column(1)].c, 'k');
+        assert_eq!(term.grid[Line(0)][Column(2)].c, ' ');
+
+        // Same with an ST terminator.
+        feed(&mut term, &oversized(b"\x1b\\!"));
+        assert_eq!(term.title(), None);
+        assert_eq!(term.grid[Line(0)][Column(2)].c, '!');
+
+        // Strings within the limit still work.
+        feed(&mut term, b"\x1b]0;rio\x07");
+        assert_eq!(term.title(), Some("rio"));
+    }
+
+    #[test]
+    fn osc_7_reports_the_current_directory() {
+        let mut term = Crosswords::new(10, 2, VoidListener {});
+        assert_eq!(term.current_directory(), None);
+
+        feed(
+            &mut term,
+            b"\x1b]7;file://rio.local/home/rio/My%20Projects\x07",
+        );
+        assert_eq!(
+            term.current_directory(),
+            Some(Path::new("/home/rio/My Projects"))
+        );
+
+        // Without a host, and with an ST terminator.
+        feed(&mut term, b"\x1b]7;file:///tmp/caf%C3%A9%2Fbar\x1b\\");
+        assert_eq!(term.current_directory(), Some(Path::new("/tmp/café/bar")));
+
+        // Other schemes and broken escapes are ignored.
+        feed(&mut term, b"\x1b]7;https://rio.local/home\x07");
+        feed(&mut term, b"\x1b]7;file://rio.local/home%2\x07");
+        assert_eq!(term.current_directory(), Some(Path::new("/tmp/café/bar")));
+    }
+
+    #[test]
+    fn screen_ansi_round_trip() {
+        let mut term = Crosswords::new(8, 4, VoidListener {});
+        feed(
+            &mut term,
+            "\x1b[1;31mbold\x1b[0m \x1b[3;4mit\r\n\x1b[44;38;5;200mbg  \x1b[0m\
+             \r\n\x1b[38;2;1;2;3mwrapping line\x1b[m 漢"
+                .as_bytes(),
+        );
+
+        let ansi = term.screen_ansi(false);
+        assert!(ansi.ends_with("\x1b[0m"));
+        // Escapes are only written when the style changes.
+        assert_eq!(ansi.matches("\x1b[0;1;31m").count(), 1);
+        assert!(ansi.contains("\x1b[0;1;31mbold\x1b[0m \x1b[0;3;4mit"));
+
+        let mut copy = Crosswords::new(8, 4, VoidListener {});
+        feed(&mut copy, ansi.as_bytes());
+        for line in 0..4 {
+            assert_eq!(copy.grid[Line(line)], term.grid[Line(line)], "line {line}");
+        }
+        assert_eq!(copy.screen_ansi(false), ansi);
+    }
+
+    #[test]
+    fn ambiguous_width_chars() {
+        // Greek alpha is ambiguous, CJK ideographs are always wide.
+        for (ambiguous_width, alpha_width) in
+            [(AmbiguousWidth::Narrow, 1), (AmbiguousWidth::Wide, 2)]
+        {
+            let mut term = Crosswords::new(6, 2, VoidListener {});
+            term.set_ambiguous_width(ambiguous_width);
+            for c in "α漢a".chars() {
+                term.input(c);
+            }
+
+            let row = &term.grid[Line(0)];
+            assert_eq!(row[Column(0)].c, 'α');
+            assert_eq!(
+                row[Column(0)].flags.contains(square::Flags::WIDE_CHAR),
+                alpha_width == 2
+            );
+            assert_eq!(row[Column(alpha_width)].c, '漢');
+            assert_eq!(row[Column(alpha_width + 2)].c, 'a');
+            assert_eq!(term.grid.cursor.pos.col, Column(alpha_width + 3));
+        }
+    }
+
+    #[test]
+    fn cursor_blinking_modes() {
+        let mut term = Crosswords::new(10, 6, VoidListener {});
+        term.set_default_cursor_blink_interval(Duration::from_millis(300));
+        assert_eq!(term.cursor_blink_interval(), None);
+
+        feed(&mut term, b"\x1b[?12h");
+        assert_eq!(
+            term.cursor_blink_interval(),
+            Some(Duration::from_millis(300))
+        );
+        feed(&mut term, b"\x1b[?12l");
+        assert_eq!(term.cursor_blink_interval(), None);
+
+        // DECSCUSR overrides the mode, odd styles blink and even ones don't.
+        feed(&mut term, b"\x1b[5 q");
+        assert_eq!(term.cursor_shape, CursorShape::Beam);
+        assert_eq!(
+            term.cursor_blink_interval(),
+            Some(Duration::from_millis(300))
+        );
+        feed(&mut term, b"\x1b[?12h\x1b[4 q");
+        assert_eq!(term.cursor_shape, CursorShape::Underline);
+        assert_eq!(term.cursor_blink_interval(), None);
+        feed(&mut term, b"\x1b[1 q\x1b[0 q");
+        assert_eq!(term.cursor_shape, CursorShape::Block);
+        assert_eq!(term.cursor_blink_interval(), None);
+    }
+
+    #[test]
+    fn resize_anchored_to_cursor_keeps_it_on_screen() {
+        let resized = |resize_anchor| {
+            let mut term = Crosswords::new(10, 10, VoidListener {});
+            term.set_resize_anchor(resize_anchor);
+            for _ in 0..30 {
+                term.newline();
+            }
+            term.goto(Line(5), Column(0));
+            // Scrolled back, the cursor is on the 8th visible row.
+            term.scroll_display(Scroll::Delta(2));
+
+            term.resize::<CrosswordsSize>(10, 6);
+            term.grid.cursor.pos.row.0 + term.grid.display_offset() as i32
+        };
+
+        // The viewport doesn't move, leaving the cursor below it.
+        assert_eq!(resized(ResizeAnchor::Top), 7);
+        assert_eq!(resized(ResizeAnchor::Cursor), 5);
+        assert_eq!(resized(ResizeAnchor::Bottom), 5);
+    }
+
+    #[test]
+    fn form_feed_settings() {
+        let form_feed = |form_feed| {
+            let mut term = Crosswords::new(10, 5, VoidListener {});
+            term.set_form_feed(form_feed);
+            feed(&mut term, b"ab\x1b[3;4H\x0c");
+            (term.grid.cursor.pos, term.grid[Line(0)][Column(0)].c)
+        };
+
+        assert_eq!(
+            form_feed(FormFeed::LineFeed),
+            (Pos::new(Line(3), Column(3)), 'a')
+        );
+        assert_eq!(
+            form_feed(FormFeed::Clear),
+            (Pos::new(Line(0), Column(0)), ' ')
+        );
+        assert_eq!(
+            form_feed(FormFeed::Ignore),
+            (Pos::new(Line(2), Column(3)), 'a')
+        );
+    }
+
+    #[test]
+    fn control_picture_styles() {
+        let shown = |style| {
+            let mut term = Crosswords::new(10, 5, VoidListener {});
+            term.set_control_picture_style(style);
+            feed(&mut term, b"a\x00\x01b\x1b[m");
+            term.grid[Line(0)][..]
+                .iter()
+                .map(|square| square.c)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+
+        // ESC starts a sequence, so it's never shown.
+        assert_eq!(shown(ControlPictureStyle::Hidden), "ab");
+        assert_eq!(shown(ControlPictureStyle::Caret), "a^@^Ab");
+        assert_eq!(
+            shown(ControlPictureStyle::ControlPictures),
+            "a\u{2400}\u{2401}b"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_bounded() {
+        let filled = |long_lines| {
+            let mut term = Crosswords::new(10, 5, VoidListener {});
+            term.set_max_line_length(100, long_lines);
+            feed(&mut term, &b"x".repeat(200_000));
+            term
+        };
+
+        // Rows of each line, from the top of the history.
+        let line_rows = |term: &Crosswords<VoidListener>| {
+            let mut lines = vec![0];
+            let mut row = term.grid.topmost_line();
+            while row <= term.grid.bottommost_line() {
+                *lines.last_mut().unwrap() += 1;
+                if !term.grid[row][Column(9)]
+                    .flags
+                    .contains(square::Flags::WRAPLINE)
+                {
+                    lines.push(0);
+                }
+                row += 1;
+            }
+            lines
+        };
+
+        let term = filled(LongLines::Wrap);
+        assert_eq!(term.grid.history_size(), 10_000);
+        let lines = line_rows(&term);
+        assert!(lines.iter().all(|rows| *rows <= 10));
+
+        // Only the first 100 columns are kept, the rest is dropped.
+        let term = filled(LongLines::Truncate);
+        assert_eq!(term.grid.history_size(), 5);
+        assert_eq!(line_rows(&term)[0], 10);
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(4), Column(9)));
+    }
+
+    #[derive(Clone, Default)]
+    struct PtyWriteListener(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+    impl EventListener for PtyWriteListener {
+        fn send_event(&self, event: RioEvent) {
+            if let RioEvent::PtyWrite(text) = event {
+                self.0.borrow_mut().push(text);
+            }
+        }
+    }
+
+    #[test]
+    fn xtgettcap_replies_known_and_unknown_capabilities() {
+        let listener = PtyWriteListener::default();
+        let mut term = Crosswords::new(5, 3, listener.clone());
+
+        // Query "colors" and "foo" in a single request.
+        feed(&mut term, b"\x1bP+q636F6C6F7273;666F6F\x1b\\");
+
+        assert_eq!(
+            *listener.0.borrow(),
+            vec![
+                String::from("\x1bP1+r636F6C6F7273=323536\x1b\\"),
+                String::from("\x1bP0+r666F6F\x1b\\"),
+            ]
+        );
+        // Nothing is printed on the grid.
+        assert_eq!(term.grid[Line(0)][Column(0)].c, ' ');
+    }
+
+    #[test]
+    fn output_wraps_at_wrap_column() {
+        let mut term = Crosswords::new(10, 3, VoidListener {});
+        term.set_wrap_column(Some(4));
+
+        for c in "abcdef".chars() {
+            term.input(c);
+        }
+
+        assert_eq!(term.grid[Line(0)][Column(3)].c, 'd');
+        assert!(term.grid[Line(0)][Column(3)]
+            .flags
+            .contains(square::Flags::WRAPLINE));
+        // The area beyond the wrap column stays blank.
+        assert_eq!(term.grid[Line(0)][Column(4)].c, ' ');
+        assert_eq!(term.grid[Line(1)][Column(0)].c, 'e');
+        assert_eq!(term.grid[Line(1)][Column(1)].c, 'f');
+
+        // Wide chars are not split by the wrap column either.
+        term.goto(Line(2), Column(3));
+        term.input('中');
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(2)));
+        assert_eq!(term.grid[Line(2)][Column(0)].c, '中');
+    }
+
+    #[test]
+    fn soft_wrap_at_wrap_column_is_one_line() {
+        let mut term = Crosswords::new(10, 3, VoidListener {});
+        term.set_wrap_column(Some(6));
+        for c in "abcdefgh".chars() {
+            term.input(c);
+        }
+        let rows = |term: &Crosswords<VoidListener>| -> Vec<String> {
+            (0..2)
+                .map(|row| term.grid[Line(row)].into_iter().map(|s| s.c).collect())
+                .collect()
+        };
+        assert_eq!(rows(&term), ["abcdef    ", "gh        "]);
+
+        // Copied without the margin or a line break.
+        let copied = term
+            .bounds_to_string(Pos::new(Line(0), Column(0)), Pos::new(Line(1), Column(9)));
+        assert_eq!(copied, "abcdefgh");
+        assert!(crate::crosswords::export::is_wrapped(&term.grid[Line(0)]));
+
+        // Reflowed within the wrap column and back to it.
+        term.resize::<CrosswordsSize>(4, 3);
+        assert!(term.grid[Line(0)].is_wrapped());
+        term.resize::<CrosswordsSize>(10, 3);
+        assert_eq!(rows(&term), ["abcdef    ", "gh        "]);
+        assert_eq!(term.grid[Line(0)].wrap_column(), Some(Column(5)));
+    }
+
+    #[test]
+    fn insert_and_delete_lines_stay_in_the_scrolling_region() {
+        let mut term = Crosswords::new(5, 6, VoidListener {});
+        let column = |term: &Crosswords<VoidListener>| -> String {
+            (0..6)
+                .map(|row| term.grid[Line(row)][Column(0)].c)
+                .collect()
+        };
+        feed(&mut term, b"0\r\n1\r\n2\r\n3\r\n4\r\n5");
+
+        // Region from the second to the fifth line, cursor on the third.
+        feed(&mut term, b"\x1b[2;5r\x1b[3;4H\x1b[L");
+        assert_eq!(column(&term), "01 235");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(0)));
+
+        feed(&mut term, b"\x1b[3;4H\x1b[2M");
+        assert_eq!(column(&term), "013  5");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(2), Column(0)));
+
+        // Outside of the region nothing changes, the cursor stays too.
+        feed(&mut term, b"\x1b[6;3H\x1b[L\x1b[M");
+        assert_eq!(column(&term), "013  5");
+        assert_eq!(term.grid.cursor.pos, Pos::new(Line(5), Column(2)));
+    }
+
+    #[test]
+    fn linefeed_at_the_bottom_scrolls_the_region() {
+        let mut term = Crosswords::new(5, 4, VoidListener {});
+        let column = |term: &Crosswords<VoidListener>| -> String {
+            (0..4)
+                .map(|row| term.grid[Line(row)][Column(0)].c)
+                .collect()
+        };
+        feed(&mut term, b"0\r\n1\r\n2\r\n3");
+        assert_eq!(term.history_size(), 0);
+
+        // A full screen region pushes the top line into the scrollback.
+        feed(&mut term, b"\n");
+        assert_eq!(column(&term), "123 ");
+        assert_eq!(term.history_size(), 1);
+        assert_eq!(term.grid[Line(-1)][Column(0)].c, '0');
+        assert_eq!(term.grid.cursor.pos.row, Line(3));
+
+        // The top line of a restricted region is dropped, the lines around
+        // the region stay.
+        feed(&mut term, b"\rx\x1b[2;3r\x1b[3;1H\n");
+        assert_eq!(column(&term), "13 x");
+        assert_eq!(term.grid.cursor.pos.row, Line(2));
+        feed(&mut term, b"\x1b[1;3r\x1b[3;1H\n");
+        assert_eq!(column(&term), "3  x");
+        assert_eq!(term.grid.cursor.pos.row, Line(2));
+        assert_eq!(term.history_size(), 1);
+    }
+
+    #[test]
+    fn scrolled_back_viewport_stays_on_its_lines_during_output() {
+        let mut term = Crosswords::new(4, 3, VoidListener {});
+        let visible = |term: &mut Crosswords<VoidListener>| -> String {
+            let top = -(term.display_offset() as i32);
+            (top..top + 3)
+                .map(|row| term.grid[Line(row)][Column(0)].c)
+                .collect()
+        };
+        feed(&mut term, b"a\r\nb\r\nc\r\nd\r\ne\r\nf");
+        term.scroll_display(Scroll::Delta(2));
+        assert_eq!(visible(&mut term), "bcd");
+
+        // New lines push the viewport further back instead of moving what
+        // is being read.
+        feed(&mut term, b"\r\ng\r\nh");
+        assert_eq!(term.display_offset(), 4);
+        assert_eq!(visible(&mut term), "bcd");
+
+        // At the bottom the viewport follows the output.
+        term.scroll_display(Scroll::Bottom);
+        feed(&mut term, b"\r\ni");
+        assert_eq!(term.display_offset(), 0);
+        assert_eq!(visible(&mut term), "ghi");
+    }
+
+    #[test]
+    fn wide_chars_fit_a_single_column() {
+        let mut term = Crosswords::new(10, 6, VoidListener {});
+        feed(&mut term, "中a".as_bytes());
+
+        // Wide chars already on the grid are reflowed, new ones too.
+        term.resize::<CrosswordsSize>(1, 6);
+        feed(&mut term, "b文c".as_bytes());
+
+        let top = term.grid.topmost_line().0;
+        let column: String = (top..6)
+            .map(|row| term.grid[Line(row)][Column(0)].c)
+            .collect();
+        assert_eq!(column.trim_end(), "中ab文c");
+        for row in top..6 {
+            let flags = term.grid[Line(row)][Column(0)].flags;
+            assert!(!flags.intersects(
+                square::Flags::WIDE_CHAR
+                    | square::Flags::WIDE_CHAR_SPACER
+                    | square::Flags::LEADING_WIDE_CHAR_SPACER
+            ));
+        }
+        let cursor = term.grid.cursor.pos;
+        assert_eq!(cursor.col, Column(0));
+        assert_eq!(term.grid[cursor.row][Column(0)].c, 'c');
+        assert!(term.grid.cursor.should_wrap);
+    }
 }
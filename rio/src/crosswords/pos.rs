@@ -38,10 +38,14 @@ pub struct Cursor<T> {
     pub should_wrap: bool,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct CursorState {
     pub pos: Pos,
     pub content: CursorShape,
+
+    /// Fractional offset (0.0..1.0) inside the cursor column, used to draw
+    /// the beam cursor as a caret between cells.
+    pub column_offset: f32,
 }
 
 impl CursorState {
@@ -49,6 +53,7 @@ impl CursorState {
         CursorState {
             pos: Pos::default(),
             content: CursorShape::from_char(config.cursor),
+            column_offset: 0.0,
         }
     }
     pub fn is_visible(&self) -> bool {
@@ -98,6 +98,15 @@ pub struct CellExtra {
     hyperlink: Option<Hyperlink>,
 }
 
+/// Attributes used for the next printed characters, as set by SGR.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pen {
+    pub fg: AnsiColor,
+    pub bg: AnsiColor,
+    pub underline_color: Option<AnsiColor>,
+    pub flags: Flags,
+}
+
 /// Content and attributes of a single cell in the terminal grid.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Square {
@@ -231,8 +240,10 @@ impl LineLength for Row<Square> {
     fn line_length(&self) -> Column {
         let mut length = Column(0);
 
-        if self[Column(self.len() - 1)].flags.contains(Flags::WRAPLINE) {
-            return Column(self.len());
+        // A soft wrapped row is entirely part of the line, up to the column
+        // it wraps at.
+        if let Some(column) = self.wrap_column() {
+            return column + 1;
         }
 
         for (index, cell) in self[..].iter().rev().enumerate() {
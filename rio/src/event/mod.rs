@@ -63,6 +63,9 @@ pub enum RioEvent {
         Arc<dyn Fn(ColorRgb) -> String + Sync + Send + 'static>,
     ),
 
+    /// Change the color of a palette entry, `None` resets it.
+    ColorChange(usize, Option<ColorRgb>),
+
     /// Write some text to the PTY.
     PtyWrite(String),
 
@@ -91,6 +94,9 @@ impl Debug for RioEvent {
             RioEvent::ClipboardLoad(ty, _) => write!(f, "ClipboardLoad({ty:?})"),
             RioEvent::TextAreaSizeRequest(_) => write!(f, "TextAreaSizeRequest"),
             RioEvent::ColorRequest(index, _) => write!(f, "ColorRequest({index})"),
+            RioEvent::ColorChange(index, color) => {
+                write!(f, "ColorChange({index}, {color:?})")
+            }
             RioEvent::PtyWrite(text) => write!(f, "PtyWrite({text})"),
             RioEvent::Title(title) => write!(f, "Title({title})"),
             RioEvent::CursorBlinkingChange => write!(f, "CursorBlinkingChange"),
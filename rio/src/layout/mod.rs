@@ -3,8 +3,11 @@ pub mod mouse;
 use crate::crosswords::grid::Dimensions;
 use crate::crosswords::pos::{Column, Line, Pos};
 use crate::crosswords::{MIN_COLUMNS, MIN_VISIBLE_ROWS};
+use bitflags::bitflags;
 use mouse::{AccumulatedScroll, Mouse};
+use sugarloaf::components::rect::Rect;
 use sugarloaf::core::SugarloafStyle;
+use sugarloaf::font::Font;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Delta<T: Default> {
@@ -15,6 +18,39 @@ pub struct Delta<T: Default> {
 const PADDING_X: f32 = 10.0;
 const PADDING_X_TABS: f32 = 30.;
 const PADDING_Y: f32 = 50.0;
+const FONT_SIZE_STEP: f32 = 1.0;
+const MIN_FONT_SIZE: f32 = 6.0;
+const MAX_FONT_SIZE: f32 = 100.0;
+
+bitflags! {
+    /// Edges of the terminal area.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Edges: u8 {
+        const LEFT   = 0b0001;
+        const RIGHT  = 0b0010;
+        const TOP    = 0b0100;
+        const BOTTOM = 0b1000;
+    }
+}
+
+/// Border drawn on the edges of the terminal area, e.g. between split panes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Border {
+    pub edges: Edges,
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+impl Border {
+    #[inline]
+    fn width_at(&self, edge: Edges) -> f32 {
+        if self.edges.contains(edge) {
+            self.width
+        } else {
+            0.
+        }
+    }
+}
 
 pub struct Layout {
     scale_factor: f32,
@@ -23,10 +59,15 @@ pub struct Layout {
     pub width_u32: u32,
     pub height_u32: u32,
     pub font_size: f32,
+    font_size_ref: f32,
+    /// Width of the columns for each pixel of font size.
+    cell_width: f32,
     pub mouse: Mouse,
     pub columns: usize,
     pub rows: usize,
+    reported_size: Option<(usize, usize)>,
     padding: Delta<f32>,
+    border: Border,
     pub styles: Styles,
 }
 
@@ -34,6 +75,7 @@ pub struct Layout {
 pub struct Styles {
     pub term: SugarloafStyle,
     pub tabs_initial_position: f32,
+    pub borders: Vec<Rect>,
 }
 
 impl Dimensions for Layout {
@@ -53,12 +95,51 @@ impl Dimensions for Layout {
     }
 }
 
+fn border_rects(layout: &Layout) -> Vec<Rect> {
+    let border = layout.border;
+    let width = layout.width / layout.scale_factor;
+    let height = layout.height / layout.scale_factor;
+
+    let mut rects = vec![];
+    if border.edges.contains(Edges::LEFT) {
+        rects.push(Rect {
+            position: [0., 0.],
+            color: border.color,
+            size: [border.width, height],
+        });
+    }
+    if border.edges.contains(Edges::RIGHT) {
+        rects.push(Rect {
+            position: [width - border.width, 0.],
+            color: border.color,
+            size: [border.width, height],
+        });
+    }
+    if border.edges.contains(Edges::TOP) {
+        rects.push(Rect {
+            position: [0., 0.],
+            color: border.color,
+            size: [width, border.width],
+        });
+    }
+    if border.edges.contains(Edges::BOTTOM) {
+        rects.push(Rect {
+            position: [0., height - border.width],
+            color: border.color,
+            size: [width, border.width],
+        });
+    }
+    rects
+}
+
 fn update_styles(layout: &mut Layout) {
     let new_styles = Styles {
         term: SugarloafStyle {
             screen_position: (
-                layout.padding.x * layout.scale_factor,
-                (layout.padding.y * layout.scale_factor),
+                (layout.padding.x + layout.border.width_at(Edges::LEFT))
+                    * layout.scale_factor,
+                (layout.padding.y + layout.border.width_at(Edges::TOP))
+                    * layout.scale_factor,
             ),
             bounds: (
                 layout.width * layout.scale_factor,
@@ -67,6 +148,7 @@ fn update_styles(layout: &mut Layout) {
             text_scale: layout.font_size * layout.scale_factor,
         },
         tabs_initial_position: (layout.width / layout.scale_factor) - PADDING_X_TABS,
+        borders: border_rects(layout),
     };
     layout.styles = new_styles;
 }
@@ -82,14 +164,18 @@ impl Layout {
             height_u32: height as u32,
             columns: 80,
             rows: 25,
+            reported_size: None,
             scale_factor,
             font_size,
+            font_size_ref: font_size,
+            cell_width: 0.5,
             mouse: Mouse::default(),
             styles,
             padding: Delta {
                 x: PADDING_X,
                 y: PADDING_Y,
             },
+            border: Border::default(),
         };
 
         update_styles(&mut layout);
@@ -116,6 +202,37 @@ impl Layout {
         self
     }
 
+    /// Columns are as wide as the advance of `font` at the font size.
+    pub fn set_cell_width(&mut self, font: &Font) -> &mut Self {
+        self.cell_width = font.cell_metrics(1.).advance;
+        self
+    }
+
+    /// Draws a border on `edges`, the terminal area shrinks by its width.
+    pub fn set_border(&mut self, edges: Edges, width: f32, color: [f32; 4]) -> &mut Self {
+        self.border = Border {
+            edges,
+            width,
+            color,
+        };
+        self
+    }
+
+    pub fn increase_font_size(&mut self) -> &mut Self {
+        self.font_size = f32::min(self.font_size + FONT_SIZE_STEP, MAX_FONT_SIZE);
+        self
+    }
+
+    pub fn decrease_font_size(&mut self) -> &mut Self {
+        self.font_size = f32::max(self.font_size - FONT_SIZE_STEP, MIN_FONT_SIZE);
+        self
+    }
+
+    pub fn reset_font_size(&mut self) -> &mut Self {
+        self.font_size = self.font_size_ref;
+        self
+    }
+
     pub fn update(&mut self) -> &mut Self {
         update_styles(self);
         self
@@ -132,11 +249,14 @@ impl Layout {
     #[inline]
     pub fn mouse_position(&self, display_offset: usize) -> Pos {
         let text_scale = (self.styles.term.text_scale as usize) + 1;
-        let col =
-            self.mouse.x.saturating_sub(PADDING_X as usize) / self.font_size as usize;
+        let border_x = (self.border.width_at(Edges::LEFT) * self.scale_factor) as usize;
+        let border_y = (self.border.width_at(Edges::TOP) * self.scale_factor) as usize;
+        let col = self.mouse.x.saturating_sub(PADDING_X as usize + border_x)
+            / self.font_size as usize;
         let col = std::cmp::min(Column(col), Column(self.columns));
 
-        let line = self.mouse.y.saturating_sub(PADDING_Y as usize) / text_scale;
+        let line =
+            self.mouse.y.saturating_sub(PADDING_Y as usize + border_y) / text_scale;
         let line = std::cmp::min(line, self.rows - 1);
 
         let point = Pos::new(line, col);
@@ -150,11 +270,14 @@ impl Layout {
     pub fn compute(&mut self) -> (usize, usize) {
         let (padding_x, padding_y) = self.padding();
         let mut rows = (self.height - padding_y) / self.scale_factor;
+        rows -= self.border.width_at(Edges::TOP) + self.border.width_at(Edges::BOTTOM);
         rows /= self.font_size;
         let visible_rows = std::cmp::max(rows as usize, MIN_VISIBLE_ROWS);
 
         let mut visible_columns = (self.width - 2. * padding_x) / self.scale_factor;
-        visible_columns /= self.font_size / 2.;
+        visible_columns -=
+            self.border.width_at(Edges::LEFT) + self.border.width_at(Edges::RIGHT);
+        visible_columns /= self.font_size * self.cell_width;
         let visible_columns = std::cmp::max(visible_columns as usize, MIN_COLUMNS);
 
         self.columns = visible_columns;
@@ -162,4 +285,159 @@ impl Layout {
 
         (visible_columns, visible_rows)
     }
+
+    /// Columns and rows from the last `compute`, only when they changed since
+    /// the last time they were taken. Pixel resizes that keep the same grid
+    /// size return `None`, so the PTY doesn't need to be resized.
+    pub fn take_pending_resize(&mut self) -> Option<(u16, u16)> {
+        let size = (self.columns, self.rows);
+        if self.reported_size == Some(size) {
+            return None;
+        }
+
+        self.reported_size = Some(size);
+        Some((size.0 as u16, size.1 as u16))
+    }
+}
+
+/// Window size in physical pixels that fits `cols` x `rows` cells of `font`
+/// at `font_size`, so the window can be created at the right size before
+/// the layout exists. `padding` (top, right, bottom, left) and `bar_height`
+/// are in logical pixels, like the font size.
+#[allow(dead_code)]
+pub fn pixel_size_for(
+    cols: u16,
+    rows: u16,
+    font: &Font,
+    font_size: f32,
+    padding: (f32, f32, f32, f32),
+    bar_height: f32,
+    scale_factor: f64,
+) -> winit::dpi::PhysicalSize<u32> {
+    let scale_factor = scale_factor as f32;
+    let (top, right, bottom, left) = padding;
+    let cell_width = font.cell_metrics(font_size).advance;
+    let width = (cols as f32 * cell_width + left + right) * scale_factor;
+    let height = (rows as f32 * font_size + top + bottom + bar_height) * scale_factor;
+    winit::dpi::PhysicalSize::new(width.ceil() as u32, height.ceil() as u32)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_change_font_size() {
+        let mut layout = Layout::new(600.0, 400.0, 1.0, 16.0);
+        let (columns, rows) = layout.compute();
+
+        layout.increase_font_size().update();
+        assert_eq!(layout.font_size, 17.0);
+        assert_eq!(layout.styles.term.text_scale, 17.0);
+        let (larger_columns, larger_rows) = layout.compute();
+        assert!(larger_columns < columns);
+        assert!(larger_rows <= rows);
+
+        layout.decrease_font_size().decrease_font_size().update();
+        assert_eq!(layout.font_size, 15.0);
+
+        layout.reset_font_size().update();
+        assert_eq!(layout.font_size, 16.0);
+        assert_eq!(layout.compute(), (columns, rows));
+    }
+
+    #[test]
+    fn test_border_reduces_columns() {
+        let mut layout = Layout::new(600.0, 400.0, 1.0, 16.0);
+        let (columns, rows) = layout.compute();
+
+        // Columns are 8px wide (half of the font size)
+        layout
+            .set_border(Edges::LEFT, 16.0, [1.0, 1.0, 1.0, 1.0])
+            .update();
+        assert_eq!(layout.compute(), (columns - 2, rows));
+        assert_eq!(layout.styles.borders.len(), 1);
+        assert_eq!(layout.styles.borders[0].position, [0., 0.]);
+        assert_eq!(layout.styles.borders[0].size, [16.0, 400.0]);
+        assert_eq!(layout.styles.term.screen_position.0, PADDING_X + 16.0);
+
+        layout.set_border(Edges::empty(), 16.0, [1.0; 4]).update();
+        assert_eq!(layout.compute(), (columns, rows));
+        assert!(layout.styles.borders.is_empty());
+    }
+
+    #[test]
+    fn test_columns_are_as_wide_as_the_font_advance() {
+        let font = Font::new(
+            sugarloaf::font::DEFAULT_FONT_NAME.to_string(),
+            sugarloaf::font::FontSynthesis::default(),
+        )
+        .unwrap();
+        let mut layout = Layout::new(600.0, 400.0, 1.0, 16.0);
+        let (columns, rows) = layout.set_cell_width(&font).compute();
+
+        let advance = font.cell_metrics(16.0).advance;
+        assert_eq!(columns, ((600.0 - 2. * PADDING_X) / advance) as usize);
+        assert_eq!(rows, Layout::new(600.0, 400.0, 1.0, 16.0).compute().1);
+    }
+
+    #[test]
+    fn test_font_size_limits() {
+        let mut layout = Layout::new(600.0, 400.0, 1.0, MIN_FONT_SIZE);
+        layout.decrease_font_size();
+        assert_eq!(layout.font_size, MIN_FONT_SIZE);
+
+        let mut layout = Layout::new(600.0, 400.0, 1.0, MAX_FONT_SIZE);
+        layout.increase_font_size();
+        assert_eq!(layout.font_size, MAX_FONT_SIZE);
+    }
+
+    #[test]
+    fn test_pixel_size_for_columns_and_rows() {
+        let font = Font::new(
+            sugarloaf::font::DEFAULT_FONT_NAME.to_string(),
+            sugarloaf::font::FontSynthesis::default(),
+        )
+        .unwrap();
+        // The padding of the layout, the tab bar is in its top padding.
+        let padding = (0.0, PADDING_X, 0.0, PADDING_X);
+        let size = pixel_size_for(80, 24, &font, 16.0, padding, PADDING_Y, 1.0);
+        assert_eq!(size, winit::dpi::PhysicalSize::new(666, 434));
+
+        for scale_factor in [1.0, 2.0] {
+            let size =
+                pixel_size_for(80, 24, &font, 16.0, padding, PADDING_Y, scale_factor);
+            let mut layout = Layout::new(
+                size.width as f32,
+                size.height as f32,
+                scale_factor as f32,
+                16.0,
+            );
+            assert_eq!(layout.set_cell_width(&font).compute(), (80, 24));
+        }
+    }
+
+    #[test]
+    fn test_take_pending_resize_only_on_grid_change() {
+        let mut layout = Layout::new(600.0, 400.0, 1.0, 16.0);
+        let (columns, rows) = layout.compute();
+        assert_eq!(
+            layout.take_pending_resize(),
+            Some((columns as u16, rows as u16))
+        );
+        assert_eq!(layout.take_pending_resize(), None);
+
+        // Columns are 8px wide, growing by less than two columns changes
+        // the grid size at most once.
+        let mut changes = 0;
+        for width in [601, 603, 605, 607, 609, 611] {
+            layout.set_size(width, 400).update();
+            layout.compute();
+            if layout.take_pending_resize().is_some() {
+                changes += 1;
+            }
+        }
+        assert_eq!(changes, 1);
+        assert_eq!(layout.columns, columns + 1);
+    }
 }
@@ -1,3 +1,18 @@
+/// The graphics `$headless` gives a test, which is skipped when the machine
+/// has no graphics adapter (`None`).
+#[cfg(test)]
+macro_rules! headless_or_skip {
+    ($headless:expr) => {
+        match $headless {
+            Some(headless) => headless,
+            None => {
+                eprintln!("skipped, no graphics adapter");
+                return;
+            }
+        }
+    };
+}
+
 mod ansi;
 mod clipboard;
 mod crosswords;
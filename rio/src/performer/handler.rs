@@ -11,6 +11,7 @@ use crate::ansi::control::C0;
 use crate::ansi::{ClearMode, LineClearMode, TabulationClearMode};
 use colors::{AnsiColor, NamedColor};
 use std::fmt::Write;
+use std::path::PathBuf;
 
 // https://vt100.net/emu/dec_ansi_parser
 use vte::{Params, ParamsIter};
@@ -29,6 +30,19 @@ const SYNC_START_ESCAPE_START: [u8; SYNC_ESCAPE_START_LEN] =
 const SYNC_END_ESCAPE_START: [u8; SYNC_ESCAPE_START_LEN] =
     [b'\x1b', b'P', b'=', b'2', b's'];
 
+/// Decodes a hex encoded string, like the names of XTGETTCAP requests.
+fn parse_hex_string(hex: &[u8]) -> Option<String> {
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = hex
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
 fn xparse_color(color: &[u8]) -> Option<ColorRgb> {
     if !color.is_empty() && color[0] == b'#' {
         parse_legacy_color(&color[1..])
@@ -85,6 +99,26 @@ fn parse_legacy_color(color: &[u8]) -> Option<ColorRgb> {
     })
 }
 
+/// Path of a `file://host/path` URL, as reported by shells with OSC 7. The
+/// host is ignored and percent-encoded bytes are decoded.
+fn parse_file_url(url: &[u8]) -> Option<PathBuf> {
+    let url = url.strip_prefix(b"file://")?;
+    let path = &url[url.iter().position(|byte| *byte == b'/')?..];
+
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut bytes = path.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [*bytes.next()?, *bytes.next()?];
+            decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
 fn parse_number(input: &[u8]) -> Option<u8> {
     if input.is_empty() {
         return None;
@@ -129,12 +163,18 @@ pub trait Handler {
     /// OSC to set window title.
     fn set_title(&mut self, _: Option<String>) {}
 
+    /// OSC 7 to report the current working directory.
+    fn set_current_directory(&mut self, _: PathBuf) {}
+
     /// Set the cursor style.
     //fn set_cursor_style(&mut self, _: Option<CursorStyle>) {}
 
     /// Set the cursor shape.
     fn set_cursor_shape(&mut self, _shape: CursorShape) {}
 
+    /// Start or stop blinking the cursor.
+    fn set_cursor_blinking(&mut self, _blinking: bool) {}
+
     /// A character to be displayed.
     fn input(&mut self, _c: char) {}
 
@@ -186,6 +226,11 @@ pub trait Handler {
     /// Linefeed.
     fn linefeed(&mut self) {}
 
+    /// Form feed, a linefeed unless the terminal is told otherwise.
+    fn form_feed(&mut self) {
+        self.linefeed();
+    }
+
     /// Ring the bell.
     ///
     /// Hopefully this is never implemented.
@@ -194,6 +239,11 @@ pub trait Handler {
     /// Substitute char under cursor.
     fn substitute(&mut self) {}
 
+    /// C0 control without an action of its own (e.g. NUL).
+    fn control_char(&mut self, byte: u8) {
+        warn!("[unhandled] execute byte={byte:02x}");
+    }
+
     /// Newline.
     fn newline(&mut self) {}
 
@@ -314,6 +364,9 @@ pub trait Handler {
 
     /// Report text area size in characters.
     fn text_area_size_chars(&mut self) {}
+
+    /// Report a terminfo capability (XTGETTCAP).
+    fn report_capability(&mut self, _name: &str) {}
 }
 
 #[derive(Debug, Default)]
@@ -324,6 +377,15 @@ struct ProcessorState {
     /// DCS sequence waiting for termination.
     dcs: Option<Dcs>,
 
+    /// Data received for the pending DCS sequence.
+    dcs_data: Vec<u8>,
+
+    /// Strings longer than this are discarded.
+    max_string_length: usize,
+
+    /// Skip the next OSC dispatch, it's an aborted string.
+    discard_osc: bool,
+
     /// State for synchronized terminal updates.
     sync_state: SyncState,
 }
@@ -353,6 +415,9 @@ impl Default for SyncState {
     }
 }
 
+/// Default maximum length of OSC and DCS strings (1MiB).
+pub const MAX_STRING_LENGTH: usize = 0x10_0000;
+
 /// Pending DCS sequence.
 #[derive(Debug)]
 enum Dcs {
@@ -361,12 +426,79 @@ enum Dcs {
 
     /// End of the synchronized update.
     SyncEnd,
+
+    /// Request of terminfo capabilities (XTGETTCAP).
+    GetCapabilities,
+}
+
+/// Tracks OSC strings, the parser keeps them in memory until terminated.
+#[derive(Debug, Default)]
+struct OscLimit {
+    /// Last byte was an ESC.
+    escape: bool,
+
+    /// Length of the OSC string being parsed.
+    length: Option<usize>,
+
+    /// Dropping the rest of an oversized string until its terminator.
+    discarding: bool,
+}
+
+/// What to do with a byte after checking the OSC string length.
+#[derive(Debug)]
+enum OscByte {
+    Parse,
+    /// The string went over the limit and has to be aborted.
+    Abort,
+    /// Part of an aborted string.
+    Discard,
+}
+
+impl OscLimit {
+    fn advance(&mut self, byte: u8, max_length: usize) -> OscByte {
+        if self.discarding {
+            match byte {
+                C0::BEL | C0::CAN | C0::SUB => {
+                    self.discarding = false;
+                    return OscByte::Discard;
+                }
+                C0::ESC => self.discarding = false,
+                _ => return OscByte::Discard,
+            }
+        }
+
+        match self.length {
+            Some(length) => {
+                match byte {
+                    C0::BEL | C0::CAN | C0::SUB | C0::ESC => self.length = None,
+                    _ if length >= max_length => {
+                        warn!("[osc] string is longer than {max_length} bytes, discarding it");
+                        self.length = None;
+                        self.discarding = true;
+                        return OscByte::Abort;
+                    }
+                    _ => self.length = Some(length + 1),
+                }
+            }
+            None if self.escape && byte == b']' => self.length = Some(0),
+            None => (),
+        }
+
+        self.escape = byte == C0::ESC;
+        OscByte::Parse
+    }
 }
 
-#[derive(Default)]
 pub struct ParserProcessor {
     state: ProcessorState,
     parser: vte::Parser,
+    osc_limit: OscLimit,
+}
+
+impl Default for ParserProcessor {
+    fn default() -> Self {
+        Self::with_max_string_length(MAX_STRING_LENGTH)
+    }
 }
 
 impl ParserProcessor {
@@ -376,6 +508,18 @@ impl ParserProcessor {
         Self::default()
     }
 
+    /// OSC and DCS strings longer than `max_string_length` are discarded.
+    pub fn with_max_string_length(max_string_length: usize) -> Self {
+        Self {
+            state: ProcessorState {
+                max_string_length,
+                ..ProcessorState::default()
+            },
+            parser: vte::Parser::new(),
+            osc_limit: OscLimit::default(),
+        }
+    }
+
     /// Process a new byte from the PTY.
     #[inline]
     pub fn advance<H>(&mut self, handler: &mut H, byte: u8)
@@ -383,13 +527,31 @@ impl ParserProcessor {
         H: Handler,
     {
         if self.state.sync_state.timeout.is_none() {
-            let mut performer = Performer::new(&mut self.state, handler);
-            self.parser.advance(&mut performer, byte);
+            self.parse(handler, byte);
         } else {
             self.advance_sync(handler, byte);
         }
     }
 
+    #[inline]
+    fn parse<H>(&mut self, handler: &mut H, byte: u8)
+    where
+        H: Handler,
+    {
+        let byte = match self.osc_limit.advance(byte, self.state.max_string_length) {
+            OscByte::Parse => byte,
+            OscByte::Abort => {
+                // Leaving the OSC string dispatches it, even when it's cancelled.
+                self.state.discard_osc = true;
+                C0::CAN
+            }
+            OscByte::Discard => return,
+        };
+
+        let mut performer = Performer::new(&mut self.state, handler);
+        self.parser.advance(&mut performer, byte);
+    }
+
     /// End a synchronized update.
     pub fn stop_sync<H>(&mut self, handler: &mut H)
     where
@@ -398,8 +560,7 @@ impl ParserProcessor {
         // Process all synchronized bytes.
         for i in 0..self.state.sync_state.buffer.len() {
             let byte = self.state.sync_state.buffer[i];
-            let mut performer = Performer::new(&mut self.state, handler);
-            self.parser.advance(&mut performer, byte);
+            self.parse(handler, byte);
         }
 
         // Resetting state after processing makes sure we don't interpret buffered sync escapes.
@@ -466,7 +627,7 @@ impl ParserProcessor {
                         Some(Instant::now() + SYNC_UPDATE_TIMEOUT);
                 }
                 Some(Dcs::SyncEnd) => self.stop_sync(handler),
-                None => (),
+                Some(Dcs::GetCapabilities) | None => (),
             },
         }
     }
@@ -502,12 +663,13 @@ impl<U: Handler> vte::Perform for Performer<'_, U> {
             C0::HT => self.handler.put_tab(1),
             C0::BS => self.handler.backspace(),
             C0::CR => self.handler.carriage_return(),
-            C0::LF | C0::VT | C0::FF => self.handler.linefeed(),
+            C0::LF | C0::VT => self.handler.linefeed(),
+            C0::FF => self.handler.form_feed(),
             C0::BEL => self.handler.bell(),
             C0::SUB => self.handler.substitute(),
             C0::SI => self.handler.set_active_charset(CharsetIndex::G0),
             C0::SO => self.handler.set_active_charset(CharsetIndex::G1),
-            _ => warn!("[unhandled] execute byte={byte:02x}"),
+            _ => self.handler.control_char(byte),
         }
     }
 
@@ -530,6 +692,10 @@ impl<U: Handler> vte::Perform for Performer<'_, U> {
                     self.state.dcs = Some(Dcs::SyncStart);
                 }
             }
+            ('q', [b'+']) => {
+                self.state.dcs = Some(Dcs::GetCapabilities);
+                self.state.dcs_data.clear();
+            }
             _ => warn!(
                 "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, action: {:?}",
                 params, intermediates, ignore, action
@@ -537,8 +703,18 @@ impl<U: Handler> vte::Perform for Performer<'_, U> {
         }
     }
 
-    fn put(&mut self, _byte: u8) {
-        info!("[put] {_byte:02x}");
+    fn put(&mut self, byte: u8) {
+        info!("[put] {byte:02x}");
+
+        if let Some(Dcs::GetCapabilities) = self.state.dcs {
+            if self.state.dcs_data.len() < self.state.max_string_length {
+                self.state.dcs_data.push(byte);
+            } else {
+                warn!("[put] DCS string is too long, discarding it");
+                self.state.dcs = None;
+                self.state.dcs_data.clear();
+            }
+        }
     }
 
     #[inline]
@@ -549,11 +725,26 @@ impl<U: Handler> vte::Perform for Performer<'_, U> {
                     Some(Instant::now() + SYNC_UPDATE_TIMEOUT);
             }
             Some(Dcs::SyncEnd) => (),
+            Some(Dcs::GetCapabilities) => {
+                // Names are hex encoded and separated by semicolons.
+                for hex_name in self.state.dcs_data.split(|byte| *byte == b';') {
+                    match parse_hex_string(hex_name) {
+                        Some(name) => self.handler.report_capability(&name),
+                        None => warn!("[unhandled unhook] invalid capability name"),
+                    }
+                }
+                self.state.dcs_data.clear();
+            }
             _ => warn!("[unhandled unhook]"),
         }
+        self.state.dcs = None;
     }
 
     fn osc_dispatch(&mut self, params: &[&[u8]], bell_terminated: bool) {
+        if std::mem::take(&mut self.state.discard_osc) {
+            return;
+        }
+
         info!("[osc_dispatch] params={params:?} bell_terminated={bell_terminated}");
 
         let terminator = if bell_terminated { "\x07" } else { "\x1b\\" };
@@ -591,6 +782,12 @@ impl<U: Handler> vte::Perform for Performer<'_, U> {
                 unhandled(params);
             }
 
+            // Report the current working directory.
+            b"7" => match params.get(1).and_then(|url| parse_file_url(url)) {
+                Some(path) => self.handler.set_current_directory(path),
+                None => unhandled(params),
+            },
+
             // Set color index.
             b"4" => {
                 if params.len() <= 1 || params.len() % 2 == 0 {
@@ -847,6 +1044,8 @@ impl<U: Handler> vte::Perform for Performer<'_, U> {
                     }
                 };
                 handler.set_cursor_shape(shape);
+                // Odd styles blink, 0 is the default steady block.
+                handler.set_cursor_blinking(cursor_style_id % 2 == 1);
             }
             ('r', []) => {
                 let top = next_param_or(1) as usize;
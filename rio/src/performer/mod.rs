@@ -30,6 +30,7 @@ pub struct Machine<T: teletypewriter::EventedPty, U: EventListener> {
     poll: mio::Poll,
     terminal: Arc<FairMutex<Crosswords<U>>>,
     event_proxy: U,
+    max_string_length: usize,
 }
 
 #[derive(Default)]
@@ -107,6 +108,7 @@ where
         terminal: Arc<FairMutex<Crosswords<U>>>,
         pty: T,
         event_proxy: U,
+        max_string_length: usize,
     ) -> Result<Machine<T, U>, Box<dyn std::error::Error>> {
         // let (mut sender, mut receiver) = unbounded::<Msg>();
         let (sender, receiver) = channel::channel();
@@ -119,6 +121,7 @@ where
             pty,
             terminal,
             event_proxy,
+            max_string_length,
         })
     }
 
@@ -257,7 +260,12 @@ where
 
     pub fn spawn(mut self) {
         tokio::spawn(async move {
-            let mut state = State::default();
+            let mut state = State {
+                parser: handler::ParserProcessor::with_max_string_length(
+                    self.max_string_length,
+                ),
+                ..State::default()
+            };
             let mut buf = [0u8; READ_BUFFER_SIZE];
 
             let mut tokens = (0..).map(Into::into);
@@ -23,6 +23,158 @@ pub enum Topic {
     #[allow(dead_code)]
     SelectionScrolling,
     Frame,
+    Redraw,
+}
+
+/// Limits renders triggered by terminal updates to one per frame interval,
+/// so heavy output is coalesced instead of rendering after every read.
+pub struct FrameThrottle {
+    interval: Duration,
+    last_frame: Option<Instant>,
+}
+
+impl FrameThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_frame: None,
+        }
+    }
+
+    /// Delay until the next frame can be rendered, `None` if it can be rendered now.
+    pub fn next_frame_delay(&self, now: Instant) -> Option<Duration> {
+        let last_frame = self.last_frame?;
+        let elapsed = now.saturating_duration_since(last_frame);
+        if elapsed >= self.interval {
+            None
+        } else {
+            Some(self.interval - elapsed)
+        }
+    }
+
+    pub fn frame_rendered(&mut self, now: Instant) {
+        self.last_frame = Some(now);
+    }
+}
+
+/// Blinking cursor, it toggles every `interval` since `start`.
+struct CursorBlink {
+    start: Instant,
+    interval: Duration,
+}
+
+impl CursorBlink {
+    fn toggles(&self, now: Instant) -> u128 {
+        now.saturating_duration_since(self.start).as_nanos() / self.interval.as_nanos()
+    }
+}
+
+/// Soonest redraw needed by the running animations (cursor blink, visual
+/// bell, flashes), so the event loop sleeps until then instead of rendering
+/// every frame. Redraws are at least `min_interval` apart.
+pub struct RedrawSchedule {
+    min_interval: Duration,
+    last_redraw: Option<Instant>,
+    animating: bool,
+    cursor_blink: Option<CursorBlink>,
+    /// Animations are paused since then.
+    paused_at: Option<Instant>,
+}
+
+impl RedrawSchedule {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_redraw: None,
+            animating: false,
+            cursor_blink: None,
+            paused_at: None,
+        }
+    }
+
+    /// Pauses or resumes the cursor blink and the animations, e.g. while
+    /// the window is occluded, so nothing wakes the event loop up. Paused,
+    /// the cursor stays shown and animations hold the frame they were at.
+    pub fn set_animations_enabled(&mut self, enabled: bool, now: Instant) {
+        if enabled {
+            self.paused_at = None;
+        } else if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Time a frame drawn at `now` shows, it stands still while animations
+    /// are paused.
+    pub fn frame_time(&self, now: Instant) -> Instant {
+        self.paused_at.unwrap_or(now)
+    }
+
+    /// An animation that changes every frame is running, e.g. a fading flash.
+    pub fn set_animating(&mut self, animating: bool) {
+        self.animating = animating;
+    }
+
+    /// Starts or stops blinking the cursor, the blink only restarts (shown)
+    /// when the interval changes.
+    pub fn set_cursor_blink(&mut self, interval: Option<Duration>, now: Instant) {
+        let interval = interval.filter(|interval| !interval.is_zero());
+        if self.cursor_blink.as_ref().map(|blink| blink.interval) != interval {
+            self.cursor_blink = interval.map(|interval| CursorBlink {
+                start: now,
+                interval,
+            });
+        }
+    }
+
+    /// The cursor is in the shown half of its blink at `now`.
+    pub fn is_cursor_shown(&self, now: Instant) -> bool {
+        self.paused_at.is_some()
+            || self
+                .cursor_blink
+                .as_ref()
+                .map_or(true, |blink| blink.toggles(now) % 2 == 0)
+    }
+
+    /// Shows the blinking cursor for a whole interval again, e.g. after a
+    /// key press, it returns whether the cursor was hidden.
+    pub fn reset_cursor_blink(&mut self, now: Instant) -> bool {
+        let was_hidden = !self.is_cursor_shown(now);
+        if let Some(blink) = &mut self.cursor_blink {
+            blink.start = now;
+        }
+        was_hidden
+    }
+
+    pub fn redrawn(&mut self, now: Instant) {
+        self.last_redraw = Some(now);
+    }
+
+    /// When the blinking cursor toggles after the last redraw.
+    pub fn next_blink_deadline(&self) -> Option<Instant> {
+        let last_redraw = self.last_redraw?;
+        let blink = self.cursor_blink.as_ref()?;
+        let toggles = blink.toggles(last_redraw) as u32 + 1;
+        Some(blink.start + blink.interval * toggles)
+    }
+
+    /// When the next frame has to be drawn, `None` while nothing animates.
+    pub fn next_redraw_at(&self) -> Option<Instant> {
+        if self.paused_at.is_some() {
+            return None;
+        }
+        let last_redraw = self.last_redraw?;
+        let earliest = last_redraw + self.min_interval;
+
+        let next = match (
+            self.animating.then_some(earliest),
+            self.next_blink_deadline(),
+        ) {
+            (Some(frame), Some(toggle)) => Some(frame.min(toggle)),
+            (frame, toggle) => frame.or(toggle),
+        };
+
+        next.map(|next| next.max(earliest))
+    }
 }
 
 /// Event scheduled to be emitted at a specific time.
@@ -101,7 +253,6 @@ impl Scheduler {
     }
 
     /// Cancel a scheduled event.
-    #[allow(dead_code)]
     pub fn unschedule(&mut self, id: TimerId) -> Option<Timer> {
         let index = self.timers.iter().position(|timer| timer.id == id)?;
         self.timers.remove(index)
@@ -121,3 +272,125 @@ impl Scheduler {
         self.timers.retain(|timer| timer.id.tab_id != tab_id);
     }
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_frame_throttle_coalesces_updates() {
+        let interval = Duration::from_millis(16);
+        let mut throttle = FrameThrottle::new(interval);
+        let start = Instant::now();
+
+        // The first frame is never delayed
+        assert_eq!(throttle.next_frame_delay(start), None);
+        throttle.frame_rendered(start);
+
+        // Updates within the interval wait for the same next frame
+        let mut renders = 0;
+        for i in 1..10 {
+            let now = start + Duration::from_millis(i);
+            match throttle.next_frame_delay(now) {
+                Some(delay) => assert_eq!(now + delay, start + interval),
+                None => renders += 1,
+            }
+        }
+        assert_eq!(renders, 0);
+
+        // Once the interval has passed the pending frame is rendered
+        assert_eq!(throttle.next_frame_delay(start + interval), None);
+        throttle.frame_rendered(start + interval);
+        assert_eq!(
+            throttle.next_frame_delay(start + interval + Duration::from_millis(6)),
+            Some(Duration::from_millis(10))
+        );
+    }
+
+    #[test]
+    fn test_next_redraw_is_the_next_cursor_blink() {
+        let interval = Duration::from_millis(500);
+        let mut schedule = RedrawSchedule::new(Duration::from_millis(16));
+        let start = Instant::now();
+        schedule.set_cursor_blink(Some(interval), start);
+
+        schedule.redrawn(start + Duration::from_millis(120));
+        assert_eq!(schedule.next_redraw_at(), Some(start + interval));
+        assert!(schedule.is_cursor_shown(start + Duration::from_millis(120)));
+
+        // Redrawn at the toggle, the cursor is hidden until the next one.
+        schedule.redrawn(start + interval);
+        assert_eq!(schedule.next_redraw_at(), Some(start + interval * 2));
+        assert!(!schedule.is_cursor_shown(start + interval));
+
+        // The same interval keeps the phase.
+        schedule.set_cursor_blink(Some(interval), start + interval);
+        assert_eq!(schedule.next_redraw_at(), Some(start + interval * 2));
+
+        schedule.set_cursor_blink(None, start + interval);
+        assert_eq!(schedule.next_redraw_at(), None);
+        assert!(schedule.is_cursor_shown(start + interval));
+    }
+
+    #[test]
+    fn test_key_press_keeps_the_cursor_solid() {
+        let interval = Duration::from_millis(500);
+        let mut schedule = RedrawSchedule::new(Duration::from_millis(16));
+        let start = Instant::now();
+        assert!(!schedule.reset_cursor_blink(start));
+
+        schedule.set_cursor_blink(Some(interval), start);
+        let key_press = start + Duration::from_millis(700);
+        assert!(!schedule.is_cursor_shown(key_press));
+        assert!(schedule.reset_cursor_blink(key_press));
+        assert!(schedule.is_cursor_shown(key_press));
+
+        schedule.redrawn(key_press);
+        assert_eq!(schedule.next_blink_deadline(), Some(key_press + interval));
+        assert!(!schedule.reset_cursor_blink(key_press + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_animations_are_coalesced_to_the_min_interval() {
+        let min_interval = Duration::from_millis(16);
+        let mut schedule = RedrawSchedule::new(min_interval);
+        let start = Instant::now();
+        assert_eq!(schedule.next_redraw_at(), None);
+
+        schedule.set_cursor_blink(Some(Duration::from_millis(5)), start);
+        schedule.set_animating(true);
+        schedule.redrawn(start);
+        assert_eq!(schedule.next_redraw_at(), Some(start + min_interval));
+
+        schedule.set_cursor_blink(Some(Duration::from_millis(500)), start);
+        assert_eq!(schedule.next_redraw_at(), Some(start + min_interval));
+    }
+
+    #[test]
+    fn test_paused_animations_hold_still() {
+        let interval = Duration::from_millis(500);
+        let mut schedule = RedrawSchedule::new(Duration::from_millis(16));
+        let start = Instant::now();
+        schedule.set_cursor_blink(Some(interval), start);
+        schedule.set_animating(true);
+        schedule.set_animations_enabled(false, start + interval);
+
+        // Drawn again and again, the cursor stays shown and nothing else
+        // is scheduled.
+        for toggle in 1..6 {
+            let now = start + interval * toggle;
+            assert!(schedule.is_cursor_shown(schedule.frame_time(now)));
+            assert_eq!(schedule.frame_time(now), start + interval);
+            schedule.redrawn(now);
+            assert_eq!(schedule.next_redraw_at(), None);
+        }
+
+        let now = start + interval * 6;
+        schedule.set_animations_enabled(true, now);
+        assert_eq!(schedule.frame_time(now), now);
+        assert_eq!(
+            schedule.next_redraw_at(),
+            Some(start + interval * 5 + Duration::from_millis(16))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+/// Flash shown over the terminal when the bell rings.
+pub struct VisualBell {
+    config: Option<config::VisualBell>,
+    start: Option<Instant>,
+}
+
+impl VisualBell {
+    pub fn new(config: Option<config::VisualBell>) -> VisualBell {
+        VisualBell {
+            config,
+            start: None,
+        }
+    }
+
+    /// Starts the flash, a bell during a flash restarts it.
+    pub fn ring(&mut self, now: Instant) {
+        if self.config.is_some() {
+            self.start = Some(now);
+        }
+    }
+
+    /// The flash still needs frames, including the one that removes it.
+    #[inline]
+    pub fn is_ringing(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Flash color for a frame drawn at `now`, `None` once it's over.
+    pub fn color(&mut self, now: Instant) -> Option<[f32; 4]> {
+        let config = self.config?;
+        let elapsed = now.saturating_duration_since(self.start?);
+        let duration = Duration::from_millis(config.duration);
+        if elapsed >= duration {
+            self.start = None;
+            return None;
+        }
+
+        let mut intensity = config.opacity;
+        if config.fade {
+            intensity *= 1. - elapsed.as_secs_f32() / duration.as_secs_f32();
+        }
+
+        let [r, g, b, a] = config.color;
+        Some([r, g, b, a * intensity])
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn visual_bell(fade: bool) -> VisualBell {
+        VisualBell::new(Some(config::VisualBell {
+            color: [1.0, 0.0, 0.0, 1.0],
+            opacity: 0.5,
+            duration: 100,
+            fade,
+        }))
+    }
+
+    #[test]
+    fn test_visual_bell_fades_out() {
+        let mut bell = visual_bell(true);
+        let now = Instant::now();
+        assert_eq!(bell.color(now), None);
+
+        bell.ring(now);
+        assert!(bell.is_ringing());
+        assert_eq!(bell.color(now), Some([1.0, 0.0, 0.0, 0.5]));
+        assert_eq!(
+            bell.color(now + Duration::from_millis(50)),
+            Some([1.0, 0.0, 0.0, 0.25])
+        );
+
+        // Past the duration the frame is back to normal.
+        assert_eq!(bell.color(now + Duration::from_millis(100)), None);
+        assert!(!bell.is_ringing());
+    }
+
+    #[test]
+    fn test_visual_bell_restarts() {
+        let mut bell = visual_bell(false);
+        let now = Instant::now();
+
+        bell.ring(now);
+        bell.ring(now + Duration::from_millis(80));
+        assert_eq!(
+            bell.color(now + Duration::from_millis(150)),
+            Some([1.0, 0.0, 0.0, 0.5])
+        );
+        assert_eq!(bell.color(now + Duration::from_millis(180)), None);
+    }
+
+    #[test]
+    fn test_visual_bell_disabled() {
+        let mut bell = VisualBell::new(None);
+        bell.ring(Instant::now());
+        assert!(!bell.is_ringing());
+    }
+}
@@ -4,6 +4,7 @@
 use crate::crosswords::vi_mode::ViMotion;
 use crate::crosswords::Mode;
 use bitflags::bitflags;
+use config::bindings::KeyBinding as ConfigKeyBinding;
 use std::fmt::Debug;
 use winit::event::ModifiersState;
 use winit::event::VirtualKeyCode;
@@ -325,7 +326,17 @@ macro_rules! bindings {
     }};
 }
 
-pub fn default_key_bindings() -> Vec<KeyBinding> {
+pub fn default_key_bindings(
+    unprocessed_config_keys: Vec<ConfigKeyBinding>,
+    backspace_sends_delete: bool,
+) -> Vec<KeyBinding> {
+    // Some programs expect backspace to be sent as BS (^H) instead of DEL.
+    let backspace = if backspace_sends_delete {
+        "\x7f"
+    } else {
+        "\x08"
+    };
+
     let mut bindings = bindings!(
         KeyBinding;
         Copy;  Action::Copy;
@@ -337,9 +348,9 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
         Tab,  ModifiersState::SHIFT, ~BindingMode::VI;
             Action::Esc("\x1b[Z".into());
         Back, ModifiersState::ALT,   ~BindingMode::VI;
-            Action::Esc("\x1b\x7f".into());
+            Action::Esc(format!("\x1b{backspace}"));
         Back, ModifiersState::SHIFT, ~BindingMode::VI;
-            Action::Esc("\x7f".into());
+            Action::Esc(backspace.into());
         Home,     ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN; Action::ScrollToTop;
         End,      ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN; Action::ScrollToBottom;
         PageUp,   ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN; Action::ScrollPageUp;
@@ -376,7 +387,7 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
             Action::Esc("\x1bOD".into());
         Left,  ~BindingMode::APP_CURSOR, ~BindingMode::VI;
             Action::Esc("\x1b[D".into());
-        Back,        ~BindingMode::VI; Action::Esc("\x7f".into());
+        Back,        ~BindingMode::VI; Action::Esc(backspace.into());
         Insert,      ~BindingMode::VI; Action::Esc("\x1b[2~".into());
         Delete,      ~BindingMode::VI; Action::Esc("\x1b[3~".into());
         PageUp,      ~BindingMode::VI; Action::Esc("\x1b[5~".into());
@@ -600,9 +611,184 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
 
     bindings.extend(platform_key_bindings());
 
+    config_key_bindings(unprocessed_config_keys, bindings)
+}
+
+/// Merges key bindings from the configuration file into `bindings`,
+/// replacing any default binding with the same trigger.
+fn config_key_bindings(
+    config_key_bindings: Vec<ConfigKeyBinding>,
+    mut bindings: Vec<KeyBinding>,
+) -> Vec<KeyBinding> {
+    for config_key_binding in config_key_bindings {
+        match convert(config_key_binding) {
+            Ok(key_binding) => {
+                bindings.retain(|binding| !binding.triggers_match(&key_binding));
+                bindings.push(key_binding);
+            }
+            Err(err_message) => {
+                log::warn!("invalid key binding: {err_message}");
+            }
+        }
+    }
+
     bindings
 }
 
+fn convert(config_key_binding: ConfigKeyBinding) -> Result<KeyBinding, String> {
+    let key = key_from_str(&config_key_binding.key)
+        .ok_or_else(|| format!("unknown key {:?}", config_key_binding.key))?;
+
+    let mut mods = ModifiersState::empty();
+    for modifier in config_key_binding
+        .with
+        .split('|')
+        .map(|modifier| modifier.trim().to_lowercase())
+        .filter(|modifier| !modifier.is_empty())
+    {
+        match modifier.as_str() {
+            "super" | "logo" | "command" => mods |= ModifiersState::LOGO,
+            "shift" => mods |= ModifiersState::SHIFT,
+            "control" | "ctrl" => mods |= ModifiersState::CTRL,
+            "alt" | "option" => mods |= ModifiersState::ALT,
+            _ => return Err(format!("unknown modifier {modifier:?}")),
+        }
+    }
+
+    let action = if !config_key_binding.esc.is_empty() {
+        Action::Esc(config_key_binding.esc)
+    } else {
+        action_from_str(&config_key_binding.action)
+            .ok_or_else(|| format!("unknown action {:?}", config_key_binding.action))?
+    };
+
+    Ok(KeyBinding {
+        trigger: Key::Keycode(key),
+        mods,
+        mode: BindingMode::empty(),
+        notmode: BindingMode::empty(),
+        action,
+    })
+}
+
+fn action_from_str(action: &str) -> Option<Action> {
+    let action = match action.to_lowercase().as_str() {
+        "paste" => Action::Paste,
+        "copy" => Action::Copy,
+        "pasteselection" => Action::PasteSelection,
+        "increasefontsize" => Action::IncreaseFontSize,
+        "decreasefontsize" => Action::DecreaseFontSize,
+        "resetfontsize" => Action::ResetFontSize,
+        "scrollpageup" => Action::ScrollPageUp,
+        "scrollpagedown" => Action::ScrollPageDown,
+        "scrollhalfpageup" => Action::ScrollHalfPageUp,
+        "scrollhalfpagedown" => Action::ScrollHalfPageDown,
+        "scrolllineup" => Action::ScrollLineUp,
+        "scrolllinedown" => Action::ScrollLineDown,
+        "scrolltotop" => Action::ScrollToTop,
+        "scrolltobottom" => Action::ScrollToBottom,
+        "clearhistory" => Action::ClearHistory,
+        "createwindow" => Action::WindowCreateNew,
+        "createtab" => Action::TabCreateNew,
+        "selectnexttab" => Action::TabSwitchNext,
+        "closetab" => Action::TabCloseCurrent,
+        "clearselection" => Action::ClearSelection,
+        "togglevimode" => Action::ToggleViMode,
+        "receivechar" => Action::ReceiveChar,
+        "none" => Action::None,
+        _ => return None,
+    };
+
+    Some(action)
+}
+
+fn key_from_str(key: &str) -> Option<VirtualKeyCode> {
+    let key = key.to_lowercase();
+
+    if let [c] = key.as_bytes() {
+        let keycode = match c {
+            b'a' => A,
+            b'b' => B,
+            b'c' => C,
+            b'd' => D,
+            b'e' => E,
+            b'f' => F,
+            b'g' => G,
+            b'h' => H,
+            b'i' => I,
+            b'j' => J,
+            b'k' => K,
+            b'l' => L,
+            b'm' => M,
+            b'n' => N,
+            b'o' => O,
+            b'p' => P,
+            b'q' => Q,
+            b'r' => R,
+            b's' => S,
+            b't' => T,
+            b'u' => U,
+            b'v' => V,
+            b'w' => W,
+            b'x' => X,
+            b'y' => Y,
+            b'z' => Z,
+            b'0' => Key0,
+            b'1' => Key1,
+            b'2' => Key2,
+            b'3' => Key3,
+            b'4' => Key4,
+            b'5' => Key5,
+            b'6' => Key6,
+            b'7' => Key7,
+            b'8' => Key8,
+            b'9' => Key9,
+            b'+' => Plus,
+            b'-' => Minus,
+            b'=' => Equals,
+            _ => return None,
+        };
+
+        return Some(keycode);
+    }
+
+    let keycode = match key.as_str() {
+        "plus" => Plus,
+        "minus" => Minus,
+        "equals" => Equals,
+        "up" => Up,
+        "down" => Down,
+        "left" => Left,
+        "right" => Right,
+        "home" => Home,
+        "end" => End,
+        "pageup" => PageUp,
+        "pagedown" => PageDown,
+        "insert" => Insert,
+        "delete" => Delete,
+        "back" | "backspace" => Back,
+        "tab" => Tab,
+        "enter" | "return" => Return,
+        "escape" | "esc" => Escape,
+        "space" => Space,
+        "f1" => F1,
+        "f2" => F2,
+        "f3" => F3,
+        "f4" => F4,
+        "f5" => F5,
+        "f6" => F6,
+        "f7" => F7,
+        "f8" => F8,
+        "f9" => F9,
+        "f10" => F10,
+        "f11" => F11,
+        "f12" => F12,
+        _ => return None,
+    };
+
+    Some(keycode)
+}
+
 #[cfg(all(target_os = "macos", not(test)))]
 pub fn platform_key_bindings() -> Vec<KeyBinding> {
     bindings!(
@@ -657,3 +843,93 @@ pub fn platform_key_bindings() -> Vec<KeyBinding> {
 pub fn platform_key_bindings() -> Vec<KeyBinding> {
     vec![]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triggered_actions(
+        bindings: &[KeyBinding],
+        mods: ModifiersState,
+        key: VirtualKeyCode,
+    ) -> Vec<Action> {
+        bindings
+            .iter()
+            .filter(|binding| {
+                binding.is_triggered_by(BindingMode::empty(), mods, &Key::Keycode(key))
+            })
+            .map(|binding| binding.action.clone())
+            .collect()
+    }
+
+    fn backspace_action(bindings: &[KeyBinding], mods: ModifiersState) -> Action {
+        triggered_actions(bindings, mods, Back).remove(0)
+    }
+
+    #[test]
+    fn backspace_sends_delete() {
+        let bindings = default_key_bindings(vec![], true);
+        assert_eq!(
+            backspace_action(&bindings, ModifiersState::empty()),
+            Action::Esc("\x7f".into())
+        );
+        assert_eq!(
+            backspace_action(&bindings, ModifiersState::ALT),
+            Action::Esc("\x1b\x7f".into())
+        );
+    }
+
+    #[test]
+    fn backspace_sends_backspace() {
+        let bindings = default_key_bindings(vec![], false);
+        assert_eq!(
+            backspace_action(&bindings, ModifiersState::empty()),
+            Action::Esc("\x08".into())
+        );
+        assert_eq!(
+            backspace_action(&bindings, ModifiersState::ALT),
+            Action::Esc("\x1b\x08".into())
+        );
+    }
+
+    #[test]
+    fn config_key_bindings_map_to_actions() {
+        let bindings = default_key_bindings(
+            vec![
+                ConfigKeyBinding {
+                    key: String::from("plus"),
+                    with: String::from("control"),
+                    action: String::from("IncreaseFontSize"),
+                    esc: String::new(),
+                },
+                ConfigKeyBinding {
+                    key: String::from("l"),
+                    with: String::from("control"),
+                    action: String::from("ScrollToTop"),
+                    esc: String::new(),
+                },
+                ConfigKeyBinding {
+                    key: String::from("not-a-key"),
+                    with: String::new(),
+                    action: String::from("Copy"),
+                    esc: String::new(),
+                },
+            ],
+            true,
+        );
+
+        assert_eq!(
+            triggered_actions(&bindings, ModifiersState::CTRL, Plus),
+            vec![Action::IncreaseFontSize]
+        );
+
+        // Config bindings replace the defaults with the same trigger
+        assert_eq!(
+            triggered_actions(&bindings, ModifiersState::CTRL, L),
+            vec![Action::ScrollToTop]
+        );
+
+        // Unbound keys don't trigger any action
+        assert!(triggered_actions(&bindings, ModifiersState::CTRL, Q).is_empty());
+    }
+}
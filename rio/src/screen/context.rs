@@ -1,12 +1,16 @@
 use crate::crosswords::pos::CursorState;
 use crate::event::sync::FairMutex;
 use crate::event::EventListener;
+use crate::performer::handler::MAX_STRING_LENGTH;
 use crate::performer::Machine;
 use crate::screen::Crosswords;
 use crate::screen::Messenger;
+use config::defaults::default_max_line_length;
+use config::{AmbiguousWidth, ControlPictureStyle, FormFeed, LongLines, ResizeAnchor};
 use std::borrow::Cow;
 use std::error::Error;
 use std::sync::Arc;
+use std::time::Duration;
 use teletypewriter::create_pty;
 
 const DEFAULT_CONTEXT_CAPACITY: usize = 6;
@@ -16,11 +20,44 @@ pub struct Context<T: EventListener> {
     pub messenger: Messenger,
 }
 
+/// Terminal settings shared by every context.
+#[derive(Clone, Copy)]
+pub struct ContextManagerConfig {
+    pub max_string_length: usize,
+    pub max_line_length: usize,
+    pub long_lines: LongLines,
+    pub ambiguous_width: AmbiguousWidth,
+    pub resize_anchor: ResizeAnchor,
+    pub wrap_column: Option<usize>,
+    pub form_feed: FormFeed,
+    pub backspace_sends_delete: bool,
+    pub control_picture_style: ControlPictureStyle,
+    pub cursor_blink_interval: Duration,
+}
+
+impl Default for ContextManagerConfig {
+    fn default() -> ContextManagerConfig {
+        ContextManagerConfig {
+            max_string_length: MAX_STRING_LENGTH,
+            max_line_length: default_max_line_length(),
+            long_lines: LongLines::default(),
+            ambiguous_width: AmbiguousWidth::default(),
+            resize_anchor: ResizeAnchor::default(),
+            wrap_column: None,
+            form_feed: FormFeed::default(),
+            backspace_sends_delete: true,
+            control_picture_style: ControlPictureStyle::default(),
+            cursor_blink_interval: Duration::from_millis(500),
+        }
+    }
+}
+
 pub struct ContextManager<T: EventListener> {
     contexts: Vec<Context<T>>,
     current_index: usize,
     capacity: usize,
     event_proxy: T,
+    config: ContextManagerConfig,
 }
 
 impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
@@ -29,16 +66,30 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
         rows: usize,
         cursor_state: CursorState,
         event_proxy: T,
+        config: ContextManagerConfig,
         spawn: bool,
     ) -> Result<Context<T>, Box<dyn Error>> {
         let shell = std::env::var("SHELL").unwrap_or_else(|_| String::from("bash"));
         let event_proxy_clone = event_proxy.clone();
         let mut terminal = Crosswords::new(columns, rows, event_proxy);
         terminal.cursor_shape = cursor_state.content;
+        terminal.set_ambiguous_width(config.ambiguous_width);
+        terminal.set_resize_anchor(config.resize_anchor);
+        terminal.set_wrap_column(config.wrap_column);
+        terminal.set_form_feed(config.form_feed);
+        terminal.set_backspace_sends_delete(config.backspace_sends_delete);
+        terminal.set_control_picture_style(config.control_picture_style);
+        terminal.set_max_line_length(config.max_line_length, config.long_lines);
+        terminal.set_default_cursor_blink_interval(config.cursor_blink_interval);
         let terminal: Arc<FairMutex<Crosswords<T>>> = Arc::new(FairMutex::new(terminal));
 
         let pty = create_pty(&Cow::Borrowed(&shell), columns as u16, rows as u16);
-        let machine = Machine::new(Arc::clone(&terminal), pty, event_proxy_clone)?;
+        let machine = Machine::new(
+            Arc::clone(&terminal),
+            pty,
+            event_proxy_clone,
+            config.max_string_length,
+        )?;
         let channel = machine.channel();
         // The only case we don't spawn is for tests
         if spawn {
@@ -57,12 +108,14 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
         rows: usize,
         cursor_state: CursorState,
         event_proxy: T,
+        config: ContextManagerConfig,
     ) -> Result<Self, Box<dyn Error>> {
         let initial_context = ContextManager::create_context(
             columns,
             rows,
             cursor_state,
             event_proxy.clone(),
+            config,
             true,
         )?;
         Ok(ContextManager {
@@ -70,6 +123,7 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
             contexts: vec![initial_context],
             capacity: DEFAULT_CONTEXT_CAPACITY,
             event_proxy,
+            config,
         })
     }
 
@@ -83,6 +137,7 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
             1,
             CursorState::default(),
             event_proxy.clone(),
+            ContextManagerConfig::default(),
             false,
         )?;
         Ok(ContextManager {
@@ -90,6 +145,7 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
             contexts: vec![initial_context],
             capacity,
             event_proxy,
+            config: ContextManagerConfig::default(),
         })
     }
 
@@ -98,11 +154,6 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
         self.contexts.len()
     }
 
-    #[inline]
-    pub fn contexts(&self) -> &Vec<Context<T>> {
-        &self.contexts
-    }
-
     #[cfg(test)]
     pub fn increase_capacity(&mut self, inc_val: usize) {
         self.capacity += inc_val;
@@ -132,6 +183,24 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
         self.contexts.remove(index_to_remove);
     }
 
+    #[inline]
+    #[allow(dead_code)]
+    pub fn close_context_by_id(&mut self, context_id: usize) {
+        if context_id >= self.contexts.len() {
+            return;
+        }
+
+        if context_id == self.current_index {
+            self.close_context();
+            return;
+        }
+
+        self.contexts.remove(context_id);
+        if context_id < self.current_index {
+            self.current_index -= 1;
+        }
+    }
+
     #[inline]
     pub fn current_index(&self) -> usize {
         self.current_index
@@ -173,6 +242,7 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
                 rows,
                 cursor_state,
                 self.event_proxy.clone(),
+                self.config,
                 spawn,
             ) {
                 Ok(new_context) => {
@@ -192,7 +262,9 @@ impl<T: EventListener + Clone + std::marker::Send + 'static> ContextManager<T> {
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::crosswords::pos::{Column, Line};
     use crate::event::VoidListener;
+    use crate::performer::handler::Handler;
 
     #[test]
     fn test_capacity() {
@@ -369,4 +441,59 @@ pub mod test {
         context_manager.switch_to_next();
         assert_eq!(context_manager.current_index, 1);
     }
+
+    #[test]
+    fn test_close_context_by_id() {
+        let mut context_manager =
+            ContextManager::start_with_capacity(5, VoidListener {}).unwrap();
+        let should_redirect = false;
+
+        context_manager.add_context(should_redirect, false, 1, 1, CursorState::default());
+        context_manager.add_context(should_redirect, false, 1, 1, CursorState::default());
+        context_manager.add_context(should_redirect, false, 1, 1, CursorState::default());
+        context_manager.set_current(2);
+
+        // Closing a context before the current one keeps the same context active
+        context_manager.close_context_by_id(0);
+        assert_eq!(context_manager.len(), 3);
+        assert_eq!(context_manager.current_index, 1);
+
+        // Closing a context after the current one does not move the index
+        context_manager.close_context_by_id(2);
+        assert_eq!(context_manager.len(), 2);
+        assert_eq!(context_manager.current_index, 1);
+
+        // Out of range ids are ignored
+        context_manager.close_context_by_id(8);
+        assert_eq!(context_manager.len(), 2);
+
+        context_manager.close_context_by_id(1);
+        assert_eq!(context_manager.len(), 1);
+        assert_eq!(context_manager.current_index, 0);
+
+        // Last context should not be closed
+        context_manager.close_context_by_id(0);
+        assert_eq!(context_manager.len(), 1);
+    }
+
+    #[test]
+    fn test_contexts_keep_independent_content() {
+        let mut context_manager =
+            ContextManager::start_with_capacity(5, VoidListener {}).unwrap();
+        context_manager.add_context(true, false, 1, 1, CursorState::default());
+
+        context_manager.set_current(0);
+        context_manager.current().terminal.lock().input('a');
+        context_manager.set_current(1);
+        context_manager.current().terminal.lock().input('b');
+
+        context_manager.set_current(0);
+        let terminal = context_manager.current().terminal.lock();
+        assert_eq!(terminal.grid[Line(0)][Column(0)].c, 'a');
+        drop(terminal);
+
+        context_manager.switch_to_next();
+        let terminal = context_manager.current().terminal.lock();
+        assert_eq!(terminal.grid[Line(0)][Column(0)].c, 'b');
+    }
 }
@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+/// Highlight over the cells from `start` to `end` (row and column, in
+/// reading order), it fades out over `duration`.
+struct Flash {
+    start: (u16, u16),
+    end: (u16, u16),
+    color: [f32; 4],
+    begin: Instant,
+    duration: Duration,
+}
+
+impl Flash {
+    /// Opacity left at `now`, `None` once it's over.
+    fn intensity(&self, now: Instant) -> Option<f32> {
+        let elapsed = now.saturating_duration_since(self.begin);
+        if elapsed >= self.duration {
+            return None;
+        }
+
+        Some(1. - elapsed.as_secs_f32() / self.duration.as_secs_f32())
+    }
+}
+
+/// Temporary highlights of cell ranges (e.g. a matched bracket or a copied
+/// region), overlapping flashes are blended.
+#[derive(Default)]
+pub struct Flashes {
+    flashes: Vec<Flash>,
+}
+
+impl Flashes {
+    pub fn add(
+        &mut self,
+        start: (u16, u16),
+        end: (u16, u16),
+        color: [f32; 4],
+        duration: Duration,
+        now: Instant,
+    ) {
+        if duration.is_zero() {
+            return;
+        }
+
+        self.flashes.push(Flash {
+            start: start.min(end),
+            end: start.max(end),
+            color,
+            begin: now,
+            duration,
+        });
+    }
+
+    /// Some flash still needs frames, including the one that removes it.
+    #[inline]
+    pub fn is_flashing(&self) -> bool {
+        !self.flashes.is_empty()
+    }
+
+    /// Forgets the flashes that are over at `now`.
+    pub fn prune(&mut self, now: Instant) {
+        self.flashes.retain(|flash| flash.intensity(now).is_some());
+    }
+
+    /// Color laid over the cell at `now`, flashes added later are on top.
+    pub fn color(&self, row: u16, col: u16, now: Instant) -> Option<[f32; 4]> {
+        let mut blended: Option<[f32; 4]> = None;
+        for flash in &self.flashes {
+            if (row, col) < flash.start || (row, col) > flash.end {
+                continue;
+            }
+            let Some(intensity) = flash.intensity(now) else { continue };
+
+            let [r, g, b, a] = flash.color;
+            let alpha = a * intensity;
+            blended = Some(match blended {
+                None => [r, g, b, alpha],
+                Some([below_r, below_g, below_b, below_alpha]) => {
+                    let below = below_alpha * (1. - alpha);
+                    let total = alpha + below;
+                    [
+                        (r * alpha + below_r * below) / total,
+                        (g * alpha + below_g * below) / total,
+                        (b * alpha + below_b * below) / total,
+                        total,
+                    ]
+                }
+            });
+        }
+
+        blended
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_flash_disappears_after_its_duration() {
+        let mut flashes = Flashes::default();
+        let now = Instant::now();
+        flashes.add(
+            (0, 2),
+            (1, 1),
+            [1.0, 0.0, 0.0, 1.0],
+            Duration::from_millis(100),
+            now,
+        );
+        assert!(flashes.is_flashing());
+
+        assert_eq!(flashes.color(0, 1, now), None);
+        assert_eq!(flashes.color(0, 2, now), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(flashes.color(0, 9, now), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(
+            flashes.color(1, 1, now + Duration::from_millis(50)),
+            Some([1.0, 0.0, 0.0, 0.5])
+        );
+        assert_eq!(flashes.color(1, 2, now), None);
+
+        let later = now + Duration::from_millis(100);
+        assert_eq!(flashes.color(0, 2, later), None);
+        flashes.prune(later);
+        assert!(!flashes.is_flashing());
+    }
+
+    #[test]
+    fn test_overlapping_flashes_blend() {
+        let mut flashes = Flashes::default();
+        let now = Instant::now();
+        let duration = Duration::from_millis(100);
+        flashes.add((0, 0), (0, 3), [1.0, 0.0, 0.0, 0.5], duration, now);
+        flashes.add((0, 2), (0, 5), [0.0, 0.0, 1.0, 0.5], duration, now);
+
+        assert_eq!(flashes.color(0, 0, now), Some([1.0, 0.0, 0.0, 0.5]));
+        assert_eq!(flashes.color(0, 4, now), Some([0.0, 0.0, 1.0, 0.5]));
+        let [r, g, b, a] = flashes.color(0, 2, now).unwrap();
+        assert!(b > r && r > 0.0);
+        assert_eq!(g, 0.0);
+        assert_eq!(a, 0.75);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::crosswords::Crosswords;
+use crate::event::VoidListener;
+use crate::layout::Layout;
+use crate::performer::handler::ParserProcessor;
+use crate::screen::state::State;
+use std::rc::Rc;
+use std::time::Instant;
+use sugarloaf::font::{Font, FontSynthesis};
+use sugarloaf::Sugarloaf;
+
+/// A terminal drawn offscreen instead of on a window, so the frames escape
+/// sequences produce can be compared in tests.
+pub struct Headless {
+    terminal: Crosswords<VoidListener>,
+    parser: ParserProcessor,
+    state: State,
+    layout: Layout,
+    sugarloaf: Sugarloaf,
+    /// Every frame is drawn at this time, so the cursor blink and flashes
+    /// look the same whenever it's rendered.
+    now: Instant,
+}
+
+impl Headless {
+    /// A terminal filling `width` x `height` pixels, `None` when the machine
+    /// has no graphics adapter.
+    pub async fn new(
+        config: &Rc<config::Config>,
+        width: u32,
+        height: u32,
+    ) -> Option<Self> {
+        let font = Font::with_fallbacks(
+            config.style.font.to_string(),
+            config.style.font_fallbacks.clone(),
+            FontSynthesis::default(),
+        )
+        .ok()?;
+        let mut layout =
+            Layout::new(width as f32, height as f32, 1.0, config.style.font_size);
+        let (columns, rows) = layout.set_cell_width(&font).compute();
+        let mut sugarloaf = Sugarloaf::headless(width, height, 1.0, font).await.ok()?;
+        let state = State::new(config);
+        sugarloaf
+            .init(state.background_color(), layout.styles.term)
+            .ok()?;
+
+        Some(Headless {
+            terminal: Crosswords::new(columns, rows, VoidListener {}),
+            parser: ParserProcessor::new(),
+            state,
+            layout,
+            sugarloaf,
+            now: Instant::now(),
+        })
+    }
+
+    /// Processes `bytes` through the parser and returns the RGBA pixels of
+    /// the frame drawn after them.
+    pub fn feed_and_render(&mut self, bytes: &[u8]) -> Vec<u8> {
+        self.feed(bytes);
+        self.render()
+    }
+
+    /// Processes `bytes` through the parser, without drawing a frame.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.parser.advance(&mut self.terminal, *byte);
+        }
+    }
+
+    /// Returns the RGBA pixels of a frame of the terminal as it is now.
+    pub fn render(&mut self) -> Vec<u8> {
+        self.state.update(
+            self.terminal.visible_rows(),
+            self.terminal.display_offset(),
+            self.terminal.cursor(),
+            &mut self.sugarloaf,
+            &self.layout.styles,
+            self.now,
+        );
+        self.sugarloaf
+            .render()
+            .expect("headless frames are never skipped");
+        self.sugarloaf.last_frame_rgba().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crosswords::pos::{Column, Line, Pos};
+
+    #[tokio::test]
+    async fn test_colored_line_is_rendered() {
+        let (width, height) = (200, 100);
+        let config = Rc::new(config::Config::default());
+        let mut headless = headless_or_skip!(Headless::new(&config, width, height).await);
+
+        let red = [255, 0, 0, 255];
+        let red_rows = |frame: &[u8]| -> Vec<usize> {
+            frame
+                .chunks(4)
+                .enumerate()
+                .filter(|(_, pixel)| *pixel == red)
+                .map(|(index, _)| index / width as usize)
+                .collect()
+        };
+
+        let frame = headless.feed_and_render(b"\x1b[48;2;255;0;0mrio\x1b[0m");
+        assert_eq!(frame.len(), (width * height * 4) as usize);
+        let rows = red_rows(&frame);
+        // Only the first line has the red background.
+        let (first, last) = (rows.first().unwrap(), rows.last().unwrap());
+        assert!(
+            last - first < config.style.font_size as usize * 2,
+            "{rows:?}"
+        );
+
+        // Time is frozen, the same screen is drawn the same way.
+        assert_eq!(headless.feed_and_render(b""), frame);
+    }
+
+    #[tokio::test]
+    async fn test_fractional_cursor_column_draws_the_caret_between_cells() {
+        let config = Rc::new(config::Config::default());
+        let mut headless = headless_or_skip!(Headless::new(&config, 200, 100).await);
+        // A steady bar cursor on an empty screen, the only thing drawn.
+        headless.feed(b"\x1b[6 q");
+        let mut caret_x = |column: f32| -> usize {
+            headless.terminal.set_fractional_cursor_column(column);
+            let frame = headless.render();
+            let background = &frame[frame.len() - 4..];
+            frame
+                .chunks(4)
+                .enumerate()
+                .filter(|(_, pixel)| pixel != &background)
+                .map(|(index, _)| index % 200)
+                .min()
+                .unwrap()
+        };
+
+        let (at_2, at_2_5, at_3) = (caret_x(2.0), caret_x(2.5), caret_x(3.0));
+        assert!(at_2 < at_2_5 && at_2_5 < at_3, "{at_2} {at_2_5} {at_3}");
+        // Halfway between the two cells.
+        assert!((at_2_5 as i32 - (at_2 + at_3) as i32 / 2).abs() <= 1);
+    }
+
+    /// A frame of "ab a" with the search query "ab", `None` when the machine
+    /// has no graphics adapter.
+    async fn search_frame(search_spotlight: bool) -> Option<Vec<u8>> {
+        let mut config = config::Config::default();
+        config.style.search_spotlight = search_spotlight;
+        let mut headless = Headless::new(&Rc::new(config), 200, 100).await?;
+        headless.state.set_search_query(Some(String::from("ab")));
+        Some(headless.feed_and_render(b"ab a"))
+    }
+
+    #[tokio::test]
+    async fn test_search_spotlight_dims_other_cells() {
+        let plain = headless_or_skip!(search_frame(false).await);
+        let spotlit = search_frame(true).await.unwrap();
+        let brightness =
+            |pixel: &[u8]| -> u32 { pixel[..3].iter().map(|&value| value as u32).sum() };
+        // The padding isn't part of the grid.
+        let padding = &plain[..4];
+
+        // The match is drawn the same, every other cell darker.
+        let (mut matched, mut dimmed) = (vec![], vec![]);
+        for (plain, spotlit) in plain.chunks(4).zip(spotlit.chunks(4)) {
+            if plain != spotlit {
+                assert!(brightness(spotlit) < brightness(plain));
+                dimmed.push(brightness(spotlit));
+            } else if plain != padding {
+                matched.push(brightness(spotlit));
+            }
+        }
+        assert!(!matched.is_empty() && !dimmed.is_empty());
+        let mean = |values: &[u32]| values.iter().sum::<u32>() / values.len() as u32;
+        assert!(mean(&matched) > mean(&dimmed));
+    }
+
+    #[tokio::test]
+    async fn test_screen_is_drawn_again_on_a_recovered_device() {
+        let config = Rc::new(config::Config::default());
+        let mut headless = headless_or_skip!(Headless::new(&config, 200, 100).await);
+        let before = headless.feed_and_render(b"\x1b[31mrio");
+
+        headless.sugarloaf.recover().await.unwrap();
+        let text = headless
+            .terminal
+            .bounds_to_string(Pos::new(Line(0), Column(0)), Pos::new(Line(0), Column(2)));
+        assert_eq!(text, "rio");
+        assert_eq!(headless.feed_and_render(b""), before);
+    }
+}
@@ -1,9 +1,16 @@
+mod bell;
 mod bindings;
 mod context;
+mod flash;
+#[cfg(test)]
+pub mod headless;
 mod messenger;
+mod mouse;
+mod ready;
 mod state;
 pub mod window;
 
+use crate::ansi::CursorShape;
 use crate::clipboard::{Clipboard, ClipboardType};
 use crate::crosswords::{
     grid::Scroll,
@@ -13,20 +20,35 @@ use crate::crosswords::{
 use crate::event::{ClickState, EventProxy};
 use crate::ime::Ime;
 use crate::layout::Layout;
+use crate::scheduler::RedrawSchedule;
+use crate::screen::bell::VisualBell;
 use crate::screen::bindings::{Action as Act, BindingMode, Key};
 use crate::screen::context::ContextManager;
+use crate::screen::ready::ReadyMarker;
 use crate::selection::{Selection, SelectionType};
 use colors::term::List;
+use colors::Colors;
+use config::{Appearance, PasteNewline};
 use messenger::Messenger;
 use state::State;
+use std::borrow::Cow;
 use std::error::Error;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
+use sugarloaf::font::{Font, FontSynthesis};
 use sugarloaf::Sugarloaf;
+use winit::event::ElementState;
 
 pub struct Screen {
     bindings: bindings::KeyBindings,
     clipboard: Clipboard,
     ignore_chars: bool,
+    paste_newline: PasteNewline,
+    visual_bell: VisualBell,
+    ready_marker: ReadyMarker,
+    redraw: RedrawSchedule,
+    light_colors: Colors,
+    dark_colors: Colors,
     layout: Layout,
     pub ime: Ime,
     pub state: State,
@@ -34,6 +56,14 @@ pub struct Screen {
     context_manager: context::ContextManager<EventProxy>,
 }
 
+/// Appearance of the window `theme`.
+pub fn appearance(theme: winit::window::Theme) -> Appearance {
+    match theme {
+        winit::window::Theme::Light => Appearance::Light,
+        winit::window::Theme::Dark => Appearance::Dark,
+    }
+}
+
 impl Screen {
     pub async fn new(
         winit_window: &winit::window::Window,
@@ -43,35 +73,96 @@ impl Screen {
         let size = winit_window.inner_size();
         let scale = winit_window.scale_factor();
 
-        let mut layout = Layout::new(
-            size.width as f32,
-            size.height as f32,
-            scale as f32,
-            config.style.font_size,
-        );
-        let (columns, rows) = layout.compute();
-
         let power_preference: wgpu::PowerPreference = match config.performance {
             config::Performance::High => wgpu::PowerPreference::HighPerformance,
             config::Performance::Low => wgpu::PowerPreference::LowPower,
         };
 
-        let sugarloaf = Sugarloaf::new(
-            winit_window,
-            power_preference,
+        let synthesis = FontSynthesis {
+            bold_strength: config
+                .style
+                .synthesize_bold
+                .then_some(config.style.bold_strength),
+            italic_slant: config
+                .style
+                .synthesize_italic
+                .then_some(config.style.italic_slant),
+        };
+        let font = Font::with_fallbacks(
             config.style.font.to_string(),
-        )
-        .await?;
+            config.style.font_fallbacks.clone(),
+            synthesis,
+        )?;
+        let mut layout = Layout::new(
+            size.width as f32,
+            size.height as f32,
+            scale as f32,
+            config.style.font_size,
+        );
+        let (columns, rows) = layout.set_cell_width(&font).compute();
+        layout.take_pending_resize();
+
+        let mut sugarloaf =
+            Sugarloaf::with_font(winit_window, power_preference, font).await;
+        sugarloaf.set_font_synthesis(synthesis);
+        sugarloaf.set_present_mode(match config.present_mode {
+            config::PresentMode::Vsync => wgpu::PresentMode::AutoVsync,
+            config::PresentMode::NoVsync => wgpu::PresentMode::AutoNoVsync,
+            config::PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            config::PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        });
+        sugarloaf.set_baseline_offset(config.style.baseline_offset);
+        sugarloaf.set_missing_glyph_replacement(config.style.missing_glyph_replacement);
+        sugarloaf.set_snap_to_pixel(config.style.snap_to_pixel);
+        sugarloaf.set_smooth_resize(config.smooth_resize);
+        sugarloaf.set_background_bleed(config.style.background_bleed);
+        sugarloaf.set_force_text_font_ranges(
+            config
+                .style
+                .force_text_font_ranges
+                .iter()
+                .map(|[start, end]| *start..=*end)
+                .collect(),
+        );
+        if !config.style.font_features.is_empty() {
+            let features: Vec<(&str, u32)> = config
+                .style
+                .font_features
+                .iter()
+                .map(|tag| (tag.as_str(), 1))
+                .collect();
+            sugarloaf.set_font_features(&features);
+        }
 
-        let state = State::new(config);
+        let mut state = State::new(config);
+        if let Some(theme) = winit_window.theme() {
+            state.set_colors(config.colors_for(appearance(theme)));
+        }
         let clipboard = Clipboard::new();
-        let bindings = bindings::default_key_bindings();
+        let bindings = bindings::default_key_bindings(
+            config.bindings.keys.to_owned(),
+            config.backspace_sends_delete,
+        );
         let ime = Ime::new();
         let context_manager = context::ContextManager::start(
             columns,
             rows,
             state.get_cursor_state(),
             event_proxy,
+            context::ContextManagerConfig {
+                max_string_length: config.advanced.max_string_length,
+                max_line_length: config.advanced.max_line_length,
+                long_lines: config.advanced.long_lines,
+                ambiguous_width: config.ambiguous_width,
+                resize_anchor: config.resize_anchor,
+                wrap_column: config.wrap_column,
+                form_feed: config.form_feed,
+                backspace_sends_delete: config.backspace_sends_delete,
+                control_picture_style: config.control_picture_style,
+                cursor_blink_interval: Duration::from_millis(
+                    config.cursor_blink_interval,
+                ),
+            },
         )?;
 
         Ok(Screen {
@@ -83,6 +174,14 @@ impl Screen {
             bindings,
             clipboard,
             ignore_chars: false,
+            paste_newline: config.paste_newline,
+            visual_bell: VisualBell::new(config.visual_bell),
+            ready_marker: ReadyMarker::new(config.developer.ready_marker.clone()),
+            redraw: RedrawSchedule::new(Duration::from_millis(
+                config.min_redraw_interval,
+            )),
+            light_colors: config.colors_for(Appearance::Light),
+            dark_colors: config.colors_for(Appearance::Dark),
         })
     }
 
@@ -146,6 +245,92 @@ impl Screen {
         mode.intersects(Mode::MOUSE_MODE) && !mode.contains(Mode::VI)
     }
 
+    /// Reports a mouse button event to the pty at the current mouse position.
+    pub fn mouse_report(&mut self, button: u8, state: ElementState) {
+        let display_offset = self.display_offset();
+        let point = self.layout.mouse_position(display_offset);
+        let mods = self.ctx().current().messenger.get_modifiers();
+        let mode = self.get_mode();
+
+        if let Some(report) = mouse::mouse_report(point, button, state, mods, &mode) {
+            self.ctx_mut().current_mut().messenger.send_bytes(report);
+        }
+    }
+
+    /// Reports mouse motion to the pty, at most once per square change.
+    pub fn mouse_motion_report(&mut self, old_point: Pos, point: Pos) {
+        let mods = self.ctx().current().messenger.get_modifiers();
+        let mode = self.get_mode();
+
+        if let Some(report) =
+            mouse::motion_report(old_point, point, &self.layout.mouse, mods, &mode)
+        {
+            self.ctx_mut().current_mut().messenger.send_bytes(report);
+        }
+    }
+
+    /// Tracks window focus and reports it to the pty if requested (?1004).
+    pub fn on_focus_change(&mut self, focused: bool) {
+        let mut terminal = self.ctx().current().terminal.lock();
+        let report = terminal.set_focused(focused);
+        drop(terminal);
+
+        if let Some(report) = report {
+            self.ctx_mut()
+                .current_mut()
+                .messenger
+                .send_bytes(report.to_vec());
+        }
+    }
+
+    /// Starts the visual bell flash, if it's enabled.
+    pub fn ring_bell(&mut self) {
+        self.visual_bell.ring(Instant::now());
+    }
+
+    /// Highlights the visible cells from `start` to `end` (row and column),
+    /// the highlight fades out over `duration`. A render has to be requested
+    /// for it to show up.
+    #[allow(dead_code)]
+    pub fn flash_range(
+        &mut self,
+        start: (u16, u16),
+        end: (u16, u16),
+        color: [f32; 4],
+        duration: Duration,
+    ) {
+        self.state
+            .flash_range(start, end, color, duration, Instant::now());
+    }
+
+    /// The visual bell or a flash needs more frames to finish.
+    #[inline]
+    pub fn is_animating(&self) -> bool {
+        self.visual_bell.is_ringing() || self.state.is_flashing()
+    }
+
+    /// Pauses or resumes the cursor blink, the visual bell and the flashes,
+    /// e.g. while the window can't be seen. Paused, the cursor is shown and
+    /// the animations hold still until they're resumed.
+    #[inline]
+    pub fn set_animations_enabled(&mut self, enabled: bool) {
+        self.redraw.set_animations_enabled(enabled, Instant::now());
+    }
+
+    /// Keeps the blinking cursor shown for a whole interval, it returns
+    /// whether a render is needed to show it.
+    #[inline]
+    pub fn reset_cursor_blink(&mut self) -> bool {
+        self.redraw.reset_cursor_blink(Instant::now())
+    }
+
+    /// When the next frame has to be drawn for the cursor blink, the bell or
+    /// the flashes, `None` while nothing animates.
+    #[inline]
+    pub fn next_redraw_at(&self) -> Option<Instant> {
+        self.redraw.next_redraw_at()
+    }
+
     #[inline]
     pub fn display_offset(&self) -> usize {
         let mut terminal = self.ctx().current().terminal.lock();
@@ -176,9 +361,9 @@ impl Screen {
         &mut self,
         virtual_keycode: Option<winit::event::VirtualKeyCode>,
         scancode: u32,
-    ) {
+    ) -> Result<(), wgpu::SurfaceError> {
         if self.ime.preedit().is_some() {
-            return;
+            return Ok(());
         }
 
         let mode = BindingMode::new(&self.get_mode());
@@ -230,16 +415,42 @@ impl Screen {
                             self.layout.rows,
                             self.state.get_cursor_state(),
                         );
-                        self.render();
+                        self.render()?;
                     }
                     Act::TabSwitchNext => {
                         self.context_manager.switch_to_next();
-                        self.render();
+                        self.render()?;
                     }
                     Act::TabCloseCurrent => {
                         self.context_manager.close_context();
-                        self.render();
+                        self.render()?;
+                    }
+                    Act::IncreaseFontSize => {
+                        self.layout.increase_font_size();
+                        self.update_font_size()?;
                     }
+                    Act::DecreaseFontSize => {
+                        self.layout.decrease_font_size();
+                        self.update_font_size()?;
+                    }
+                    Act::ResetFontSize => {
+                        self.layout.reset_font_size();
+                        self.update_font_size()?;
+                    }
+                    Act::ScrollPageUp => self.scroll_display(Scroll::PageUp)?,
+                    Act::ScrollPageDown => self.scroll_display(Scroll::PageDown)?,
+                    Act::ScrollHalfPageUp => {
+                        let lines = (self.layout.rows / 2) as i32;
+                        self.scroll_display(Scroll::Delta(lines))?;
+                    }
+                    Act::ScrollHalfPageDown => {
+                        let lines = (self.layout.rows / 2) as i32;
+                        self.scroll_display(Scroll::Delta(-lines))?;
+                    }
+                    Act::ScrollLineUp => self.scroll_display(Scroll::Delta(1))?,
+                    Act::ScrollLineDown => self.scroll_display(Scroll::Delta(-1))?,
+                    Act::ScrollToTop => self.scroll_display(Scroll::Top)?,
+                    Act::ScrollToBottom => self.scroll_display(Scroll::Bottom)?,
                     Act::ReceiveChar | Act::None => (),
                     _ => (),
                 }
@@ -247,6 +458,22 @@ impl Screen {
         }
 
         self.ignore_chars = ignore_chars.unwrap_or(false);
+        Ok(())
+    }
+
+    #[inline]
+    fn scroll_display(&mut self, scroll: Scroll) -> Result<(), wgpu::SurfaceError> {
+        let mut terminal = self.ctx().current().terminal.lock();
+        terminal.scroll_display(scroll);
+        drop(terminal);
+        self.render()
+    }
+
+    fn update_font_size(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let width = self.layout.width_u32;
+        let height = self.layout.height_u32;
+        self.resize(winit::dpi::PhysicalSize::new(width, height));
+        self.render()
     }
 
     pub fn copy_selection(&mut self, ty: ClipboardType) {
@@ -379,6 +606,7 @@ impl Screen {
 
     #[inline]
     pub fn paste(&mut self, text: &str, bracketed: bool) {
+        let text = &normalize_newlines(text, self.paste_newline);
         if bracketed && self.get_mode().contains(Mode::BRACKETED_PASTE) {
             self.ctx_mut()
                 .current_mut()
@@ -404,33 +632,135 @@ impl Screen {
             self.ctx_mut()
                 .current_mut()
                 .messenger
-                .send_bytes(text.replace("\r\n", "\r").replace('\n', "\r").into_bytes());
+                .send_bytes(text.as_bytes().to_vec());
         }
     }
 
     #[inline]
-    pub fn init(&mut self, color: colors::ColorWGPU) {
-        self.sugarloaf.init(color, self.layout.styles.term);
+    pub fn init(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.sugarloaf
+            .init(self.state.background_color(), self.layout.styles.term)
+    }
+
+    /// Switches to the colors of the light or dark theme, the embedder
+    /// forwards changes of the system appearance. A render has to be
+    /// requested for it to show up.
+    pub fn set_appearance(&mut self, appearance: Appearance) {
+        let colors = match appearance {
+            Appearance::Light => self.light_colors,
+            Appearance::Dark => self.dark_colors,
+        };
+        self.state.set_colors(colors);
+        self.sugarloaf
+            .set_background_color(self.state.background_color());
     }
 
+    /// Color of the 256 color palette entry `index`, the first 16 are the
+    /// ANSI colors of the theme.
+    pub fn palette_color(&self, index: u8) -> [f32; 4] {
+        self.state.palette_color(index)
+    }
+
+    /// Replaces a palette entry of every tab (e.g. with OSC 4) and damages
+    /// the screen. A render has to be requested for it to show up.
+    pub fn set_palette_color(&mut self, index: u8, color: [f32; 4]) {
+        self.state.set_palette_color(index, color);
+        self.ctx().current().terminal.lock().mark_fully_damaged();
+    }
+
+    /// Goes back to the color a palette entry had before
+    /// `set_palette_color`, like `set_palette_color` it damages the screen.
+    pub fn reset_palette_color(&mut self, index: u8) {
+        self.state.reset_palette_color(index);
+        self.ctx().current().terminal.lock().mark_fully_damaged();
+    }
+
+    /// Draws a frame of the current terminal, the error is the one of a
+    /// frame that can't be drawn (the surface ran out of memory).
     #[inline]
-    pub fn render(&mut self) {
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // Nothing is stacked, it wouldn't be drawn until the window is back.
+        if self.sugarloaf.is_minimized() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        self.draw(now)?;
+
+        // The frame went away with the device, the grid is still in the
+        // terminal so it's drawn once more on a new one. If that device is
+        // lost too the frame is skipped.
+        if self.sugarloaf.is_device_lost() && self.recover_device() {
+            self.draw(now)?;
+        }
+
+        self.redraw.set_animating(self.is_animating());
+        self.redraw.redrawn(now);
+
+        if let Some(marker) = self.ready_marker.take(self.sugarloaf.has_rendered()) {
+            self.ctx_mut().current_mut().messenger.send_bytes(marker);
+        }
+
+        Ok(())
+    }
+
+    /// Stacks the visible rows of the current terminal and draws them.
+    fn draw(&mut self, now: Instant) -> Result<(), wgpu::SurfaceError> {
+        let frame_time = self.redraw.frame_time(now);
         let mut terminal = self.ctx().current().terminal.lock();
         let visible_rows = terminal.visible_rows();
-        let cursor = terminal.cursor();
+        let display_offset = terminal.display_offset();
+        let mut cursor = terminal.cursor();
+        let cursor_blink_interval = terminal.cursor_blink_interval();
+        let pen = terminal.current_pen();
+        // Tab stops are only needed to draw the leaders.
+        let tab_stops = self.state.has_tab_leader().then(|| terminal.tab_stops());
         drop(terminal);
 
+        self.redraw.set_cursor_blink(cursor_blink_interval, now);
+        if !self.redraw.is_cursor_shown(frame_time) {
+            cursor.content = CursorShape::Hidden;
+        }
+
+        self.state.set_pen_background(pen.bg);
+        if let Some(tab_stops) = tab_stops {
+            self.state.set_tab_stops(tab_stops);
+        }
+        self.state.set_tabs(
+            self.context_manager.len(),
+            self.context_manager.current_index(),
+        );
+
         self.state.set_ime(self.ime.preedit());
 
         self.state.update(
             visible_rows,
+            display_offset,
             cursor,
             &mut self.sugarloaf,
             &self.layout.styles,
-            &self.context_manager,
+            frame_time,
         );
 
-        self.sugarloaf.render();
+        self.sugarloaf
+            .set_overlay(self.visual_bell.color(frame_time));
+        self.sugarloaf.render()
+    }
+
+    /// Recreates the graphics device after it was lost, e.g. when the
+    /// driver resets or the machine resumes from sleep.
+    fn recover_device(&mut self) -> bool {
+        log::warn!("graphics device lost, recreating it");
+        let recovered = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.sugarloaf.recover())
+        });
+        match recovered {
+            Ok(()) => true,
+            Err(error) => {
+                log::error!("failed to recreate the graphics device: {error}");
+                false
+            }
+        }
     }
 
     #[inline]
@@ -503,24 +833,46 @@ impl Screen {
     #[inline]
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) -> &mut Self {
         self.sugarloaf.resize(new_size.width, new_size.height);
+        // Minimized, the grid keeps its size until the window is restored.
+        if new_size.width == 0 || new_size.height == 0 {
+            return self;
+        }
         self.layout
             .set_size(new_size.width, new_size.height)
             .update();
-        let (c, l) = self.layout.compute();
+        self.layout.compute();
 
-        let mut terminal = self.context_manager.current_mut().terminal.lock();
-        terminal.resize::<Layout>(self.layout.columns, self.layout.rows);
-        drop(terminal);
+        // The PTY only needs to know when the grid size changes.
+        if let Some((c, l)) = self.layout.take_pending_resize() {
+            let mut terminal = self.context_manager.current_mut().terminal.lock();
+            terminal.resize::<Layout>(self.layout.columns, self.layout.rows);
+            drop(terminal);
 
-        let _ = self.ctx_mut().current_mut().messenger.send_resize(
-            new_size.width as u16,
-            new_size.height as u16,
-            c as u16,
-            l as u16,
-        );
+            let _ = self.ctx_mut().current_mut().messenger.send_resize(
+                new_size.width as u16,
+                new_size.height as u16,
+                c,
+                l,
+            );
+        }
         self
     }
 
+    /// Draws a border on the given edges of the terminal area, e.g. to
+    /// separate split panes.
+    #[allow(dead_code)]
+    pub fn set_border(
+        &mut self,
+        edges: crate::layout::Edges,
+        width: f32,
+        color: [f32; 4],
+    ) -> &mut Self {
+        self.layout.set_border(edges, width, color);
+        let size =
+            winit::dpi::PhysicalSize::new(self.layout.width_u32, self.layout.height_u32);
+        self.resize(size)
+    }
+
     pub fn set_scale(
         &mut self,
         new_scale: f32,
@@ -537,3 +889,36 @@ impl Screen {
         self
     }
 }
+
+/// Converts CRLF, CR and LF line endings of pasted text to `paste_newline`.
+fn normalize_newlines(text: &str, paste_newline: PasteNewline) -> Cow<'_, str> {
+    let newline = match paste_newline {
+        PasteNewline::Keep => return Cow::Borrowed(text),
+        PasteNewline::Lf => "\n",
+        PasteNewline::Cr => "\r",
+    };
+
+    Cow::Owned(text.replace("\r\n", newline).replace(['\r', '\n'], newline))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_pasted_newlines() {
+        let text = "one\r\ntwo\rthree\nfour";
+
+        assert_eq!(
+            normalize_newlines(text, PasteNewline::Cr),
+            "one\rtwo\rthree\rfour"
+        );
+        assert_eq!(
+            normalize_newlines(text, PasteNewline::Lf),
+            "one\ntwo\nthree\nfour"
+        );
+        assert_eq!(normalize_newlines(text, PasteNewline::Keep), text);
+        // Blank lines are kept.
+        assert_eq!(normalize_newlines("a\r\n\r\nb", PasteNewline::Lf), "a\n\nb");
+    }
+}
@@ -0,0 +1,178 @@
+// Mouse reports encoding
+// https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-Mouse-Tracking
+
+use crate::crosswords::pos::Pos;
+use crate::crosswords::Mode;
+use crate::layout::mouse::Mouse;
+use winit::event::{ElementState, ModifiersState};
+
+/// Button code used to report motion while no button is pressed.
+const NO_BUTTON_MOTION: u8 = 35;
+
+/// Encodes a mouse report for `point`, returns `None` when it can't be reported.
+pub fn mouse_report(
+    point: Pos,
+    button: u8,
+    state: ElementState,
+    mods: ModifiersState,
+    mode: &Mode,
+) -> Option<Vec<u8>> {
+    // Assure the mouse point is not in the scrollback.
+    if point.row.0 < 0 {
+        return None;
+    }
+
+    // Calculate modifiers value.
+    let mut mods_value = 0;
+    if mods.shift() {
+        mods_value += 4;
+    }
+    if mods.alt() {
+        mods_value += 8;
+    }
+    if mods.ctrl() {
+        mods_value += 16;
+    }
+
+    if mode.contains(Mode::SGR_MOUSE) {
+        Some(sgr_mouse_report(point, button + mods_value, state))
+    } else if state == ElementState::Released {
+        // Normal reports can't tell which button was released.
+        normal_mouse_report(point, 3 + mods_value, mode)
+    } else {
+        normal_mouse_report(point, button + mods_value, mode)
+    }
+}
+
+/// Encodes a motion report, only when the pointer moved to another square.
+pub fn motion_report(
+    old_point: Pos,
+    point: Pos,
+    mouse: &Mouse,
+    mods: ModifiersState,
+    mode: &Mode,
+) -> Option<Vec<u8>> {
+    if old_point == point || !mode.intersects(Mode::MOUSE_MOTION | Mode::MOUSE_DRAG) {
+        return None;
+    }
+
+    let button = if mouse.left_button_state == ElementState::Pressed {
+        32
+    } else if mouse.middle_button_state == ElementState::Pressed {
+        33
+    } else if mouse.right_button_state == ElementState::Pressed {
+        34
+    } else if mode.contains(Mode::MOUSE_MOTION) {
+        NO_BUTTON_MOTION
+    } else {
+        return None;
+    };
+
+    mouse_report(point, button, ElementState::Pressed, mods, mode)
+}
+
+fn normal_mouse_report(point: Pos, button: u8, mode: &Mode) -> Option<Vec<u8>> {
+    let utf8 = mode.contains(Mode::UTF8_MOUSE);
+    let max_point = if utf8 { 2015 } else { 223 };
+    let line = point.row.0 as usize;
+    let column = point.col.0;
+
+    if line >= max_point || column >= max_point {
+        return None;
+    }
+
+    let mut msg = vec![b'\x1b', b'[', b'M', 32 + button];
+
+    let mouse_pos_encode = |pos: usize| -> Vec<u8> {
+        let pos = 32 + 1 + pos as u32;
+        let mut pos_encode = Vec::with_capacity(2);
+        if utf8 && pos >= 128 {
+            pos_encode.push(0xc0 + (pos / 64) as u8);
+            pos_encode.push(0x80 + (pos & 63) as u8);
+        } else {
+            pos_encode.push(pos as u8);
+        }
+        pos_encode
+    };
+
+    msg.append(&mut mouse_pos_encode(column));
+    msg.append(&mut mouse_pos_encode(line));
+    Some(msg)
+}
+
+fn sgr_mouse_report(point: Pos, button: u8, state: ElementState) -> Vec<u8> {
+    let c = match state {
+        ElementState::Pressed => 'M',
+        ElementState::Released => 'm',
+    };
+
+    format!(
+        "\x1b[<{};{};{}{}",
+        button,
+        point.col.0 + 1,
+        point.row.0 + 1,
+        c
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::crosswords::pos::{Column, Line};
+
+    #[test]
+    fn test_normal_mouse_report() {
+        let point = Pos::new(Line(1), Column(2));
+        let mode = Mode::MOUSE_REPORT_CLICK;
+        let mods = ModifiersState::empty();
+
+        assert_eq!(
+            mouse_report(point, 0, ElementState::Pressed, mods, &mode),
+            Some(b"\x1b[M #\"".to_vec())
+        );
+        assert_eq!(
+            mouse_report(point, 0, ElementState::Released, mods, &mode),
+            Some(b"\x1b[M##\"".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_sgr_mouse_report() {
+        let point = Pos::new(Line(1), Column(2));
+        let mode = Mode::MOUSE_REPORT_CLICK | Mode::SGR_MOUSE;
+
+        assert_eq!(
+            mouse_report(
+                point,
+                0,
+                ElementState::Released,
+                ModifiersState::CTRL,
+                &mode
+            ),
+            Some(b"\x1b[<16;3;2m".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_motion_report_once_per_square() {
+        let mouse = Mouse::default();
+        let mode = Mode::MOUSE_MOTION | Mode::SGR_MOUSE;
+        let mods = ModifiersState::empty();
+        let point = Pos::new(Line(0), Column(4));
+
+        // Moving inside the same square is not reported.
+        assert_eq!(motion_report(point, point, &mouse, mods, &mode), None);
+
+        // Crossing to another square reports motion without buttons.
+        let next_point = Pos::new(Line(0), Column(5));
+        assert_eq!(
+            motion_report(point, next_point, &mouse, mods, &mode),
+            Some(b"\x1b[<35;6;1M".to_vec())
+        );
+
+        // Pure motion is not reported when only drag is tracked.
+        let mode = Mode::MOUSE_DRAG | Mode::SGR_MOUSE;
+        assert_eq!(motion_report(point, next_point, &mouse, mods, &mode), None);
+    }
+}
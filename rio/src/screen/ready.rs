@@ -0,0 +1,48 @@
+/// Bytes written to the pty once the first frame is presented, so test
+/// harnesses and other automation can wait for the terminal to be up.
+pub struct ReadyMarker {
+    marker: Option<Vec<u8>>,
+}
+
+impl ReadyMarker {
+    pub fn new(marker: Option<String>) -> ReadyMarker {
+        ReadyMarker {
+            marker: marker
+                .filter(|marker| !marker.is_empty())
+                .map(String::into_bytes),
+        }
+    }
+
+    /// Bytes to write after drawing a frame, only the first time `rendered`
+    /// is true.
+    pub fn take(&mut self, rendered: bool) -> Option<Vec<u8>> {
+        if rendered {
+            self.marker.take()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_ready_marker_after_first_frame() {
+        let mut ready = ReadyMarker::new(Some(String::from("\x1b[0n")));
+        assert_eq!(ready.take(false), None);
+
+        assert_eq!(ready.take(true), Some(b"\x1b[0n".to_vec()));
+        assert_eq!(ready.take(true), None);
+    }
+
+    #[test]
+    fn test_ready_marker_disabled() {
+        let mut ready = ReadyMarker::new(None);
+        assert_eq!(ready.take(true), None);
+
+        let mut ready = ReadyMarker::new(Some(String::new()));
+        assert_eq!(ready.take(true), None);
+    }
+}
@@ -2,21 +2,25 @@ use crate::ansi::CursorShape;
 use crate::crosswords::grid::row::Row;
 use crate::crosswords::pos;
 use crate::crosswords::pos::CursorState;
-use crate::crosswords::square::{Flags, Square};
+use crate::crosswords::square::{Flags, LineLength, Square};
 use crate::ime::Preedit;
-use crate::screen::context;
-use crate::screen::EventProxy;
+use crate::screen::flash::Flashes;
 use crate::selection::SelectionRange;
 use colors::{
-    term::{List, TermColors},
-    AnsiColor, Colors, NamedColor,
+    term::{nearest, nearest_256, List, TermColors},
+    AnsiColor, ColorArray, ColorRgb, ColorWGPU, Colors, NamedColor,
 };
-use config::Config;
+use config::{ColorDepth, Config};
+use std::ops::Range;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use sugarloaf::components::rect::Rect;
 use sugarloaf::core::{Sugar, SugarDecoration, SugarStack, SugarStyle};
 use sugarloaf::Sugarloaf;
 
+/// Brightness kept by the cells outside of the matches with `search-spotlight`.
+const SPOTLIGHT_DIM: f32 = 0.4;
+
 #[derive(Default)]
 struct Cursor {
     state: CursorState,
@@ -24,13 +28,37 @@ struct Cursor {
     content_ref: char,
 }
 
+/// Cells drawn by the embedder on top of the terminal grid (e.g. a command
+/// palette), aligned to the grid and starting at `origin`.
+pub struct OverlayGrid {
+    pub origin: pos::Pos,
+    pub rows: Vec<Row<Square>>,
+    pub opacity: f32,
+}
+
 pub struct State {
     pub option_as_alt: bool,
     is_ime_enabled: bool,
     named_colors: Colors,
+    /// Colors of the theme, before `set_palette_color` changes them.
+    theme: Colors,
     pub colors: List,
     cursor: Cursor,
     selection_range: Option<SelectionRange>,
+    overlay: Option<OverlayGrid>,
+    search_query: Option<Vec<char>>,
+    search_spotlight: bool,
+    flashes: Flashes,
+    wrap_indicator: Option<char>,
+    bar_cursor_width: f32,
+    underline_cursor_height: Option<f32>,
+    tab_leader: Option<char>,
+    indent_guides: Option<config::IndentGuides>,
+    tab_stops: Vec<bool>,
+    tabs: (usize, usize),
+    fill_cursor_line: bool,
+    pen_background: AnsiColor,
+    color_depth: ColorDepth,
 }
 
 // TODO: Finish from
@@ -60,6 +88,29 @@ impl From<Square> for Sugar {
     }
 }
 
+/// Theme color of the ANSI palette entry `index` (0 to 15).
+fn ansi_color_mut(colors: &mut Colors, index: u8) -> Option<&mut ColorArray> {
+    Some(match index {
+        0 => &mut colors.black,
+        1 => &mut colors.red,
+        2 => &mut colors.green,
+        3 => &mut colors.yellow,
+        4 => &mut colors.blue,
+        5 => &mut colors.magenta,
+        6 => &mut colors.cyan,
+        7 => &mut colors.white,
+        8 => &mut colors.light_black,
+        9 => &mut colors.light_red,
+        10 => &mut colors.light_green,
+        11 => &mut colors.light_yellow,
+        12 => &mut colors.light_blue,
+        13 => &mut colors.light_magenta,
+        14 => &mut colors.light_cyan,
+        15 => &mut colors.light_white,
+        _ => return None,
+    })
+}
+
 impl State {
     pub fn new(config: &Rc<Config>) -> State {
         let term_colors = TermColors::default();
@@ -75,7 +126,22 @@ impl State {
             is_ime_enabled: false,
             colors,
             selection_range: None,
+            overlay: None,
+            search_query: None,
+            search_spotlight: config.style.search_spotlight,
+            flashes: Flashes::default(),
+            wrap_indicator: config.style.wrap_indicator,
+            bar_cursor_width: config.bar_cursor_width,
+            underline_cursor_height: config.underline_cursor_height,
+            tab_leader: config.style.tab_leader,
+            indent_guides: config.style.indent_guides,
+            tab_stops: vec![],
+            tabs: (1, 0),
+            fill_cursor_line: config.style.fill_cursor_line,
+            pen_background: AnsiColor::Named(NamedColor::Background),
+            color_depth: config.style.color_depth,
             named_colors: config.colors,
+            theme: config.colors,
             cursor: Cursor {
                 content: config.cursor,
                 content_ref: config.cursor,
@@ -88,9 +154,10 @@ impl State {
         self.cursor.state.clone()
     }
 
-    // TODO: Square.into()
+    /// Foreground and background a square is drawn with, after resolving
+    /// the palette and reverse video.
     #[inline]
-    fn create_sugar(&self, square: &Square) -> Sugar {
+    fn square_colors(&self, square: &Square) -> (ColorArray, ColorArray) {
         let flags = square.flags;
 
         let foreground_color = match square.fg {
@@ -183,6 +250,71 @@ impl State {
             AnsiColor::Indexed(idx) => self.colors[idx as usize],
         };
 
+        let (foreground_color, background_color) = if flags.contains(Flags::INVERSE) {
+            (background_color, foreground_color)
+        } else {
+            (foreground_color, background_color)
+        };
+
+        (
+            self.clamp_color(foreground_color),
+            self.clamp_color(background_color),
+        )
+    }
+
+    /// Replaces the theme colors, e.g. when the system appearance changes.
+    /// Palette entries changed with `set_palette_color` are reset.
+    pub fn set_colors(&mut self, colors: Colors) {
+        self.named_colors = colors;
+        self.theme = colors;
+        self.colors = List::from(&TermColors::default());
+    }
+
+    /// Color the window is cleared with.
+    #[inline]
+    pub fn background_color(&self) -> ColorWGPU {
+        self.named_colors.background.1
+    }
+
+    /// Color of the 256 color palette entry `index`, the first 16 are the
+    /// ANSI colors of the theme.
+    pub fn palette_color(&self, index: u8) -> ColorArray {
+        // Colors are copied to look the ANSI ones up like they're changed.
+        let mut colors = self.named_colors;
+        match ansi_color_mut(&mut colors, index) {
+            Some(color) => *color,
+            None => self.colors[index as usize],
+        }
+    }
+
+    /// Replaces the 256 color palette entry `index`, cells using it change on
+    /// the next frame.
+    pub fn set_palette_color(&mut self, index: u8, color: ColorArray) {
+        if let Some(ansi_color) = ansi_color_mut(&mut self.named_colors, index) {
+            *ansi_color = color;
+        }
+        self.colors[index as usize] = color;
+    }
+
+    /// Goes back to the color of the palette entry `index` before
+    /// `set_palette_color`.
+    pub fn reset_palette_color(&mut self, index: u8) {
+        let mut theme = self.theme;
+        if let (Some(color), Some(theme_color)) = (
+            ansi_color_mut(&mut self.named_colors, index),
+            ansi_color_mut(&mut theme, index),
+        ) {
+            *color = *theme_color;
+        }
+        self.colors[index as usize] = List::from(&TermColors::default())[index as usize];
+    }
+
+    // TODO: Square.into()
+    #[inline]
+    fn create_sugar(&self, square: &Square) -> Sugar {
+        let flags = square.flags;
+        let (foreground_color, background_color) = self.square_colors(square);
+
         let content = if square.c == '\t' || flags.contains(Flags::HIDDEN) {
             ' '
         } else {
@@ -207,12 +339,16 @@ impl State {
             decoration = Some(SugarDecoration {
                 position: (0.0, 0.95),
                 size: (1.0, 0.05),
+                pixel_width: None,
+                pixel_height: None,
                 color: self.named_colors.foreground,
             });
         } else if flags.contains(Flags::STRIKEOUT) {
             decoration = Some(SugarDecoration {
                 position: (0.0, 0.5),
                 size: (1.0, 0.05),
+                pixel_width: None,
+                pixel_height: None,
                 color: self.named_colors.foreground,
             });
         }
@@ -226,22 +362,68 @@ impl State {
         }
     }
 
+    /// Closest color available with the configured `color-depth`.
+    #[inline]
+    fn clamp_color(&self, color: ColorArray) -> ColorArray {
+        let clamped = match self.color_depth {
+            ColorDepth::TrueColor => return color,
+            ColorDepth::Ansi256 => {
+                self.colors[nearest_256(ColorRgb::from_color_arr(color)) as usize]
+            }
+            ColorDepth::Ansi16 => {
+                let colors = &self.named_colors;
+                let palette = [
+                    colors.black,
+                    colors.red,
+                    colors.green,
+                    colors.yellow,
+                    colors.blue,
+                    colors.magenta,
+                    colors.cyan,
+                    colors.white,
+                    colors.light_black,
+                    colors.light_red,
+                    colors.light_green,
+                    colors.light_yellow,
+                    colors.light_blue,
+                    colors.light_magenta,
+                    colors.light_cyan,
+                    colors.light_white,
+                ];
+                palette[nearest(&palette, color)]
+            }
+            ColorDepth::Monochrome => {
+                let palette =
+                    [self.named_colors.background.0, self.named_colors.foreground];
+                palette[nearest(&palette, color)]
+            }
+        };
+
+        [clamped[0], clamped[1], clamped[2], color[3]]
+    }
+
     #[inline]
     fn cursor_to_decoration(&self) -> Option<SugarDecoration> {
         match self.cursor.state.content {
             CursorShape::Block => Some(SugarDecoration {
                 position: (0.0, 0.0),
                 size: (1.0, 1.0),
+                pixel_width: None,
+                pixel_height: None,
                 color: self.named_colors.cursor,
             }),
             CursorShape::Underline => Some(SugarDecoration {
                 position: (0.0, 0.95),
                 size: (1.0, 0.05),
+                pixel_width: None,
+                pixel_height: self.underline_cursor_height,
                 color: self.named_colors.cursor,
             }),
             CursorShape::Beam => Some(SugarDecoration {
-                position: (0.0, 0.0),
+                position: (self.cursor.state.column_offset, 0.0),
                 size: (0.1, 1.0),
+                pixel_width: Some(self.bar_cursor_width),
+                pixel_height: None,
                 color: self.named_colors.cursor,
             }),
             CursorShape::Hidden => None,
@@ -307,6 +489,140 @@ impl State {
         stack
     }
 
+    /// Background of the current pen, used to fill the cursor line.
+    #[inline]
+    pub fn set_pen_background(&mut self, background: AnsiColor) {
+        self.pen_background = background;
+    }
+
+    /// Tabs are filled with a leader character.
+    #[inline]
+    pub fn has_tab_leader(&self) -> bool {
+        self.tab_leader.is_some()
+    }
+
+    /// Tab stops of the terminal, where the leader of a tab ends.
+    #[inline]
+    pub fn set_tab_stops(&mut self, tab_stops: Vec<bool>) {
+        self.tab_stops = tab_stops;
+    }
+
+    /// Number of tabs and the current one, they're drawn when there's more
+    /// than one.
+    #[inline]
+    pub fn set_tabs(&mut self, count: usize, current: usize) {
+        self.tabs = (count, current);
+    }
+
+    /// Paints the blank background of the cursor row with the pen background,
+    /// so a colored prompt line reaches the window edge.
+    #[inline]
+    fn fill_cursor_line(&self, row: &mut Row<Square>) {
+        let background = AnsiColor::Named(NamedColor::Background);
+        for square in row.inner.iter_mut() {
+            if square.bg == background {
+                square.bg = self.pen_background;
+            }
+        }
+    }
+
+    /// Draws the wrap indicator right after the last column of soft-wrapped
+    /// rows, so it never covers grid content and isn't part of copied text.
+    #[inline]
+    fn apply_wrap_indicator(&self, stack: &mut SugarStack, row: &Row<Square>) {
+        let Some(indicator) = self.wrap_indicator else { return };
+
+        if row.is_wrapped() {
+            stack.push(Sugar {
+                content: indicator,
+                foreground_color: self.named_colors.dim_foreground,
+                background_color: self.named_colors.background.0,
+                style: None,
+                decoration: None,
+            });
+        }
+    }
+
+    /// Fills the cells a tab skipped over with the leader, up to the next tab
+    /// stop or the first cell written since. Only what's drawn changes, the
+    /// grid keeps the tab for copying.
+    #[inline]
+    fn apply_tab_leader(&self, stack: &mut SugarStack, row: &Row<Square>) {
+        let Some(leader) = self.tab_leader else { return };
+
+        let mut in_tab = false;
+        for (column, square) in row.inner.iter().enumerate() {
+            if in_tab && (self.tab_stops.get(column) != Some(&false) || square.c != ' ') {
+                in_tab = false;
+            }
+            if square.c == '\t' {
+                in_tab = true;
+            }
+            if !in_tab {
+                continue;
+            }
+
+            // The cursor and selected cells keep their content.
+            if let Some(sugar) =
+                stack.get_mut(column).filter(|sugar| sugar.content == ' ')
+            {
+                sugar.content = leader;
+                sugar.foreground_color = self.named_colors.dim_foreground;
+            }
+        }
+    }
+
+    /// Draws a guide on the left edge of every `interval` column of the
+    /// leading whitespace, rows without text have none.
+    #[inline]
+    fn apply_indent_guides(&self, stack: &mut SugarStack, row: &Row<Square>) {
+        let Some(guides) = self.indent_guides else { return };
+        let interval = usize::from(guides.interval.max(1));
+        let Some(indentation) = row
+            .inner
+            .iter()
+            .position(|square| square.c != ' ' && square.c != '\t')
+        else {
+            return;
+        };
+
+        for column in (interval..indentation).step_by(interval) {
+            // The cursor keeps its shape.
+            if let Some(sugar) = stack
+                .get_mut(column)
+                .filter(|sugar| sugar.decoration.is_none())
+            {
+                sugar.decoration = Some(SugarDecoration {
+                    position: (0.0, 0.0),
+                    size: (0.1, 1.0),
+                    pixel_width: Some(1.0),
+                    pixel_height: None,
+                    color: guides.color,
+                });
+            }
+        }
+    }
+
+    /// Replaces the sugars covered by the overlay grid on `line`.
+    #[inline]
+    fn apply_overlay(&self, stack: &mut SugarStack, line: pos::Line) {
+        let Some(overlay) = &self.overlay else { return };
+
+        let Ok(index) = usize::try_from((line - overlay.origin.row).0) else {
+            return;
+        };
+        let Some(row) = overlay.rows.get(index) else { return };
+
+        let start = overlay.origin.col.0;
+        for (column, square) in row.inner.iter().enumerate() {
+            if let Some(sugar) = stack.get_mut(start + column) {
+                let mut overlay_sugar = self.create_sugar(square);
+                overlay_sugar.background_color[3] *= overlay.opacity;
+                *sugar = overlay_sugar;
+            }
+        }
+    }
+
     #[inline]
     fn create_cursor(&self, square: &Square) -> Sugar {
         let mut cloned_square = square.clone();
@@ -340,54 +656,184 @@ impl State {
         self.cursor.content = self.cursor.content_ref;
     }
 
+    /// Highlights every visible match of `query`, the bottom-most match is
+    /// the active one.
+    #[inline]
+    #[allow(dead_code)]
+    pub fn set_search_query(&mut self, query: Option<String>) {
+        self.search_query = query
+            .filter(|query| !query.is_empty())
+            .map(|query| query.chars().collect());
+    }
+
+    /// Columns of every match of the search query in each row.
+    fn search_matches(&self, rows: &[Row<Square>]) -> Vec<Vec<Range<usize>>> {
+        let Some(query) = &self.search_query else { return vec![] };
+        rows.iter().map(|row| find_matches(row, query)).collect()
+    }
+
+    #[inline]
+    fn apply_search(
+        &self,
+        stack: &mut SugarStack,
+        matches: &[Range<usize>],
+        active: Option<usize>,
+    ) {
+        for (index, range) in matches.iter().enumerate() {
+            let background_color = if Some(index) == active {
+                self.named_colors.light_yellow
+            } else {
+                self.named_colors.dim_yellow
+            };
+
+            for sugar in stack[range.clone()].iter_mut() {
+                sugar.foreground_color = self.named_colors.background.0;
+                sugar.background_color = background_color;
+            }
+        }
+    }
+
+    /// Darkens the cells outside of `matches`, which are sorted by column.
+    #[inline]
+    fn apply_spotlight(&self, stack: &mut SugarStack, matches: &[Range<usize>]) {
+        let mut matches = matches.iter().peekable();
+        for (column, sugar) in stack.iter_mut().enumerate() {
+            while matches.next_if(|range| range.end <= column).is_some() {}
+            if matches
+                .peek()
+                .map_or(false, |range| range.contains(&column))
+            {
+                continue;
+            }
+
+            for color in [&mut sugar.foreground_color, &mut sugar.background_color] {
+                for channel in &mut color[..3] {
+                    *channel *= SPOTLIGHT_DIM;
+                }
+            }
+        }
+    }
+
+    /// Highlights the visible cells from `start` to `end`, fading out over
+    /// `duration`.
+    pub fn flash_range(
+        &mut self,
+        start: (u16, u16),
+        end: (u16, u16),
+        color: [f32; 4],
+        duration: Duration,
+        now: Instant,
+    ) {
+        self.flashes.add(start, end, color, duration, now);
+    }
+
+    /// Some flash still needs frames.
+    #[inline]
+    pub fn is_flashing(&self) -> bool {
+        self.flashes.is_flashing()
+    }
+
+    /// Blends the flashes over the backgrounds of the visible `row`.
+    #[inline]
+    fn apply_flashes(&self, stack: &mut SugarStack, row: usize, now: Instant) {
+        for (column, sugar) in stack.iter_mut().enumerate() {
+            let Some([r, g, b, a]) = self.flashes.color(row as u16, column as u16, now)
+            else {
+                continue;
+            };
+
+            for (channel, flash) in sugar.background_color[..3].iter_mut().zip([r, g, b])
+            {
+                *channel = *channel * (1. - a) + flash * a;
+            }
+        }
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn set_overlay_grid(&mut self, overlay: Option<OverlayGrid>) {
+        self.overlay = overlay;
+    }
+
     #[inline]
     pub fn set_selection(&mut self, selection_range: Option<SelectionRange>) {
         self.selection_range = selection_range;
     }
 
+    /// Stacks the visible `rows` on `sugarloaf` as they look at `now`.
     #[inline]
     pub fn update(
         &mut self,
-        rows: Vec<Row<Square>>,
+        mut rows: Vec<Row<Square>>,
+        display_offset: usize,
         cursor: CursorState,
         sugarloaf: &mut Sugarloaf,
         styles: &crate::layout::Styles,
-        context_manager: &context::ContextManager<EventProxy>,
+        now: Instant,
     ) {
         self.cursor.state = cursor;
 
         let term_style = styles.term;
         let is_cursor_visible = self.cursor.state.is_visible();
+        self.flashes.prune(now);
 
-        if let Some(sel) = self.selection_range {
-            for (i, row) in rows.iter().enumerate() {
-                let has_cursor = is_cursor_visible && self.cursor.state.pos.row == i;
-                let sugar_stack = self.create_sugar_stack_with_selection(
+        let search_matches = self.search_matches(&rows);
+        let active_row = search_matches
+            .iter()
+            .rposition(|matches| !matches.is_empty());
+        let search_active = |row: usize| -> Option<usize> {
+            if Some(row) == active_row {
+                search_matches[row].len().checked_sub(1)
+            } else {
+                None
+            }
+        };
+
+        for (i, row) in rows.iter_mut().enumerate() {
+            let has_cursor = is_cursor_visible && self.cursor.state.pos.row == i;
+            if self.fill_cursor_line
+                && grid_line(i, display_offset) == self.cursor.state.pos.row
+            {
+                self.fill_cursor_line(row);
+            }
+            let mut sugar_stack = match self.selection_range {
+                Some(sel) => self.create_sugar_stack_with_selection(
                     row,
                     has_cursor,
                     &sel,
-                    pos::Line(i as i32),
-                );
-                sugarloaf.stack(sugar_stack, term_style);
+                    grid_line(i, display_offset),
+                ),
+                None => self.create_sugar_stack(row, has_cursor),
+            };
+            self.apply_wrap_indicator(&mut sugar_stack, row);
+            self.apply_tab_leader(&mut sugar_stack, row);
+            self.apply_indent_guides(&mut sugar_stack, row);
+            if let Some(matches) = search_matches.get(i) {
+                self.apply_search(&mut sugar_stack, matches, search_active(i));
+                if self.search_spotlight {
+                    self.apply_spotlight(&mut sugar_stack, matches);
+                }
             }
-
-            return;
+            if self.flashes.is_flashing() {
+                self.apply_flashes(&mut sugar_stack, i, now);
+            }
+            self.apply_overlay(&mut sugar_stack, pos::Line(i as i32));
+            sugarloaf.stack(sugar_stack, term_style);
         }
 
-        for (i, row) in rows.iter().enumerate() {
-            let has_cursor = is_cursor_visible && self.cursor.state.pos.row == i;
-            let sugar_stack = self.create_sugar_stack(row, has_cursor);
-            sugarloaf.stack(sugar_stack, term_style);
+        if !styles.borders.is_empty() {
+            sugarloaf.pile_rect(styles.borders.clone());
         }
 
-        if context_manager.len() > 1 {
+        let (tabs, current_tab) = self.tabs;
+        if tabs > 1 {
             let mut renderable_tabs = vec![];
             let mut initial_position = styles.tabs_initial_position;
             let position_modifier = 20.;
-            for (i, _) in context_manager.contexts().iter().enumerate() {
+            for i in 0..tabs {
                 let mut color = self.named_colors.tabs;
                 let mut size = 16.0;
-                if i == context_manager.current_index() {
+                if i == current_tab {
                     color = self.named_colors.tabs_active;
                     size = 26.0;
                 }
@@ -410,3 +856,488 @@ impl State {
     //         String::from("")
     //     };
 }
+
+/// Finds non-overlapping matches of `query` in the occupied part of `row`,
+/// the spacer of a wide char is included in its match.
+fn find_matches(row: &Row<Square>, query: &[char]) -> Vec<Range<usize>> {
+    let columns: Vec<usize> = (0..row.line_length().0)
+        .filter(|column| !row.inner[*column].flags.contains(Flags::WIDE_CHAR_SPACER))
+        .collect();
+
+    let mut matches = vec![];
+    let mut start = 0;
+    while start + query.len() <= columns.len() {
+        let is_match = query
+            .iter()
+            .enumerate()
+            .all(|(offset, c)| row.inner[columns[start + offset]].c == *c);
+
+        if !is_match {
+            start += 1;
+            continue;
+        }
+
+        let last_column = columns[start + query.len() - 1];
+        let end = if row.inner[last_column].flags.contains(Flags::WIDE_CHAR) {
+            last_column + 2
+        } else {
+            last_column + 1
+        };
+        matches.push(columns[start]..std::cmp::min(end, row.len()));
+        start += query.len();
+    }
+
+    matches
+}
+
+/// Grid line shown at the visible `row`, scrollback lines are negative.
+#[inline]
+fn grid_line(row: usize, display_offset: usize) -> pos::Line {
+    pos::Line(row as i32) - display_offset
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use config::Appearance;
+
+    fn row_of(content: &str) -> Row<Square> {
+        let mut row: Row<Square> = Row::new(content.chars().count());
+        for (i, c) in content.chars().enumerate() {
+            row.inner[i].c = c;
+        }
+        row
+    }
+
+    #[test]
+    fn test_overlay_grid_wins_over_base_cells() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        state.set_overlay_grid(Some(OverlayGrid {
+            origin: pos::Pos::new(pos::Line(1), pos::Column(1)),
+            rows: vec![row_of("xy")],
+            opacity: 0.5,
+        }));
+
+        let base = row_of("abcd");
+
+        let mut stack = state.create_sugar_stack(&base, false);
+        state.apply_overlay(&mut stack, pos::Line(0));
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "abcd");
+
+        let mut stack = state.create_sugar_stack(&base, false);
+        state.apply_overlay(&mut stack, pos::Line(1));
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "axyd");
+        assert_eq!(
+            stack[1].background_color[3],
+            stack[0].background_color[3] * 0.5
+        );
+
+        let mut stack = state.create_sugar_stack(&base, false);
+        state.apply_overlay(&mut stack, pos::Line(2));
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "abcd");
+    }
+
+    #[test]
+    fn test_search_highlights_all_matches_with_one_active() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        state.set_search_query(Some(String::from("ab")));
+
+        let rows = vec![row_of("ab ab   "), row_of("cd      "), row_of("xxab    ")];
+        let matches = state.search_matches(&rows);
+        assert_eq!(matches, vec![vec![0..2, 3..5], vec![], vec![2..4]]);
+
+        let mut stack = state.create_sugar_stack(&rows[0], false);
+        state.apply_search(&mut stack, &matches[0], None);
+        let match_color = state.named_colors.dim_yellow;
+        assert_eq!(stack[0].background_color, match_color);
+        assert_eq!(stack[1].background_color, match_color);
+        assert_ne!(stack[2].background_color, match_color);
+        assert_eq!(stack[3].background_color, match_color);
+
+        let mut stack = state.create_sugar_stack(&rows[2], false);
+        state.apply_search(&mut stack, &matches[2], Some(0));
+        let active_color = state.named_colors.light_yellow;
+        assert_eq!(stack[2].background_color, active_color);
+        assert_eq!(stack[3].background_color, active_color);
+    }
+
+    #[test]
+    fn test_search_spotlight_dims_other_cells() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        state.set_search_query(Some(String::from("ab")));
+
+        let rows = vec![row_of("ab xab  ")];
+        let matches = state.search_matches(&rows);
+        let mut stack = state.create_sugar_stack(&rows[0], false);
+        state.apply_search(&mut stack, &matches[0], Some(1));
+        state.apply_spotlight(&mut stack, &matches[0]);
+
+        let brightness = |column: usize| -> f32 {
+            let [r, g, b, _] = stack[column].background_color;
+            r + g + b
+        };
+        for column in [2, 3, 6] {
+            for matched in [0, 1, 4, 5] {
+                assert!(brightness(column) < brightness(matched));
+            }
+        }
+        assert_eq!(stack[4].background_color, state.named_colors.light_yellow);
+    }
+
+    #[test]
+    fn test_flash_overlay_goes_away() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        let now = Instant::now();
+        let duration = Duration::from_millis(100);
+        state.flash_range((0, 1), (0, 2), [1.0, 1.0, 1.0, 1.0], duration, now);
+
+        let row = row_of("abcd");
+        let background = state.create_sugar_stack(&row, false)[0].background_color;
+        let mut stack = state.create_sugar_stack(&row, false);
+        state.apply_flashes(&mut stack, 0, now);
+        assert_eq!(stack[0].background_color, background);
+        assert_eq!(stack[1].background_color, [1.0, 1.0, 1.0, background[3]]);
+        assert_eq!(stack[3].background_color, background);
+
+        let mut stack = state.create_sugar_stack(&row, false);
+        state.apply_flashes(&mut stack, 0, now + duration);
+        assert_eq!(stack[1].background_color, background);
+    }
+
+    #[test]
+    fn test_square_colors_of_reverse_video() {
+        let state = State::new(&Rc::new(Config::default()));
+        let mut row = row_of("ab");
+        row.inner[1].fg = AnsiColor::Named(NamedColor::Red);
+        row.inner[1].flags.insert(Flags::INVERSE);
+
+        let colors = &state.named_colors;
+        assert_eq!(
+            state.square_colors(&row.inner[0]),
+            (colors.foreground, colors.background.0)
+        );
+        assert_eq!(
+            state.square_colors(&row.inner[1]),
+            (colors.background.0, colors.red)
+        );
+    }
+
+    #[test]
+    fn test_appearance_changes_the_default_background() {
+        let mut config = Config::default();
+        let mut light = config.colors;
+        light.background = (
+            colors::hex_to_color_arr("#FFFFFF"),
+            colors::hex_to_color_wgpu("#FFFFFF"),
+        );
+        config.light_colors = Some(light);
+        let mut state = State::new(&Rc::new(config.clone()));
+        let row = row_of("ab");
+        let dark_background = state.square_colors(&row.inner[0]).1;
+        assert_eq!(dark_background, config.colors.background.0);
+
+        state.set_colors(config.colors_for(Appearance::Light));
+        assert_eq!(state.background_color(), light.background.1);
+        assert_eq!(state.square_colors(&row.inner[0]).1, light.background.0);
+
+        // No dark theme, back to the usual colors.
+        state.set_colors(config.colors_for(Appearance::Dark));
+        assert_eq!(state.square_colors(&row.inner[0]).1, dark_background);
+    }
+
+    #[test]
+    fn test_set_colors_resets_palette_entries() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        let (red, entry) = (state.palette_color(1), state.palette_color(200));
+        let custom = [0.5, 0.25, 0.0, 1.0];
+        state.set_palette_color(1, custom);
+        state.set_palette_color(200, custom);
+
+        state.set_colors(Config::default().colors);
+        assert_eq!(state.palette_color(1), red);
+        assert_eq!(state.palette_color(200), entry);
+    }
+
+    #[test]
+    fn test_palette_color_changes_rendered_runs() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        let mut terminal =
+            crate::crosswords::Crosswords::new(4, 1, crate::event::VoidListener {});
+        let mut parser = crate::performer::handler::ParserProcessor::new();
+        for byte in b"\x1b[31mab\x1b[38;5;1mc" {
+            parser.advance(&mut terminal, *byte);
+        }
+        let rows = terminal.visible_rows();
+        assert_eq!(state.palette_color(1), state.named_colors.red);
+
+        let custom = [0.5, 0.25, 0.0, 1.0];
+        state.set_palette_color(1, custom);
+        assert_eq!(state.palette_color(1), custom);
+        let stack = state.create_sugar_stack(&rows[0], false);
+        assert_eq!(stack[0].foreground_color, custom);
+        assert_eq!(stack[1].foreground_color, custom);
+        assert_eq!(stack[2].foreground_color, custom);
+        assert_eq!(stack[3].foreground_color, state.named_colors.foreground);
+
+        // Past the ANSI colors it's the 256 color palette.
+        let default_200 = state.palette_color(200);
+        state.set_palette_color(200, custom);
+        assert_eq!(state.palette_color(200), custom);
+        assert_eq!(state.palette_color(2), state.named_colors.green);
+
+        state.reset_palette_color(1);
+        state.reset_palette_color(200);
+        assert_eq!(state.palette_color(1), Config::default().colors.red);
+        assert_eq!(state.palette_color(200), default_200);
+    }
+
+    #[test]
+    fn test_cursor_shapes_at_the_first_cell() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        let row = row_of("ab");
+
+        for shape in [
+            CursorShape::Block,
+            CursorShape::Underline,
+            CursorShape::Beam,
+        ] {
+            state.cursor.state.pos = pos::Pos::new(pos::Line(0), pos::Column(0));
+            state.cursor.state.content = shape;
+            let stack = state.create_sugar_stack(&row, true);
+
+            let decoration = stack[0].decoration.as_ref().unwrap();
+            assert_eq!(decoration.color, state.named_colors.cursor);
+            assert!(stack[1].decoration.is_none());
+            match shape {
+                // The block covers the cell, the character is drawn over it
+                // with the background color.
+                CursorShape::Block => {
+                    assert_eq!(decoration.position, (0.0, 0.0));
+                    assert_eq!(decoration.size, (1.0, 1.0));
+                    assert_eq!(
+                        stack[0].foreground_color,
+                        state.named_colors.background.0
+                    );
+                }
+                CursorShape::Underline => {
+                    assert_eq!(decoration.position.0, 0.0);
+                    assert_eq!(decoration.position.1 + decoration.size.1, 1.0);
+                    assert_eq!(stack[0].foreground_color, stack[1].foreground_color);
+                }
+                CursorShape::Beam => {
+                    assert_eq!(decoration.position, (0.0, 0.0));
+                    assert_eq!(decoration.pixel_width, Some(state.bar_cursor_width));
+                    assert_eq!(stack[0].foreground_color, stack[1].foreground_color);
+                }
+                CursorShape::Hidden => unreachable!(),
+            }
+        }
+
+        state.cursor.state.content = CursorShape::Hidden;
+        let stack = state.create_sugar_stack(&row, true);
+        assert!(stack.iter().all(|sugar| sugar.decoration.is_none()));
+    }
+
+    #[test]
+    fn test_indent_guides_at_the_configured_columns() {
+        let mut config = Config::default();
+        config.style.indent_guides = Some(config::IndentGuides {
+            interval: 2,
+            color: [1.0, 0.0, 0.0, 1.0],
+        });
+        let mut state = State::new(&Rc::new(config));
+        let mut guided = |content: &str| -> SugarStack {
+            let row = row_of(content);
+            let mut stack = state.create_sugar_stack(&row, false);
+            state.apply_indent_guides(&mut stack, &row);
+            stack
+        };
+        let columns = |stack: &SugarStack| -> Vec<usize> {
+            stack
+                .iter()
+                .enumerate()
+                .filter(|(_, sugar)| sugar.decoration.is_some())
+                .map(|(column, _)| column)
+                .collect()
+        };
+
+        let stack = guided("      x = 1;");
+        assert_eq!(columns(&stack), vec![2, 4]);
+        let decoration = stack[2].decoration.as_ref().unwrap();
+        assert_eq!(decoration.position, (0.0, 0.0));
+        assert_eq!(decoration.pixel_width, Some(1.0));
+        assert_eq!(decoration.color, [1.0, 0.0, 0.0, 1.0]);
+
+        // Nothing past the indentation or on blank rows.
+        assert!(columns(&guided("  a    b")).is_empty());
+        assert!(columns(&guided("        ")).is_empty());
+    }
+
+    #[test]
+    fn test_search_ignores_blank_cells() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        state.set_search_query(Some(String::from(" ")));
+
+        let rows = vec![row_of("a b     "), row_of("        ")];
+        assert_eq!(state.search_matches(&rows), vec![vec![1..2], vec![]]);
+
+        state.set_search_query(Some(String::new()));
+        assert!(state.search_matches(&rows).is_empty());
+    }
+
+    #[test]
+    fn test_selection_across_scrollback_boundary() {
+        let mut state = State::new(&Rc::new(Config::default()));
+        // From the last scrollback line into the first screen line.
+        let range = SelectionRange::new(
+            pos::Pos::new(pos::Line(-1), pos::Column(2)),
+            pos::Pos::new(pos::Line(0), pos::Column(1)),
+            false,
+        );
+
+        let rows = vec![row_of("abcd"), row_of("efgh"), row_of("ijkl")];
+        let display_offset = 1;
+        let selected = |state: &mut State, i: usize| -> String {
+            let line = grid_line(i, display_offset);
+            state
+                .create_sugar_stack_with_selection(&rows[i], false, &range, line)
+                .iter()
+                .filter(|sugar| sugar.background_color == state.named_colors.light_blue)
+                .map(|sugar| sugar.content)
+                .collect()
+        };
+
+        assert_eq!(selected(&mut state, 0), "cd");
+        assert_eq!(selected(&mut state, 1), "ef");
+        assert_eq!(selected(&mut state, 2), "");
+    }
+
+    #[test]
+    fn test_wrap_indicator_only_on_soft_wrapped_rows() {
+        let mut config = Config::default();
+        config.style.wrap_indicator = Some('↵');
+        let mut state = State::new(&Rc::new(config));
+
+        let mut wrapped = row_of("abcd");
+        wrapped.inner[3].flags.insert(Flags::WRAPLINE);
+        let hard_broken = row_of("ef  ");
+
+        let mut stack = state.create_sugar_stack(&wrapped, false);
+        state.apply_wrap_indicator(&mut stack, &wrapped);
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "abcd↵");
+        assert_eq!(stack[4].foreground_color, state.named_colors.dim_foreground);
+
+        let mut stack = state.create_sugar_stack(&hard_broken, false);
+        state.apply_wrap_indicator(&mut stack, &hard_broken);
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "ef  ");
+    }
+
+    #[test]
+    fn test_tab_leader_fills_cells_up_to_the_tab_stop() {
+        let mut config = Config::default();
+        config.style.tab_leader = Some('.');
+        let mut state = State::new(&Rc::new(config));
+        let tab_stops: Vec<bool> = (0..16).map(|column| column % 8 == 0).collect();
+        state.set_tab_stops(tab_stops.clone());
+
+        // "ab\tcd" moves from column 2 to the tab stop at column 8.
+        let mut terminal =
+            crate::crosswords::Crosswords::new(16, 2, crate::event::VoidListener {});
+        let mut parser = crate::performer::handler::ParserProcessor::new();
+        for byte in b"ab\tcd\r\n\tef" {
+            parser.advance(&mut terminal, *byte);
+        }
+        assert_eq!(terminal.tab_stops(), tab_stops);
+        let rows = terminal.visible_rows();
+
+        let mut stack = state.create_sugar_stack(&rows[0], false);
+        state.apply_tab_leader(&mut stack, &rows[0]);
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "ab......cd      ");
+        assert_eq!(stack[2].foreground_color, state.named_colors.dim_foreground);
+        assert_ne!(stack[8].foreground_color, state.named_colors.dim_foreground);
+
+        let mut stack = state.create_sugar_stack(&rows[1], false);
+        state.apply_tab_leader(&mut stack, &rows[1]);
+        let content: String = stack.iter().map(|sugar| sugar.content).collect();
+        assert_eq!(content, "........ef      ");
+
+        // Copying still gives the tab.
+        assert_eq!(
+            terminal.bounds_to_string(
+                pos::Pos::new(pos::Line(0), pos::Column(0)),
+                pos::Pos::new(pos::Line(0), pos::Column(15)),
+            ),
+            "ab\tcd"
+        );
+    }
+
+    #[test]
+    fn test_fill_cursor_line_with_pen_background() {
+        let mut config = Config::default();
+        config.style.fill_cursor_line = true;
+        let mut state = State::new(&Rc::new(config));
+        state.set_pen_background(AnsiColor::Named(NamedColor::Blue));
+
+        let mut row = row_of("$ ls    ");
+        row.inner[2].bg = AnsiColor::Named(NamedColor::Red);
+        state.fill_cursor_line(&mut row);
+
+        let stack = state.create_sugar_stack(&row, false);
+        assert_eq!(stack.len(), 8);
+        for (column, sugar) in stack.iter().enumerate() {
+            let expected = if column == 2 {
+                state.named_colors.red
+            } else {
+                state.named_colors.blue
+            };
+            assert_eq!(sugar.background_color, expected);
+        }
+    }
+
+    #[test]
+    fn test_color_depth_clamps_true_colors() {
+        let mut config = Config::default();
+        config.style.color_depth = ColorDepth::Ansi16;
+        let mut state = State::new(&Rc::new(config));
+
+        let mut row = row_of("rio");
+        for square in row.inner.iter_mut() {
+            square.fg = AnsiColor::Spec(ColorRgb {
+                r: 230,
+                g: 20,
+                b: 40,
+            });
+            square.bg = AnsiColor::Spec(ColorRgb {
+                r: 20,
+                g: 170,
+                b: 235,
+            });
+        }
+
+        let stack = state.create_sugar_stack(&row, false);
+        for sugar in stack.iter() {
+            assert_eq!(sugar.foreground_color, state.named_colors.red);
+            assert_eq!(sugar.background_color, state.named_colors.blue);
+        }
+
+        // Only rendering is clamped, the row keeps its colors.
+        assert_eq!(
+            row.inner[0].fg,
+            AnsiColor::Spec(ColorRgb {
+                r: 230,
+                g: 20,
+                b: 40
+            })
+        );
+
+        state.color_depth = ColorDepth::TrueColor;
+        let stack = state.create_sugar_stack(&row, false);
+        assert_ne!(stack[0].foreground_color, state.named_colors.red);
+    }
+}
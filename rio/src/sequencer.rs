@@ -1,8 +1,8 @@
 use crate::clipboard::ClipboardType;
 use crate::event::{ClickState, EventP, EventProxy, RioEvent, RioEventType};
 use crate::ime::Preedit;
-use crate::scheduler::{Scheduler, TimerId, Topic};
-use crate::screen::{window::create_window_builder, Screen};
+use crate::scheduler::{FrameThrottle, Scheduler, TimerId, Topic};
+use crate::screen::{appearance, window::create_window_builder, Screen};
 use colors::ColorRgb;
 use std::error::Error;
 use std::rc::Rc;
@@ -10,12 +10,58 @@ use std::time::{Duration, Instant};
 use winit::event::{
     ElementState, Event, Ime, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
 };
-use winit::event_loop::{DeviceEventFilter, EventLoop};
+use winit::event_loop::{ControlFlow, DeviceEventFilter, EventLoop};
 use winit::platform::run_return::EventLoopExtRunReturn;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use winit::platform::wayland::EventLoopWindowTargetExtWayland;
 use winit::window::ImePurpose;
 
+/// Wakes up for the next frame the animations need (cursor blink, bell,
+/// flashes), replacing the one scheduled before.
+fn schedule_redraw(scheduler: &mut Scheduler, screen: &Screen) {
+    let timer_id = TimerId::new(Topic::Redraw, 0);
+    scheduler.unschedule(timer_id);
+    if let Some(deadline) = screen.next_redraw_at() {
+        scheduler.schedule(
+            EventP::new(RioEventType::Rio(RioEvent::Render)),
+            deadline.saturating_duration_since(Instant::now()),
+            false,
+            timer_id,
+        );
+    }
+}
+
+/// A terminal update is rendered right away when a frame is due, otherwise
+/// `schedule_frame` gets the delay until the next one, so heavy output is
+/// drawn once per frame. Returns whether it's rendered now.
+fn coalesce_update(
+    frame_throttle: &FrameThrottle,
+    now: Instant,
+    schedule_frame: impl FnOnce(Duration),
+) -> bool {
+    match frame_throttle.next_frame_delay(now) {
+        None => true,
+        Some(delay) => {
+            schedule_frame(delay);
+            false
+        }
+    }
+}
+
+/// Ends the event loop with the error of a frame that couldn't be drawn,
+/// `run` returns it once the loop is over.
+fn exit_on_render_error(
+    result: Result<(), wgpu::SurfaceError>,
+    control_flow: &mut ControlFlow,
+    render_error: &mut Option<wgpu::SurfaceError>,
+) {
+    if let Err(error) = result {
+        log::error!("{error}, rendering cannot continue");
+        *render_error = Some(error);
+        *control_flow = ControlFlow::Exit;
+    }
+}
+
 pub struct Sequencer {
     config: Rc<config::Config>,
 }
@@ -71,15 +117,35 @@ impl Sequencer {
         let mut screen = Screen::new(&winit_window, &self.config, event_proxy).await?;
         let mut is_window_focused = false;
         let mut should_render = false;
-        screen.init(self.config.colors.background.1);
+        // Renders are limited to one per interval, ~60 frames per second by default
+        let min_redraw_interval = Duration::from_millis(self.config.min_redraw_interval);
+        let mut frame_throttle = FrameThrottle::new(min_redraw_interval);
+        screen.init()?;
         event_loop.set_device_event_filter(DeviceEventFilter::Always);
+        let mut render_error = None;
+        let render_error_ref = &mut render_error;
         event_loop.run_return(move |event, _, control_flow| {
             match event {
                 Event::UserEvent(EventP { payload, .. }) => {
                     if let RioEventType::Rio(event) = payload {
                         match event {
                             RioEvent::Wakeup => {
-                                should_render = true;
+                                // Coalesce updates into at most one render per frame
+                                let now = Instant::now();
+                                should_render |=
+                                    coalesce_update(&frame_throttle, now, |delay| {
+                                        let timer_id = TimerId::new(Topic::Frame, 0);
+                                        if !scheduler.scheduled(timer_id) {
+                                            scheduler.schedule(
+                                                EventP::new(RioEventType::Rio(
+                                                    RioEvent::Render,
+                                                )),
+                                                delay,
+                                                false,
+                                                timer_id,
+                                            );
+                                        }
+                                    });
                             }
                             RioEvent::Render => {
                                 if self.config.advanced.disable_render_when_unfocused
@@ -87,7 +153,13 @@ impl Sequencer {
                                 {
                                     return;
                                 }
-                                screen.render();
+                                exit_on_render_error(
+                                    screen.render(),
+                                    control_flow,
+                                    render_error_ref,
+                                );
+                                frame_throttle.frame_rendered(Instant::now());
+                                schedule_redraw(&mut scheduler, &screen);
                             }
                             RioEvent::PrepareRender(millis) => {
                                 let timer_id = TimerId::new(Topic::Frame, 0);
@@ -124,14 +196,12 @@ impl Sequencer {
                                 }
                             }
                             RioEvent::ColorRequest(index, format) => {
-                                // TODO: colors could be coming terminal as well
-                                // if colors has been declaratively changed
-                                // Rio doesn't cover this case yet.
-                                //
-                                // In the future should try first get
-                                // from Crosswords then state colors
-                                // screen.colors()[index] or screen.state.colors[index]
-                                let color = screen.state.colors[index];
+                                // Past the palette are the foreground,
+                                // background and cursor colors.
+                                let color = match u8::try_from(index) {
+                                    Ok(index) => screen.palette_color(index),
+                                    Err(_) => screen.state.colors[index],
+                                };
                                 let rgb = ColorRgb::from_color_arr(color);
                                 screen
                                     .ctx_mut()
@@ -139,6 +209,28 @@ impl Sequencer {
                                     .messenger
                                     .send_bytes(format(rgb).into_bytes());
                             }
+                            RioEvent::ColorChange(index, color) => {
+                                // Only palette entries can be changed yet.
+                                if let Ok(index) = u8::try_from(index) {
+                                    match color {
+                                        Some(color) => screen
+                                            .set_palette_color(index, color.to_arr()),
+                                        None => screen.reset_palette_color(index),
+                                    }
+                                    should_render = true;
+                                }
+                            }
+                            RioEvent::Bell => {
+                                screen.ring_bell();
+                                should_render = true;
+                            }
+                            RioEvent::PtyWrite(text) => {
+                                screen
+                                    .ctx_mut()
+                                    .current_mut()
+                                    .messenger
+                                    .send_bytes(text.into_bytes());
+                            }
                             _ => {}
                         }
                     }
@@ -193,15 +285,15 @@ impl Sequencer {
                                 screen.layout_mut().mouse_mut().click_state =
                                     ClickState::None;
 
-                                // let code = match button {
-                                //     MouseButton::Left => 0,
-                                //     MouseButton::Middle => 1,
-                                //     MouseButton::Right => 2,
-                                //     // Can't properly report more than three buttons..
-                                //     MouseButton::Other(_) => return,
-                                // };
+                                let code = match button {
+                                    MouseButton::Left => 0,
+                                    MouseButton::Middle => 1,
+                                    MouseButton::Right => 2,
+                                    // Can't properly report more than three buttons..
+                                    MouseButton::Other(_) => return,
+                                };
 
-                                // self.mouse_report(code, ElementState::Pressed);
+                                screen.mouse_report(code, ElementState::Pressed);
                             } else {
                                 // Calculate time since the last click to handle double/triple clicks.
                                 let now = Instant::now();
@@ -254,14 +346,14 @@ impl Sequencer {
                                 .shift()
                                 && screen.mouse_mode()
                             {
-                                // let code = match button {
-                                //     MouseButton::Left => 0,
-                                //     MouseButton::Middle => 1,
-                                //     MouseButton::Right => 2,
-                                //     // Can't properly report more than three buttons.
-                                //     MouseButton::Other(_) => return,
-                                // };
-                                // self.mouse_report(code, ElementState::Released);
+                                let code = match button {
+                                    MouseButton::Left => 0,
+                                    MouseButton::Middle => 1,
+                                    MouseButton::Right => 2,
+                                    // Can't properly report more than three buttons.
+                                    MouseButton::Other(_) => return,
+                                };
+                                screen.mouse_report(code, ElementState::Released);
                                 return;
                             }
 
@@ -329,20 +421,9 @@ impl Sequencer {
                     {
                         screen.update_selection(point);
                         should_render = true;
+                    } else if screen.mouse_mode() {
+                        screen.mouse_motion_report(old_point, point);
                     }
-                    // else if square_changed
-                    //     && screen.terminal().mode().intersects(TermMode::MOUSE_MOTION | TermMode::MOUSE_DRAG)
-                    // {
-                    //     // if lmb_pressed {
-                    //         // self.mouse_report(32, ElementState::Pressed);
-                    //     // } else if self.ctx.mouse().middle_button_state == ElementState::Pressed {
-                    //         // self.mouse_report(33, ElementState::Pressed);
-                    //     // } else if self.ctx.mouse().right_button_state == ElementState::Pressed {
-                    //         // self.mouse_report(34, ElementState::Pressed);
-                    //     // } else if self.ctx.terminal().mode().contains(TermMode::MOUSE_MOTION) {
-                    //         // self.mouse_report(35, ElementState::Pressed);
-                    //     // }
-                    // }
                 }
 
                 Event::WindowEvent {
@@ -407,7 +488,14 @@ impl Sequencer {
                 } => match state {
                     ElementState::Pressed => {
                         winit_window.set_cursor_visible(false);
-                        screen.input_keycode(virtual_keycode, scancode);
+                        if screen.reset_cursor_blink() {
+                            should_render = true;
+                        }
+                        exit_on_render_error(
+                            screen.input_keycode(virtual_keycode, scancode),
+                            control_flow,
+                            render_error_ref,
+                        );
                     }
 
                     ElementState::Released => {
@@ -432,7 +520,11 @@ impl Sequencer {
 
                         if screen.ime.preedit() != preedit.as_ref() {
                             screen.ime.set_preedit(preedit);
-                            screen.render();
+                            exit_on_render_error(
+                                screen.render(),
+                                control_flow,
+                                render_error_ref,
+                            );
                         }
                     }
                     Ime::Enabled => {
@@ -448,6 +540,24 @@ impl Sequencer {
                     ..
                 } => {
                     is_window_focused = focused;
+                    screen.on_focus_change(focused);
+                }
+
+                Event::WindowEvent {
+                    event: winit::event::WindowEvent::Occluded(occluded),
+                    ..
+                } => {
+                    // Nothing animates while the window can't be seen.
+                    screen.set_animations_enabled(!occluded);
+                    should_render = !occluded;
+                }
+
+                Event::WindowEvent {
+                    event: winit::event::WindowEvent::ThemeChanged(theme),
+                    ..
+                } => {
+                    screen.set_appearance(appearance(theme));
+                    exit_on_render_error(screen.render(), control_flow, render_error_ref);
                 }
 
                 Event::WindowEvent {
@@ -462,10 +572,6 @@ impl Sequencer {
                     event: winit::event::WindowEvent::Resized(new_size),
                     ..
                 } => {
-                    if new_size.width == 0 || new_size.height == 0 {
-                        return;
-                    }
-
                     screen.resize(new_size);
                     should_render = true;
                 }
@@ -489,12 +595,20 @@ impl Sequencer {
                     // TODO: Now we are forcing an exit operation
                     // but it should be revaluated since CloseRequested in MacOs
                     // not necessarily exit the process
-                    std::process::exit(0);
+                    if render_error_ref.is_none() {
+                        std::process::exit(0);
+                    }
                 }
                 Event::MainEventsCleared { .. } => {
                     if should_render {
-                        screen.render();
+                        exit_on_render_error(
+                            screen.render(),
+                            control_flow,
+                            render_error_ref,
+                        );
+                        frame_throttle.frame_rendered(Instant::now());
                         should_render = false;
+                        schedule_redraw(&mut scheduler, &screen);
                         return;
                     }
 
@@ -502,11 +616,78 @@ impl Sequencer {
                 }
                 Event::RedrawRequested { .. } => {}
                 _ => {
-                    *control_flow = winit::event_loop::ControlFlow::Wait;
+                    // Wake up for pending timers, e.g. a coalesced frame
+                    *control_flow = match scheduler.update() {
+                        Some(deadline) => {
+                            winit::event_loop::ControlFlow::WaitUntil(deadline)
+                        }
+                        None => winit::event_loop::ControlFlow::Wait,
+                    };
                 }
             }
         });
 
-        Ok(())
+        match render_error {
+            Some(error) => Err(Box::new(error)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::headless::Headless;
+
+    #[tokio::test]
+    async fn test_updates_within_a_frame_are_rendered_once() {
+        let config = Rc::new(config::Config::default());
+        let interval = Duration::from_millis(config.min_redraw_interval);
+        let mut headless = headless_or_skip!(Headless::new(&config, 200, 100).await);
+        let mut frame_throttle = FrameThrottle::new(interval);
+        let start = Instant::now();
+
+        // The first update is drawn right away.
+        headless.feed(b"r");
+        assert!(coalesce_update(&frame_throttle, start, |_| unreachable!()));
+        let first = headless.render();
+        frame_throttle.frame_rendered(start);
+
+        // The next ones wait for the frame scheduled by the first of them.
+        let mut scheduled = None;
+        for (index, byte) in b"io gpu".iter().enumerate() {
+            let now = start + Duration::from_millis(index as u64);
+            headless.feed(&[*byte]);
+            let render = coalesce_update(&frame_throttle, now, |delay| {
+                scheduled.get_or_insert(now + delay);
+            });
+            assert!(!render);
+        }
+        assert_eq!(scheduled, Some(start + interval));
+
+        // That frame has all of them.
+        let last = headless.render();
+        assert_ne!(last, first);
+        drop(headless);
+        let mut fed_at_once = Headless::new(&config, 200, 100).await.unwrap();
+        assert_eq!(fed_at_once.feed_and_render(b"rio gpu"), last);
+    }
+
+    #[test]
+    fn test_surface_out_of_memory_ends_the_event_loop() {
+        let mut control_flow = ControlFlow::Wait;
+        let mut render_error = None;
+
+        exit_on_render_error(Ok(()), &mut control_flow, &mut render_error);
+        assert_eq!(control_flow, ControlFlow::Wait);
+        assert!(render_error.is_none());
+
+        exit_on_render_error(
+            Err(wgpu::SurfaceError::OutOfMemory),
+            &mut control_flow,
+            &mut render_error,
+        );
+        assert_eq!(control_flow, ControlFlow::Exit);
+        assert_eq!(render_error, Some(wgpu::SurfaceError::OutOfMemory));
     }
 }
@@ -0,0 +1,41 @@
+//! Times system font lookups. Each one used to build a font source, which
+//! enumerates the installed fonts, now the thread's source is reused.
+//!
+//! cargo run --release --example font_lookup -- "DejaVu Sans Mono"
+
+use font_kit::source::SystemSource;
+use std::time::Instant;
+use sugarloaf::font::{Font, FontSynthesis};
+
+const LOOKUPS: u32 = 20;
+
+fn main() {
+    let font_name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("DejaVu Sans Mono"));
+
+    // The first lookup builds the thread's source.
+    if let Err(error) = Font::load(font_name.clone(), FontSynthesis::default()) {
+        println!("{error}");
+        return;
+    }
+
+    let start = Instant::now();
+    for _ in 0..LOOKUPS {
+        let _ = SystemSource::new();
+    }
+    let source = start.elapsed() / LOOKUPS;
+
+    let start = Instant::now();
+    for _ in 0..LOOKUPS {
+        let _ = Font::load(font_name.clone(), FontSynthesis::default());
+    }
+    let lookup = start.elapsed() / LOOKUPS;
+
+    println!("building a font source: {source:?}");
+    println!("loading {font_name:?} with the thread's source: {lookup:?}");
+    println!(
+        "each lookup saves {:.0}%",
+        source.as_secs_f64() / (source + lookup).as_secs_f64() * 100.
+    );
+}
@@ -111,7 +111,8 @@ async fn main() {
                                 size: [200.0, 200.0],
                             },
                         ])
-                        .render();
+                        .render()
+                        .expect("Render frame");
                 }
                 _ => (),
             },
@@ -144,7 +145,8 @@ async fn main() {
                             size: [200.0, 200.0],
                         },
                     ])
-                    .render();
+                    .render()
+                    .expect("Render frame");
 
                 // write_png(
                 //     "/tmp/rio-rect.png",
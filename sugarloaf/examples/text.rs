@@ -246,7 +246,9 @@ async fn main() {
 
         match event {
             Event::Resumed => {
-                sugarloaf.init(wgpu::Color::RED, styles);
+                sugarloaf
+                    .init(wgpu::Color::RED, styles)
+                    .expect("Init frame");
                 window.request_redraw();
             }
             Event::WindowEvent { event, .. } => match event {
@@ -286,7 +288,7 @@ async fn main() {
                 sugarloaf.stack(loaf, styles);
                 sugarloaf.stack(rio, styles);
                 sugarloaf.stack(special, styles);
-                sugarloaf.render();
+                sugarloaf.render().expect("Render frame");
             }
             _ => {
                 *control_flow = winit::event_loop::ControlFlow::Wait;
@@ -1,2 +1,17 @@
+pub mod blit;
 pub mod rect;
 pub mod text;
+
+/// A device without any window for tests, `None` when the machine has no
+/// adapter.
+#[cfg(test)]
+pub(crate) async fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await?;
+    adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .ok()
+}
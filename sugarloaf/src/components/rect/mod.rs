@@ -4,7 +4,9 @@ use bytemuck::{Pod, Zeroable};
 use std::{borrow::Cow, mem};
 use wgpu::util::DeviceExt;
 
-const MAX_INSTANCES: usize = 10_000;
+/// Instances the buffer is created for, it grows to fit every rect of a
+/// frame so they are all drawn at once.
+const INITIAL_INSTANCES: usize = 10_000;
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
@@ -105,11 +107,12 @@ pub struct RectBrush {
     pipeline: wgpu::RenderPipeline,
     current_transform: [f32; 16],
     scale: f32,
+    supported_instances: usize,
+    draw_calls: u32,
 }
 
-impl Renderable for RectBrush {
-    fn init(context: &Context) -> Self {
-        let device = &context.device;
+impl RectBrush {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, scale: f32) -> Self {
         let vertex_data = create_vertices_rect();
 
         let transform = device.create_buffer(&wgpu::BufferDescriptor {
@@ -207,7 +210,7 @@ impl Renderable for RectBrush {
                 module: &shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: context.format,
+                    format,
                     blend: BLEND,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -225,14 +228,14 @@ impl Renderable for RectBrush {
 
         let instances = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Instances Buffer"),
-            size: mem::size_of::<Rect>() as u64 * MAX_INSTANCES as u64,
+            size: mem::size_of::<Rect>() as u64 * INITIAL_INSTANCES as u64,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         // Done
         RectBrush {
-            scale: context.scale,
+            scale,
             vertex_buf,
             index_buf,
             index_count: QUAD_INDICES.len(),
@@ -241,35 +244,28 @@ impl Renderable for RectBrush {
             pipeline,
             current_transform: [0.0; 16],
             instances,
+            supported_instances: INITIAL_INSTANCES,
+            draw_calls: 0,
         }
     }
 
-    fn update(&mut self, _event: winit::event::WindowEvent) {
-        //empty
+    /// Draw calls issued by the last `render`, backgrounds, selections and
+    /// decorations all share a single one.
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls
     }
 
-    fn resize(
-        &mut self,
-        _config: &wgpu::SurfaceConfiguration,
-        _device: &wgpu::Device,
-        _queue: &wgpu::Queue,
-    ) {
-        // queue.write_buffer(&self.transform, 0, bytemuck::cast_slice(&IDENTITY_MATRIX));
-    }
-
-    fn render(
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
         &mut self,
+        device: &wgpu::Device,
+        staging_belt: &mut wgpu::util::StagingBelt,
+        scale: f32,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
         transform: [f32; 16],
         instances: &[Rect],
-        ctx: &mut Context,
     ) {
-        // device.push_error_scope(wgpu::ErrorFilter::Validation);
-        let scale = ctx.scale;
-        let device = &ctx.device;
-        let staging_belt = &mut ctx.staging_belt;
-
         if transform != self.current_transform || scale != self.scale {
             let uniforms = Uniforms::new(transform, scale);
 
@@ -287,57 +283,89 @@ impl Renderable for RectBrush {
             self.scale = scale;
         }
 
-        let mut i = 0;
-        let total = instances.len();
+        self.draw_calls = 0;
+        if instances.is_empty() {
+            return;
+        }
 
-        while i < total {
-            let end = (i + MAX_INSTANCES).min(total);
-            let amount = end - i;
+        if instances.len() > self.supported_instances {
+            self.supported_instances = instances.len().next_power_of_two();
+            self.instances = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Instances Buffer"),
+                size: mem::size_of::<Rect>() as u64 * self.supported_instances as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
 
-            let instance_bytes = bytemuck::cast_slice(&instances[i..end]);
+        let instance_bytes = bytemuck::cast_slice(instances);
+        let mut instance_buffer = staging_belt.write_buffer(
+            encoder,
+            &self.instances,
+            0,
+            wgpu::BufferSize::new(instance_bytes.len() as u64).unwrap(),
+            device,
+        );
+        instance_buffer.copy_from_slice(instance_bytes);
+        drop(instance_buffer);
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        rpass.set_vertex_buffer(1, self.instances.slice(..));
+        rpass.draw_indexed(0..self.index_count as u32, 0, 0..instances.len() as u32);
+        self.draw_calls = 1;
+    }
+}
 
-            // queue.write_buffer(&self.instances, 0, instance_bytes);
-            let mut instance_buffer = staging_belt.write_buffer(
-                encoder,
-                &self.instances,
-                0,
-                wgpu::BufferSize::new(instance_bytes.len() as u64).unwrap(),
-                device,
-            );
+impl Renderable for RectBrush {
+    fn init(context: &Context) -> Self {
+        RectBrush::new(&context.device, context.format, context.scale)
+    }
 
-            instance_buffer.copy_from_slice(instance_bytes);
-
-            {
-                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: None,
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Load,
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
-                // rpass.push_debug_group("Prepare data for draw.");
-                rpass.set_pipeline(&self.pipeline);
-                rpass.set_bind_group(0, &self.bind_group, &[]);
-                rpass.set_index_buffer(
-                    self.index_buf.slice(..),
-                    wgpu::IndexFormat::Uint16,
-                );
-                rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-                rpass.set_vertex_buffer(1, self.instances.slice(..));
-                // rpass.pop_debug_group();
-                // rpass.insert_debug_marker("Draw!");
-                rpass.draw_indexed(0..self.index_count as u32, 0, 0..amount as u32);
-            }
+    fn update(&mut self, _event: winit::event::WindowEvent) {
+        //empty
+    }
 
-            i += MAX_INSTANCES;
-        }
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        // queue.write_buffer(&self.transform, 0, bytemuck::cast_slice(&IDENTITY_MATRIX));
+    }
 
-        // queue.submit(Some(encoder.finish()));
+    fn render(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        transform: [f32; 16],
+        instances: &[Rect],
+        ctx: &mut Context,
+    ) {
+        self.draw(
+            &ctx.device,
+            &mut ctx.staging_belt,
+            ctx.scale,
+            encoder,
+            view,
+            transform,
+            instances,
+        );
     }
 }
 
@@ -374,3 +402,84 @@ impl Renderable for RectBrush {
 //         max_outliers: 1250, // Bounded by swiftshader
 //     });
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::headless_device;
+
+    #[tokio::test]
+    async fn styled_screen_is_a_single_draw_call() {
+        let (device, queue) = headless_or_skip!(headless_device().await);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let mut brush = RectBrush::new(&device, format, 1.0);
+
+        // Every cell of a 400x100 screen has a background, a selection and
+        // two decorations, way past the initial buffer.
+        let (columns, rows) = (400, 100);
+        let mut rects = vec![];
+        for row in 0..rows {
+            for column in 0..columns {
+                let position = [column as f32 * 8., row as f32 * 16.];
+                for (color, size) in [
+                    ([0.2, 0.2, 0.2, 1.0], [8., 16.]),
+                    ([0.0, 0.0, 1.0, 0.5], [8., 16.]),
+                    ([1.0, 1.0, 1.0, 1.0], [8., 1.]),
+                    ([1.0, 0.0, 0.0, 1.0], [8., 1.]),
+                ] {
+                    rects.push(Rect {
+                        position,
+                        color,
+                        size,
+                    });
+                }
+            }
+        }
+        assert!(rects.len() > INITIAL_INSTANCES);
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 3200,
+                height: 1600,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut staging_belt = wgpu::util::StagingBelt::new(2 * 1024);
+        let mut encoder = device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        let transform = crate::sugarloaf::orthographic_projection(3200, 1600);
+        brush.draw(
+            &device,
+            &mut staging_belt,
+            1.0,
+            &mut encoder,
+            &view,
+            transform,
+            &rects,
+        );
+        assert_eq!(brush.draw_calls(), 1);
+        brush.draw(
+            &device,
+            &mut staging_belt,
+            1.0,
+            &mut encoder,
+            &view,
+            transform,
+            &[],
+        );
+        assert_eq!(brush.draw_calls(), 0);
+
+        staging_belt.finish();
+        queue.submit(Some(encoder.finish()));
+        device.poll(wgpu::Maintain::Wait);
+    }
+}
@@ -3,6 +3,7 @@
 // #[deny(unused_results)]
 mod builder;
 mod pipeline;
+mod renderer;
 
 /// A region of the screen.
 pub struct Region {
@@ -21,6 +22,7 @@ pub use glyph_brush::{
     Layout, LineBreak, LineBreaker, OwnedSection, OwnedText, Section, SectionGeometry,
     SectionGlyph, SectionGlyphIter, SectionText, Text, VerticalAlign,
 };
+pub use renderer::TextRenderer;
 
 use ab_glyph::{Font, Rect};
 use core::hash::BuildHasher;
@@ -220,6 +222,7 @@ impl<F: Font + Sync, H: BuildHasher> GlyphBrush<(), F, H> {
     /// See [`queue`](struct.GlyphBrush.html#method.queue).
     ///
     /// It __does not__ submit the encoder command buffer to the device queue.
+    /// Returns the number of draw calls, none when there are no glyphs.
     ///
     /// Trims the cache, see [caching behaviour](#caching-behaviour).
     ///
@@ -234,7 +237,7 @@ impl<F: Font + Sync, H: BuildHasher> GlyphBrush<(), F, H> {
         encoder: &mut wgpu::CommandEncoder,
         target: &wgpu::TextureView,
         w_h: (u32, u32),
-    ) -> Result<(), String> {
+    ) -> Result<u32, String> {
         self.draw_queued_with_transform(
             device,
             staging_belt,
@@ -263,12 +266,11 @@ impl<F: Font + Sync, H: BuildHasher> GlyphBrush<(), F, H> {
         encoder: &mut wgpu::CommandEncoder,
         target: &wgpu::TextureView,
         transform: [f32; 16],
-    ) -> Result<(), String> {
+    ) -> Result<u32, String> {
         self.process_queued(device, staging_belt, encoder);
-        self.pipeline
-            .draw(device, staging_belt, encoder, target, transform, None);
-
-        Ok(())
+        Ok(self
+            .pipeline
+            .draw(device, staging_belt, encoder, target, transform, None))
     }
 
     /// Draws all queued sections onto a render target, applying a position
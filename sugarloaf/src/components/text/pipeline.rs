@@ -65,14 +65,14 @@ impl Pipeline<()> {
         target: &wgpu::TextureView,
         transform: [f32; 16],
         region: Option<Region>,
-    ) {
+    ) -> u32 {
         draw(
             self,
             (device, staging_belt, encoder, target),
             None,
             transform,
             region,
-        );
+        )
     }
 }
 
@@ -109,7 +109,7 @@ impl Pipeline<wgpu::DepthStencilState> {
         depth_stencil_attachment: wgpu::RenderPassDepthStencilAttachment,
         transform: [f32; 16],
         region: Option<Region>,
-    ) {
+    ) -> u32 {
         let (device, staging_belt, encoder, target) = config;
         draw(
             self,
@@ -117,7 +117,7 @@ impl Pipeline<wgpu::DepthStencilState> {
             Some(depth_stencil_attachment),
             transform,
             region,
-        );
+        )
     }
 }
 
@@ -341,7 +341,11 @@ fn draw<D>(
     depth_stencil_attachment: Option<wgpu::RenderPassDepthStencilAttachment>,
     transform: [f32; 16],
     region: Option<Region>,
-) {
+) -> u32 {
+    if pipeline.current_instances == 0 {
+        return 0;
+    }
+
     let (device, staging_belt, encoder, target) = config;
     if transform != pipeline.current_transform {
         let mut transform_view = staging_belt.write_buffer(
@@ -379,6 +383,7 @@ fn draw<D>(
     }
 
     render_pass.draw(0..4, 0..pipeline.current_instances as u32);
+    1
 }
 
 fn create_uniforms(
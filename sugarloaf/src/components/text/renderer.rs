@@ -0,0 +1,190 @@
+use super::{GlyphBrush, GlyphCruncher, OwnedSection};
+use glyph_brush::ab_glyph::{FontArc, Rect};
+
+/// Text rendering backend used by Sugarloaf.
+///
+/// Sugarloaf only queues single line sections and draws them once per frame,
+/// so a backend needs to position, measure and draw those sections. The
+/// default implementation is the wgpu based `GlyphBrush`.
+pub trait TextRenderer {
+    /// Available fonts, the `FontId` of a section is the index on this slice.
+    fn fonts(&self) -> &[FontArc];
+
+    /// Queues a section to be drawn by the next `draw_queued`.
+    fn queue(&mut self, section: &OwnedSection);
+
+    /// Pixel bounds of a section once laid out.
+    fn glyph_bounds(&mut self, section: &OwnedSection) -> Option<Rect>;
+
+    /// Draws all queued sections onto `target`, returns the number of draw
+    /// calls.
+    fn draw_queued(
+        &mut self,
+        device: &wgpu::Device,
+        staging_belt: &mut wgpu::util::StagingBelt,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        w_h: (u32, u32),
+    ) -> Result<u32, String>;
+
+    /// Width and height of a section, `(0., 0.)` when it has no glyphs.
+    fn font_bound(&mut self, section: &OwnedSection) -> (f32, f32) {
+        match self.glyph_bounds(section) {
+            Some(rect) => (rect.max.x - rect.min.x, rect.max.y - rect.min.y),
+            None => (0., 0.),
+        }
+    }
+}
+
+impl TextRenderer for GlyphBrush<()> {
+    #[inline]
+    fn fonts(&self) -> &[FontArc] {
+        GlyphBrush::fonts(self)
+    }
+
+    #[inline]
+    fn queue(&mut self, section: &OwnedSection) {
+        GlyphBrush::queue(self, section)
+    }
+
+    #[inline]
+    fn glyph_bounds(&mut self, section: &OwnedSection) -> Option<Rect> {
+        GlyphCruncher::glyph_bounds(self, section)
+    }
+
+    #[inline]
+    fn draw_queued(
+        &mut self,
+        device: &wgpu::Device,
+        staging_belt: &mut wgpu::util::StagingBelt,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        w_h: (u32, u32),
+    ) -> Result<u32, String> {
+        GlyphBrush::draw_queued(self, device, staging_belt, encoder, target, w_h)
+    }
+}
+
+// The tests lay out text with the bundled Cascadia Mono.
+#[cfg(all(test, feature = "bundled-fonts"))]
+mod tests {
+    use super::*;
+    use crate::components::headless_device;
+    use crate::components::text::GlyphBrushBuilder;
+    use crate::font::FONT_CASCADIAMONO_REGULAR;
+    use glyph_brush::{FontId, OwnedText};
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+    const SIZE: (u32, u32) = (64, 64);
+
+    fn brush(device: &wgpu::Device) -> GlyphBrush<()> {
+        let font = FontArc::try_from_slice(FONT_CASCADIAMONO_REGULAR).unwrap();
+        GlyphBrushBuilder::using_font(font).build(device, FORMAT)
+    }
+
+    fn section(content: &str) -> OwnedSection {
+        OwnedSection {
+            screen_position: (0., 32.),
+            bounds: (64., 64.),
+            text: vec![OwnedText::new(content)
+                .with_font_id(FontId(0))
+                .with_scale(32.)
+                .with_color([1., 1., 1., 1.])],
+            layout: glyph_brush::Layout::default_single_line()
+                .v_align(glyph_brush::VerticalAlign::Bottom),
+        }
+    }
+
+    /// Pixels of a `SIZE` frame `draw` draws on, with the number of draw
+    /// calls it returns.
+    fn draw_frame(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        draw: impl FnOnce(
+            &mut wgpu::util::StagingBelt,
+            &mut wgpu::CommandEncoder,
+            &wgpu::TextureView,
+        ) -> Result<u32, String>,
+    ) -> (Vec<u8>, u32) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE.0,
+                height: SIZE.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut staging_belt = wgpu::util::StagingBelt::new(1024);
+        let mut encoder = device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let draw_calls = draw(&mut staging_belt, &mut encoder, &view).unwrap();
+
+        // 64 pixels are 256 bytes, rows are already aligned for the copy.
+        let bytes_per_row = SIZE.0 * 4;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (bytes_per_row * SIZE.1) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        staging_belt.finish();
+        queue.submit(Some(encoder.finish()));
+        staging_belt.recall();
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let pixels = slice.get_mapped_range().to_vec();
+        (pixels, draw_calls)
+    }
+
+    #[tokio::test]
+    async fn test_default_renderer_draws_like_glyph_brush() {
+        let (device, queue) = headless_or_skip!(headless_device().await);
+        let mut expected_brush = brush(&device);
+        let mut renderer: Box<dyn TextRenderer> = Box::new(brush(&device));
+
+        for content in [" ", "a", "rio"] {
+            let section = section(content);
+            let rect =
+                GlyphCruncher::glyph_bounds(&mut expected_brush, &section).unwrap();
+            assert_eq!(
+                renderer.font_bound(&section),
+                (rect.max.x - rect.min.x, rect.max.y - rect.min.y)
+            );
+        }
+        assert_eq!(renderer.font_bound(&section("")), (0., 0.));
+        assert_eq!(renderer.fonts().len(), 1);
+
+        expected_brush.queue(&section("rio"));
+        let expected = draw_frame(&device, &queue, |staging_belt, encoder, view| {
+            expected_brush.draw_queued(&device, staging_belt, encoder, view, SIZE)
+        });
+        renderer.queue(&section("rio"));
+        let drawn = draw_frame(&device, &queue, |staging_belt, encoder, view| {
+            renderer.draw_queued(&device, staging_belt, encoder, view, SIZE)
+        });
+
+        assert!(expected.0.iter().any(|&value| value != 0));
+        assert_eq!(drawn, expected);
+    }
+}
@@ -1,12 +1,134 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 #[derive(Debug)]
 pub struct Context {
     pub device: wgpu::Device,
-    pub surface: wgpu::Surface,
+    /// `None` for a headless context, frames are only drawn into textures.
+    pub surface: Option<wgpu::Surface>,
     pub queue: wgpu::Queue,
     pub staging_belt: wgpu::util::StagingBelt,
     pub format: wgpu::TextureFormat,
     pub size: winit::dpi::PhysicalSize<u32>,
     pub scale: f32,
+    instance: wgpu::Instance,
+    power_preference: wgpu::PowerPreference,
+    device_lost: Arc<AtomicBool>,
+    present_modes: Vec<wgpu::PresentMode>,
+    present_mode: wgpu::PresentMode,
+    minimized: bool,
+}
+
+/// Adapter resources able to draw on a surface.
+struct Device {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    format: wgpu::TextureFormat,
+    present_modes: Vec<wgpu::PresentMode>,
+}
+
+/// The error comes from the device being lost, e.g. after a driver reset
+/// or resuming from sleep. wgpu reports it like a validation error, caused
+/// by the device error of wgpu-core.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_device_lost_error(error: &wgpu::Error) -> bool {
+    let error: &(dyn std::error::Error + 'static) = error;
+    std::iter::successors(Some(error), |error| error.source()).any(|error| {
+        matches!(
+            error.downcast_ref(),
+            Some(wgpu_core::device::DeviceError::Lost)
+        )
+    })
+}
+
+/// The browser reports a lost device apart from errors.
+#[cfg(target_arch = "wasm32")]
+fn is_device_lost_error(_error: &wgpu::Error) -> bool {
+    false
+}
+
+/// Handles the uncaptured errors of `device`, the returned flag is set once
+/// it's lost. Other errors are still fatal, as they are by default.
+fn watch_device_lost(device: &wgpu::Device) -> Arc<AtomicBool> {
+    let device_lost = Arc::new(AtomicBool::new(false));
+    let flag = device_lost.clone();
+    device.on_uncaptured_error(Box::new(move |error| {
+        if !is_device_lost_error(&error) {
+            panic!("wgpu error: {error}\n");
+        }
+        log::error!("{error}");
+        flag.store(true, Ordering::Relaxed);
+    }));
+    device_lost
+}
+
+/// Present mode used for `requested`, modes the surface doesn't support
+/// fall back to `AutoVsync`. The automatic modes always work, wgpu picks a
+/// supported mode for them.
+fn choose_present_mode(
+    supported: &[wgpu::PresentMode],
+    requested: wgpu::PresentMode,
+) -> wgpu::PresentMode {
+    match requested {
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => requested,
+        mode if supported.contains(&mode) => mode,
+        mode => {
+            log::warn!("present mode {mode:?} is not supported, using vsync");
+            wgpu::PresentMode::AutoVsync
+        }
+    }
+}
+
+/// Format frames are drawn in, out of the ones the surface supports (the
+/// preferred one first). Colors are already sRGB encoded when drawn, so an
+/// 8 bit format without sRGB conversion keeps them as configured, an sRGB
+/// one would brighten them. Other formats are only used when there is no
+/// 8 bit one.
+fn choose_format(formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    [Bgra8Unorm, Rgba8Unorm, Bgra8UnormSrgb, Rgba8UnormSrgb]
+        .into_iter()
+        .find(|format| formats.contains(format))
+        .or_else(|| formats.first().copied())
+}
+
+/// Adapter, device and queue able to draw on `surface`, with the format
+/// frames are drawn in and the present modes of the surface. Without a
+/// surface frames are drawn in RGBA and never presented.
+async fn request_device(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    power_preference: wgpu::PowerPreference,
+) -> Result<Device, String> {
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or_else(|| String::from("no graphics adapter can draw on the window"))?;
+
+    let (format, present_modes) = match surface {
+        Some(surface) => {
+            let caps = surface.get_capabilities(&adapter);
+            let format = choose_format(&caps.formats)
+                .ok_or_else(|| String::from("no supported formats for surface"))?;
+            (format, caps.present_modes)
+        }
+        None => (wgpu::TextureFormat::Rgba8Unorm, vec![]),
+    };
+
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(Device {
+        device,
+        queue,
+        format,
+        present_modes,
+    })
 }
 
 impl Context {
@@ -94,26 +216,15 @@ impl Context {
         }
         .unwrap();
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
+        let Device {
+            device,
+            queue,
+            format,
+            present_modes,
+        } = request_device(&instance, Some(&surface), power_preference)
             .await
-            .expect("Request adapter");
-
-        let caps = surface.get_capabilities(&adapter);
-        let formats = caps.formats;
-        let format = *formats.last().expect("No supported formats for surface");
-
-        let (device, queue) = (async {
-            adapter
-                .request_device(&wgpu::DeviceDescriptor::default(), None)
-                .await
-                .expect("Request device")
-        })
-        .await;
+            .expect("Request device");
+        let device_lost = watch_device_lost(&device);
 
         let staging_belt = wgpu::util::StagingBelt::new(2 * 1024);
 
@@ -133,18 +244,116 @@ impl Context {
         Context {
             device,
             queue,
-            surface,
+            surface: Some(surface),
             staging_belt,
             format,
             size,
             scale: scale as f32,
+            instance,
+            power_preference,
+            device_lost,
+            present_modes,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            minimized: false,
         }
     }
 
+    /// A context without a window, frames of `size` physical pixels are
+    /// only drawn into textures (e.g. to read them back in tests).
+    pub async fn headless(
+        size: winit::dpi::PhysicalSize<u32>,
+        scale: f32,
+        power_preference: wgpu::PowerPreference,
+    ) -> Result<Context, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let Device {
+            device,
+            queue,
+            format,
+            present_modes,
+        } = request_device(&instance, None, power_preference).await?;
+        let device_lost = watch_device_lost(&device);
+
+        Ok(Context {
+            device,
+            queue,
+            surface: None,
+            staging_belt: wgpu::util::StagingBelt::new(2 * 1024),
+            format,
+            size,
+            scale,
+            instance,
+            power_preference,
+            device_lost,
+            present_modes,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            minimized: size.width == 0 || size.height == 0,
+        })
+    }
+
+    /// The window has no area (e.g. it's minimized), there is nothing to
+    /// draw on until it's resized again.
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Present modes the surface supports on the current adapter.
+    pub fn supported_present_modes(&self) -> Vec<wgpu::PresentMode> {
+        self.present_modes.clone()
+    }
+
+    /// Presents frames with `mode` from now on, an unsupported mode falls
+    /// back to `AutoVsync`. Returns the mode in use.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        self.present_mode = choose_present_mode(&self.present_modes, mode);
+        self.resize(self.size.width, self.size.height);
+        self.present_mode
+    }
+
+    /// The device was lost, nothing can be drawn until it's recreated.
+    #[inline]
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Replaces a lost device (and its queue) by a new one and configures
+    /// the surface for it. Everything created with the old device has to be
+    /// created again.
+    pub async fn recreate_device(&mut self) -> Result<(), String> {
+        let Device {
+            device,
+            queue,
+            format,
+            present_modes,
+        } = request_device(&self.instance, self.surface.as_ref(), self.power_preference)
+            .await?;
+        self.device_lost = watch_device_lost(&device);
+        self.device = device;
+        self.queue = queue;
+        self.format = format;
+        // The new adapter may not support the mode in use.
+        self.present_mode = choose_present_mode(&present_modes, self.present_mode);
+        self.present_modes = present_modes;
+        self.staging_belt = wgpu::util::StagingBelt::new(2 * 1024);
+        self.resize(self.size.width, self.size.height);
+        Ok(())
+    }
+
+    /// Configures the surface for a new size. A zero size can't be
+    /// configured, the last one is kept until the window has an area again.
     pub fn resize(&mut self, width: u32, height: u32) {
+        self.minimized = width == 0 || height == 0;
+        if self.minimized {
+            return;
+        }
+
         self.size.width = width;
         self.size.height = height;
-        self.surface.configure(
+        let Some(surface) = &self.surface else {
+            return;
+        };
+        surface.configure(
             &self.device,
             &wgpu::SurfaceConfiguration {
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -153,8 +362,100 @@ impl Context {
                 height,
                 view_formats: vec![],
                 alpha_mode: wgpu::CompositeAlphaMode::Auto,
-                present_mode: wgpu::PresentMode::AutoVsync,
+                present_mode: self.present_mode,
             },
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Cause(&'static str);
+
+    impl std::fmt::Display for Cause {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl std::error::Error for Cause {}
+
+    #[test]
+    fn device_lost_is_told_apart_from_other_errors() {
+        use wgpu_core::device::DeviceError;
+
+        // How wgpu wraps the errors of wgpu-core.
+        let error =
+            |cause: Box<dyn std::error::Error + Send + Sync>| wgpu::Error::Validation {
+                source: Box::new(wgpu_core::error::ContextError {
+                    string: "Queue::submit",
+                    cause,
+                    label_key: "label",
+                    label: String::new(),
+                }),
+                description: String::new(),
+            };
+        assert!(is_device_lost_error(&error(Box::new(DeviceError::Lost))));
+        assert!(!is_device_lost_error(&error(Box::new(
+            DeviceError::Invalid
+        ))));
+        // Only the error, not its message, tells it's lost.
+        assert!(!is_device_lost_error(&error(Box::new(Cause(
+            "Parent device is lost"
+        )))));
+        assert!(!is_device_lost_error(&wgpu::Error::OutOfMemory {
+            source: Box::new(Cause("Not enough memory left")),
+        }));
+    }
+
+    #[test]
+    fn surface_format_keeps_colors_as_configured() {
+        use wgpu::TextureFormat::*;
+        assert_eq!(
+            choose_format(&[Bgra8UnormSrgb, Bgra8Unorm, Rgba16Float]),
+            Some(Bgra8Unorm)
+        );
+        assert_eq!(
+            choose_format(&[Rgba16Float, Rgba8UnormSrgb]),
+            Some(Rgba8UnormSrgb)
+        );
+        assert_eq!(choose_format(&[Rgb10a2Unorm]), Some(Rgb10a2Unorm));
+        assert_eq!(choose_format(&[]), None);
+    }
+
+    #[test]
+    fn unsupported_present_mode_falls_back_to_vsync() {
+        use wgpu::PresentMode::*;
+        let supported = [Fifo, Mailbox];
+        assert_eq!(choose_present_mode(&supported, Mailbox), Mailbox);
+        assert_eq!(choose_present_mode(&supported, Immediate), AutoVsync);
+        assert_eq!(choose_present_mode(&supported, AutoNoVsync), AutoNoVsync);
+        assert_eq!(choose_present_mode(&[], FifoRelaxed), AutoVsync);
+    }
+
+    #[tokio::test]
+    async fn headless_context_presents_nothing() {
+        let size = winit::dpi::PhysicalSize::new(8, 8);
+        let mut ctx = headless_or_skip!(Context::headless(
+            size,
+            1.0,
+            wgpu::PowerPreference::default()
+        )
+        .await
+        .ok());
+        // Without a surface no mode is supported, the automatic ones work
+        // anywhere.
+        assert!(ctx.supported_present_modes().is_empty());
+        assert_eq!(
+            ctx.set_present_mode(wgpu::PresentMode::Mailbox),
+            wgpu::PresentMode::AutoVsync
+        );
+        assert_eq!(
+            ctx.set_present_mode(wgpu::PresentMode::AutoNoVsync),
+            wgpu::PresentMode::AutoNoVsync
+        );
+    }
+}
@@ -18,6 +18,12 @@ pub struct SugarStyle {
 pub struct SugarDecoration {
     pub position: (f32, f32),
     pub size: (f32, f32),
+    /// Width in pixels instead of `size.0`, scaled with the scale factor so
+    /// it looks the same on every display (e.g. the bar cursor).
+    pub pixel_width: Option<f32>,
+    /// Height in pixels instead of `size.1`, the bottom edge stays where
+    /// `size.1` puts it (e.g. the underline cursor).
+    pub pixel_height: Option<f32>,
     pub color: [f32; 4],
 }
 
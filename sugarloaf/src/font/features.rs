@@ -0,0 +1,252 @@
+use glyph_brush::ab_glyph::{
+    CodepointIdIter, Font, FontArc, GlyphId, GlyphImage, Outline,
+};
+use owned_ttf_parser::gsub::{SingleSubstitution, SubstitutionSubtable};
+use owned_ttf_parser::opentype_layout::Coverage;
+use std::collections::HashMap;
+
+/// Glyphs each OpenType feature of a face replaces, from the single and
+/// alternate substitutions of its GSUB table. Substitutions that depend on
+/// the next or previous glyphs (ligatures, contextual alternates) need
+/// shaping and are left out.
+#[derive(Debug, Default)]
+pub struct FeatureTable {
+    features: HashMap<[u8; 4], HashMap<u16, Vec<u16>>>,
+}
+
+/// Glyphs covered by `coverage`, with their coverage index. Only the listed
+/// glyphs and ranges are walked, not every glyph of the face.
+fn covered(coverage: Coverage) -> Vec<(u16, u16)> {
+    match coverage {
+        Coverage::Format1 { glyphs } => glyphs
+            .into_iter()
+            .zip(0..)
+            .map(|(glyph, index)| (glyph.0, index))
+            .collect(),
+        Coverage::Format2 { records } => records
+            .into_iter()
+            .flat_map(|record| {
+                (record.start.0..=record.end.0)
+                    .map(move |glyph| (glyph, record.value + (glyph - record.start.0)))
+            })
+            .collect(),
+    }
+}
+
+impl FeatureTable {
+    /// Features of face `index` of the font `data`, empty when it has none
+    /// or they can't be read.
+    pub fn parse(data: &[u8], index: u32) -> FeatureTable {
+        let mut table = FeatureTable::default();
+        let Ok(face) = owned_ttf_parser::Face::from_slice(data, index) else {
+            return table;
+        };
+        let Some(gsub) = face.tables().gsub else {
+            return table;
+        };
+
+        // Features are listed once per script and language.
+        for feature in gsub.features {
+            let substitutions = table.features.entry(feature.tag.to_bytes()).or_default();
+            for lookup_index in feature.lookup_indices {
+                let Some(lookup) = gsub.lookups.get(lookup_index) else {
+                    continue;
+                };
+                for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+                    match subtable {
+                        SubstitutionSubtable::Single(single) => {
+                            for (glyph, index) in covered(single.coverage()) {
+                                let substitute = match single {
+                                    SingleSubstitution::Format1 { delta, .. } => {
+                                        Some(glyph.wrapping_add(delta as u16))
+                                    }
+                                    SingleSubstitution::Format2 {
+                                        substitutes, ..
+                                    } => substitutes.get(index).map(|id| id.0),
+                                };
+                                if let Some(substitute) = substitute {
+                                    substitutions
+                                        .entry(glyph)
+                                        .or_insert(vec![substitute]);
+                                }
+                            }
+                        }
+                        SubstitutionSubtable::Alternate(alternate) => {
+                            for (glyph, index) in covered(alternate.coverage) {
+                                if let Some(set) = alternate.alternate_sets.get(index) {
+                                    substitutions.entry(glyph).or_insert(
+                                        set.alternates
+                                            .into_iter()
+                                            .map(|id| id.0)
+                                            .collect(),
+                                    );
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        table
+            .features
+            .retain(|_, substitutions| !substitutions.is_empty());
+        table
+    }
+
+    /// Glyph replacements of `features`, tags with a value. 0 turns the
+    /// feature off and other values pick an alternate, 1 is the first one.
+    /// Features later in the list win over earlier ones.
+    pub fn substitutions(&self, features: &[(&str, u32)]) -> HashMap<GlyphId, GlyphId> {
+        let mut substitutions = HashMap::new();
+        for (tag, value) in features {
+            let Ok(tag) = <[u8; 4]>::try_from(tag.as_bytes()) else {
+                continue;
+            };
+            let Some(feature) = self.features.get(&tag).filter(|_| *value > 0) else {
+                continue;
+            };
+            for (glyph, alternates) in feature {
+                if let Some(substitute) = alternates.get(*value as usize - 1) {
+                    substitutions.insert(GlyphId(*glyph), GlyphId(*substitute));
+                }
+            }
+        }
+        substitutions
+    }
+}
+
+/// Face with the glyphs of some characters replaced by the ones of enabled
+/// OpenType features, e.g. a slashed zero.
+pub struct FeatureFont {
+    font: FontArc,
+    substitutions: HashMap<GlyphId, GlyphId>,
+}
+
+impl FeatureFont {
+    pub fn new(font: FontArc, substitutions: HashMap<GlyphId, GlyphId>) -> FeatureFont {
+        FeatureFont {
+            font,
+            substitutions,
+        }
+    }
+}
+
+impl Font for FeatureFont {
+    fn units_per_em(&self) -> Option<f32> {
+        self.font.units_per_em()
+    }
+
+    fn ascent_unscaled(&self) -> f32 {
+        self.font.ascent_unscaled()
+    }
+
+    fn descent_unscaled(&self) -> f32 {
+        self.font.descent_unscaled()
+    }
+
+    fn line_gap_unscaled(&self) -> f32 {
+        self.font.line_gap_unscaled()
+    }
+
+    fn glyph_id(&self, c: char) -> GlyphId {
+        let id = self.font.glyph_id(c);
+        self.substitutions.get(&id).copied().unwrap_or(id)
+    }
+
+    fn h_advance_unscaled(&self, id: GlyphId) -> f32 {
+        self.font.h_advance_unscaled(id)
+    }
+
+    fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+        self.font.h_side_bearing_unscaled(id)
+    }
+
+    fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+        self.font.v_advance_unscaled(id)
+    }
+
+    fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+        self.font.v_side_bearing_unscaled(id)
+    }
+
+    fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+        self.font.kern_unscaled(first, second)
+    }
+
+    fn outline(&self, id: GlyphId) -> Option<Outline> {
+        self.font.outline(id)
+    }
+
+    fn glyph_count(&self) -> usize {
+        self.font.glyph_count()
+    }
+
+    fn codepoint_ids(&self) -> CodepointIdIter<'_> {
+        self.font.codepoint_ids()
+    }
+
+    fn glyph_raster_image(&self, id: GlyphId, size: u16) -> Option<GlyphImage> {
+        self.font.glyph_raster_image(id, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn slashed_zero_replaces_the_glyph_of_zero() {
+        let data = crate::font::FONT_CASCADIAMONO_REGULAR;
+        let font = FontArc::try_from_slice(data).unwrap();
+        let table = FeatureTable::parse(data, 0);
+        let zero = font.glyph_id('0');
+
+        let slashed = FeatureFont::new(font.clone(), table.substitutions(&[("zero", 1)]));
+        assert_ne!(slashed.glyph_id('0'), zero);
+        assert!(slashed.outline(slashed.glyph_id('0')).is_some());
+        assert_eq!(slashed.glyph_id('a'), font.glyph_id('a'));
+
+        // Off, unknown or malformed tags replace nothing.
+        assert!(table.substitutions(&[("zero", 0)]).is_empty());
+        assert!(table.substitutions(&[("zzzz", 1)]).is_empty());
+        assert!(table.substitutions(&[("zero-", 1)]).is_empty());
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn coverage_ranges_cover_the_same_glyphs() {
+        let data = crate::font::FONT_CASCADIAMONO_REGULAR;
+        let face = owned_ttf_parser::Face::from_slice(data, 0).unwrap();
+        let gsub = face.tables().gsub.unwrap();
+        let mut formats = [false; 2];
+        for lookup in gsub.lookups {
+            for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+                let coverage = match subtable {
+                    SubstitutionSubtable::Single(single) => single.coverage(),
+                    SubstitutionSubtable::Alternate(alternate) => alternate.coverage,
+                    _ => continue,
+                };
+                formats[matches!(coverage, Coverage::Format2 { .. }) as usize] = true;
+                let every_glyph: Vec<(u16, u16)> = (0..face.number_of_glyphs())
+                    .filter_map(|glyph| {
+                        let index = coverage.get(owned_ttf_parser::GlyphId(glyph))?;
+                        Some((glyph, index))
+                    })
+                    .collect();
+                let mut ranges = covered(coverage);
+                ranges.sort_unstable();
+                assert_eq!(ranges, every_glyph);
+            }
+        }
+        assert_eq!(formats, [true, true]);
+    }
+
+    #[test]
+    fn fonts_without_features_have_none() {
+        let table = FeatureTable::parse(b"not a font", 0);
+        assert!(table.substitutions(&[("zero", 1)]).is_empty());
+    }
+}
@@ -1,143 +1,1537 @@
+mod features;
+mod synthetic;
+
+pub use features::{FeatureFont, FeatureTable};
+use font_kit::handle::Handle;
+use font_kit::properties::{Properties, Style, Weight};
 use font_kit::source::SystemSource;
-use glyph_brush::ab_glyph::{FontArc, FontVec};
+use glyph_brush::ab_glyph::{Font as GFont, FontArc, FontVec, ScaleFont};
 use log::warn;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+pub use synthetic::{
+    SyntheticBold, SyntheticItalic, DEFAULT_BOLD_STRENGTH, DEFAULT_ITALIC_SLANT,
+    MAX_ITALIC_SLANT,
+};
 
 pub const DEFAULT_FONT_NAME: &str = "cascadiamono";
 
+#[cfg(feature = "bundled-fonts")]
 pub const FONT_CASCADIAMONO_REGULAR: &[u8; 308212] =
     include_bytes!("./resources/CascadiaMono/CascadiaMonoPL-Regular.otf");
 
+#[cfg(feature = "bundled-fonts")]
 pub const FONT_CASCADIAMONO_BOLD: &[u8; 312976] =
     include_bytes!("./resources/CascadiaMono/CascadiaMonoPL-Bold.otf");
 
+#[cfg(feature = "bundled-fonts")]
 pub const FONT_CASCADIAMONO_ITALIC: &[u8; 191296] =
     include_bytes!("./resources/CascadiaMono/CascadiaMonoPL-Italic.otf");
 
+#[cfg(feature = "bundled-fonts")]
 pub const FONT_CASCADIAMONO_BOLD_ITALIC: &[u8; 193360] =
     include_bytes!("./resources/CascadiaMono/CascadiaMonoPL-BoldItalic.otf");
 
+#[cfg(feature = "bundled-fonts")]
 pub const FONT_EMOJI: &[u8; 877988] =
     include_bytes!("./resources/NotoEmoji/static/NotoEmoji-Regular.ttf");
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(feature = "bundled-fonts")]
 pub const FONT_DEJAVU_MONO: &[u8; 340712] =
     include_bytes!("./resources/DejaVuSansMono.ttf");
 
+/// Weights from this one on are bold, the lighter ones regular.
+const BOLD_WEIGHT_THRESHOLD: f32 = 550.;
+
+/// Index of the face with the given style among the faces of a family, the
+/// one with the weight closest to normal (or to bold) on its side of
+/// `BOLD_WEIGHT_THRESHOLD`.
+fn closest_face(faces: &[Properties], italic: bool, bold: bool) -> Option<usize> {
+    let target = if bold { Weight::BOLD } else { Weight::NORMAL };
+    faces
+        .iter()
+        .enumerate()
+        .filter(|(_, properties)| {
+            (properties.style != Style::Normal) == italic
+                && (properties.weight.0 >= BOLD_WEIGHT_THRESHOLD) == bold
+        })
+        .min_by(|(_, a), (_, b)| {
+            let distance =
+                |properties: &Properties| (properties.weight.0 - target.0).abs();
+            distance(a).total_cmp(&distance(b))
+        })
+        .map(|(index, _)| index)
+}
+
+/// Index of the regular face, upright and not bold, among the faces of a
+/// family.
+fn regular_face(faces: &[Properties]) -> Option<usize> {
+    closest_face(faces, false, false)
+}
+
+/// Index of the italic (or oblique) face that isn't bold among the faces of
+/// a family.
+fn italic_face(faces: &[Properties]) -> Option<usize> {
+    closest_face(faces, true, false)
+}
+
+/// Index of the bold face, upright or italic, among the faces of a family.
+fn bold_face(faces: &[Properties], italic: bool) -> Option<usize> {
+    closest_face(faces, italic, true)
+}
+
+/// Faces made from the ones a family has when it lacks them, instead of
+/// using the bundled Cascadia Mono ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontSynthesis {
+    /// How much the outlines of the regular face grow on each side, in ems,
+    /// to embolden it when there's no bold face.
+    pub bold_strength: Option<f32>,
+    /// Slant in degrees of the obliques made from the regular and bold faces
+    /// when there are no italic ones.
+    pub italic_slant: Option<f32>,
+}
+
+impl Default for FontSynthesis {
+    fn default() -> FontSynthesis {
+        FontSynthesis {
+            bold_strength: Some(DEFAULT_BOLD_STRENGTH),
+            italic_slant: Some(DEFAULT_ITALIC_SLANT),
+        }
+    }
+}
+
+/// Why a font couldn't be loaded.
+#[derive(Debug, PartialEq)]
+pub enum FontError {
+    /// No system font with this name.
+    SystemFontMissing(String),
+    /// The font exists but its data couldn't be read.
+    FontDataUnavailable(String),
+    /// The font data isn't a font ab_glyph can use.
+    ParseFailed(String),
+    /// The family was found but none of its faces is regular.
+    MissingRegularFace { family: String, found: Vec<String> },
+    /// The family was found but it has no face of this style, e.g. bold.
+    MissingStyle { family: String, style: String },
+    /// The face index is past the faces of the font (collections like .ttc
+    /// files pack several of them, other fonts have one).
+    BadFaceIndex {
+        name: String,
+        index: u32,
+        faces: u32,
+    },
+    /// Glyphs of the font have different widths, they wouldn't fit the grid.
+    NotMonospace(String),
+    /// Glyphs are only color bitmaps (sbix or CBDT tables), the text
+    /// pipeline only rasterizes outlines so they would be blank.
+    NoOutlines(String),
+    /// The font can't be loaded and the fonts aren't bundled (the
+    /// `bundled-fonts` feature is disabled), so there's nothing to use.
+    NoBundledFallback(String),
+}
+
+impl std::fmt::Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontError::SystemFontMissing(name) => {
+                write!(f, "font {name} is not installed")
+            }
+            FontError::FontDataUnavailable(name) => {
+                write!(f, "failed to read the data of font {name}")
+            }
+            FontError::ParseFailed(name) => write!(f, "failed to parse font {name}"),
+            FontError::MissingRegularFace { family, found } => write!(
+                f,
+                "font {family} has no regular face (found: {})",
+                found.join(", ")
+            ),
+            FontError::MissingStyle { family, style } => {
+                write!(f, "font {family} has no {style} face")
+            }
+            FontError::BadFaceIndex { name, index, faces } => write!(
+                f,
+                "font {name} has no face {index}, it has {faces} (counting from 0)"
+            ),
+            FontError::NotMonospace(name) => write!(f, "font {name} is not monospace"),
+            FontError::NoOutlines(name) => write!(
+                f,
+                "font {name} only has color bitmap glyphs, which can't be drawn yet"
+            ),
+            FontError::NoBundledFallback(name) => write!(
+                f,
+                "font {name} can't be loaded and there are no bundled fonts to use instead"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+/// The bundled font `$bundled`, or `$stand_in` when fonts aren't bundled
+/// (the `bundled-fonts` feature is disabled).
+macro_rules! bundled_or {
+    ($bundled:expr, $stand_in:expr) => {{
+        #[cfg(feature = "bundled-fonts")]
+        let font = $bundled;
+        #[cfg(not(feature = "bundled-fonts"))]
+        let font = $stand_in;
+        font
+    }};
+}
+
+/// Data a face was read from. It's read again for the OpenType features of
+/// the face, only once some are turned on, and for its typographic metrics.
+#[derive(Clone)]
+enum FaceData {
+    /// Embedded in the binary.
+    Bundled(&'static [u8]),
+    File(PathBuf),
+    /// Font data of a system font that isn't in a file.
+    Memory(Arc<Vec<u8>>),
+    /// Only the font is known, it has no features.
+    None,
+}
+
+impl FaceData {
+    /// What `parse` gets from the data, `None` when it can't be read.
+    fn read<T>(&self, parse: impl FnOnce(&[u8]) -> T) -> Option<T> {
+        match self {
+            FaceData::Bundled(data) => Some(parse(data)),
+            FaceData::File(path) => match std::fs::read(path) {
+                Ok(data) => Some(parse(&data)),
+                Err(error) => {
+                    warn!("failed to read font {}: {error}", path.display());
+                    None
+                }
+            },
+            FaceData::Memory(data) => Some(parse(data)),
+            FaceData::None => None,
+        }
+    }
+}
+
+/// Where the middle of the typographic box of face `index` (OS/2 ascender
+/// to descender) is below the middle of its line, which spans the ascent to
+/// the descent, as a fraction of the line height. Fonts with more room above
+/// their glyphs than below (e.g. for accents on capitals) look too low.
+fn typographic_center(data: &[u8], index: u32) -> Option<f32> {
+    let face = owned_ttf_parser::Face::from_slice(data, index).ok()?;
+    let (ascent, descent) = (face.ascender() as f32, face.descender() as f32);
+    let typographic =
+        face.typographic_ascender()? as f32 + face.typographic_descender()? as f32;
+    if ascent <= descent {
+        return None;
+    }
+    Some(((ascent + descent) - typographic) / 2. / (ascent - descent))
+}
+
+/// A face as it was loaded, with where it comes from so the OpenType
+/// features of its data can be turned on later.
+#[derive(Clone)]
+pub struct Face {
+    pub font: FontArc,
+    data: FaceData,
+    index: u32,
+    features: Arc<OnceLock<FeatureTable>>,
+    center: Arc<OnceLock<f32>>,
+}
+
+impl Face {
+    fn new(font: FontArc, data: FaceData, index: u32) -> Face {
+        Face {
+            font,
+            data,
+            index,
+            features: Arc::default(),
+            center: Arc::default(),
+        }
+    }
+
+    /// Another font with the glyphs of this face, e.g. a synthetic style.
+    fn with_font(&self, font: FontArc) -> Face {
+        Face {
+            font,
+            ..self.clone()
+        }
+    }
+
+    /// The font with the glyph substitutions of `features`. The features of
+    /// the face are read the first time some are asked for.
+    fn with_features(&self, features: &[(&str, u32)]) -> FontArc {
+        if features.is_empty() {
+            return self.font.clone();
+        }
+        let substitutions = self
+            .features
+            .get_or_init(|| {
+                let index = self.index;
+                self.data
+                    .read(|data| FeatureTable::parse(data, index))
+                    .unwrap_or_default()
+            })
+            .substitutions(features);
+        if substitutions.is_empty() {
+            return self.font.clone();
+        }
+        FontArc::new(FeatureFont::new(self.font.clone(), substitutions))
+    }
+
+    /// Pixels glyphs drawn `line_height` pixels tall are moved down so they
+    /// look vertically centered in their line, see `typographic_center`.
+    /// Zero when the face has no typographic metrics.
+    fn baseline_offset(&self, line_height: f32) -> f32 {
+        let center = self.center.get_or_init(|| {
+            let index = self.index;
+            self.data
+                .read(|data| typographic_center(data, index))
+                .flatten()
+                .unwrap_or(0.)
+        });
+        -center * line_height
+    }
+
+    /// The face draws glyphs in their own colors, see `has_color_tables`.
+    #[cfg_attr(not(target_os = "macos"), allow(unused))]
+    fn has_colors(&self) -> bool {
+        let index = self.index;
+        self.data
+            .read(|data| has_color_tables(data, index))
+            .unwrap_or(false)
+    }
+
+    /// Metrics of the cells the face draws at `px` pixels. The advance is the
+    /// one of 'M', or the widest advance of the face when 'M' is missing or
+    /// has no advance.
+    fn cell_metrics(&self, px: f32) -> CellMetrics {
+        let scaled = self.font.as_scaled(px);
+        let advance = match scaled.glyph_id('M') {
+            id if id.0 != 0 && scaled.h_advance(id) > 0. => scaled.h_advance(id),
+            _ => {
+                let index = self.index;
+                self.data
+                    .read(|data| max_advance(data, index))
+                    .flatten()
+                    .unwrap_or(0.)
+                    * scaled.h_scale_factor()
+            }
+        };
+
+        CellMetrics {
+            advance,
+            line_height: scaled.height() + scaled.line_gap(),
+            ascent: scaled.ascent(),
+            descent: scaled.descent(),
+        }
+    }
+}
+
+/// A face without OpenType features.
+impl From<FontArc> for Face {
+    fn from(font: FontArc) -> Face {
+        Face::new(font, FaceData::None, 0)
+    }
+}
+
+#[derive(Clone)]
 pub struct ComposedFontArc {
     pub regular: FontArc,
     pub bold: FontArc,
     pub italic: FontArc,
     pub bold_italic: FontArc,
+    /// Regular, bold, italic and bold italic faces before OpenType features.
+    faces: [Face; 4],
+}
+
+impl ComposedFontArc {
+    fn new(
+        regular: Face,
+        bold: Face,
+        italic: Face,
+        bold_italic: Face,
+    ) -> ComposedFontArc {
+        ComposedFontArc {
+            regular: regular.font.clone(),
+            bold: bold.font.clone(),
+            italic: italic.font.clone(),
+            bold_italic: bold_italic.font.clone(),
+            faces: [regular, bold, italic, bold_italic],
+        }
+    }
+
+    /// Faces of a family, the missing ones are synthesized as `synthesis`
+    /// allows or bundled ones. Without bundled fonts the regular (or bold)
+    /// face stands in for them.
+    fn compose(
+        regular: Face,
+        bold: Option<Face>,
+        italic: Option<Face>,
+        bold_italic: Option<Face>,
+        synthesis: FontSynthesis,
+    ) -> ComposedFontArc {
+        let bold = bold.or_else(|| {
+            synthesis.bold_strength.map(|strength| {
+                regular.with_font(FontArc::new(SyntheticBold::new(
+                    regular.font.clone(),
+                    strength,
+                )))
+            })
+        });
+        let slanted = |upright: &Face| {
+            synthesis.italic_slant.map(|slant| {
+                upright.with_font(FontArc::new(SyntheticItalic::new(
+                    upright.font.clone(),
+                    slant,
+                )))
+            })
+        };
+
+        let italic = italic.or_else(|| slanted(&regular)).unwrap_or_else(|| {
+            bundled_or!(bundled_face(FONT_CASCADIAMONO_ITALIC), regular.clone())
+        });
+        let bold_italic = bold_italic.or_else(|| bold.as_ref().and_then(slanted));
+        let bold = bold.unwrap_or_else(|| {
+            bundled_or!(bundled_face(FONT_CASCADIAMONO_BOLD), regular.clone())
+        });
+        let bold_italic = bold_italic.unwrap_or_else(|| {
+            bundled_or!(bundled_face(FONT_CASCADIAMONO_BOLD_ITALIC), bold.clone())
+        });
+
+        ComposedFontArc::new(regular, bold, italic, bold_italic)
+    }
+
+    /// Bundled Cascadia Mono, `None` when fonts aren't bundled.
+    fn bundled() -> Option<ComposedFontArc> {
+        #[cfg(feature = "bundled-fonts")]
+        return Some(ComposedFontArc::new(
+            bundled_face(FONT_CASCADIAMONO_REGULAR),
+            bundled_face(FONT_CASCADIAMONO_BOLD),
+            bundled_face(FONT_CASCADIAMONO_ITALIC),
+            bundled_face(FONT_CASCADIAMONO_BOLD_ITALIC),
+        ));
+        #[cfg(not(feature = "bundled-fonts"))]
+        None
+    }
+
+    /// Turns on the OpenType `features` of every face, replacing the ones
+    /// turned on before.
+    fn set_features(&mut self, features: &[(&str, u32)]) {
+        let [regular, bold, italic, bold_italic] = &self.faces;
+        self.regular = regular.with_features(features);
+        self.bold = bold.with_features(features);
+        self.italic = italic.with_features(features);
+        self.bold_italic = bold_italic.with_features(features);
+    }
+}
+
+/// Fonts embedded in the binary are known to parse.
+#[cfg(feature = "bundled-fonts")]
+fn bundled_font(data: &'static [u8]) -> FontArc {
+    FontArc::try_from_slice(data).expect("bundled fonts are valid")
+}
+
+/// A bundled font with its OpenType features.
+#[cfg(feature = "bundled-fonts")]
+fn bundled_face(data: &'static [u8]) -> Face {
+    Face::new(bundled_font(data), FaceData::Bundled(data), 0)
+}
+
+/// Number of faces of a collection (.ttc) from its header, `None` for any
+/// other font.
+fn collection_faces(data: &[u8]) -> Option<u32> {
+    match data {
+        [b't', b't', b'c', b'f', _, _, _, _, a, b, c, d, ..] => {
+            Some(u32::from_be_bytes([*a, *b, *c, *d]))
+        }
+        _ => None,
+    }
+}
+
+/// Face `index` of the font `data`, read from `source`. The index only
+/// matters for collections, other fonts have a single face which is read
+/// whatever the index.
+fn font_face(
+    data: Vec<u8>,
+    source: FaceData,
+    name: &str,
+    index: u32,
+) -> Result<Face, FontError> {
+    let index = match collection_faces(&data) {
+        Some(faces) if index >= faces => {
+            return Err(FontError::BadFaceIndex {
+                name: name.to_string(),
+                index,
+                faces,
+            })
+        }
+        Some(_) => index,
+        None => 0,
+    };
+
+    let font_vec = FontVec::try_from_vec_and_index(data, index)
+        .map_err(|_| FontError::ParseFailed(name.to_string()))?;
+    Ok(Face::new(FontArc::new(font_vec), source, index))
+}
+
+/// Reads face `index` of a .ttf, .otf or .ttc font file.
+fn font_file(path: &Path, index: u32) -> Result<Face, FontError> {
+    let name = path.display().to_string();
+    let data =
+        std::fs::read(path).map_err(|_| FontError::FontDataUnavailable(name.clone()))?;
+    font_face(data, FaceData::File(path.to_path_buf()), &name, index)
+}
+
+#[inline]
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            ["ttf", "otf", "ttc"]
+                .iter()
+                .any(|font_extension| extension.eq_ignore_ascii_case(font_extension))
+        })
+}
+
+/// Every glyph the font has among a few narrow and wide characters takes the
+/// same advance.
+fn is_monospace(font: &FontArc) -> bool {
+    let mut advances = "iMW0 "
+        .chars()
+        .map(|c| font.glyph_id(c))
+        .filter(|glyph| glyph.0 != 0)
+        .map(|glyph| font.h_advance_unscaled(glyph));
+    match advances.next() {
+        Some(first) => advances.all(|advance| advance == first),
+        None => false,
+    }
+}
+
+/// Font files next to `path` that are other styles of the same family, e.g.
+/// `MyMono-Bold.ttf` for `MyMono-Regular.ttf`. The style is lowercase and
+/// without separators, `bolditalic` for `MyMono-Bold Italic.otf`.
+fn sibling_faces(path: &Path) -> Vec<(String, PathBuf)> {
+    let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        return vec![];
+    };
+    let family = stem
+        .trim_end_matches("Regular")
+        .trim_end_matches(|c: char| matches!(c, '-' | '_' | ' '));
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|sibling| is_font_file(sibling))
+        .filter_map(|sibling| {
+            let style = sibling.file_stem()?.to_str()?.strip_prefix(family)?;
+            let style = style
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase();
+            Some((style, sibling))
+        })
+        .collect()
+}
+
+/// `font` has a glyph for `c`, id 0 is the missing glyph.
+#[inline]
+fn has_glyph(font: &FontArc, c: char) -> bool {
+    font.glyph_id(c).0 != 0
+}
+
+/// The font has an outline for a common emoji, color emoji fonts often only
+/// have bitmaps.
+#[cfg_attr(not(target_os = "macos"), allow(unused))]
+fn has_emoji_outlines(font: &FontArc) -> bool {
+    let id = font.glyph_id('\u{1F600}');
+    // Id 0 is the missing glyph, which usually has an outline.
+    id.0 != 0 && font.outline_glyph(id.with_scale(16.0)).is_some()
 }
 
+/// Native color emojis, when the text pipeline can draw them.
+#[cfg(target_os = "macos")]
+fn native_emojis() -> Result<Face, FontError> {
+    let name = "AppleColorEmoji";
+    let face = system_face(name)?;
+    if !has_emoji_outlines(&face.font) {
+        return Err(FontError::NoOutlines(name.to_string()));
+    }
+    Ok(face)
+}
+
+thread_local! {
+    /// Building a source enumerates every installed font, which is slow with
+    /// fontconfig and hundreds of fonts. Sources can't be shared between
+    /// threads, so each thread that loads fonts keeps its own.
+    static SYSTEM_SOURCE: SystemSource = SystemSource::new();
+}
+
+/// A face of a system font and its index in the font data, which holds
+/// several faces when it's a collection (.ttc) like Menlo.
+struct SystemFace {
+    font: font_kit::font::Font,
+    source: FaceData,
+    index: u32,
+}
+
+impl SystemFace {
+    fn load(handle: &Handle) -> Option<SystemFace> {
+        let (source, index) = match handle {
+            Handle::Path { path, font_index } => {
+                (FaceData::File(path.clone()), *font_index)
+            }
+            Handle::Memory { bytes, font_index } => {
+                (FaceData::Memory(bytes.clone()), *font_index)
+            }
+        };
+        let font = handle.load().ok()?;
+        Some(SystemFace {
+            font,
+            source,
+            index,
+        })
+    }
+
+    /// Reads the data of the face.
+    fn face(&self, name: &str) -> Result<Face, FontError> {
+        let data = self
+            .font
+            .copy_font_data()
+            .ok_or_else(|| FontError::FontDataUnavailable(name.to_string()))?;
+        font_face(data.to_vec(), self.source.clone(), name, self.index)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn system_face(postscript_name: &str) -> Result<Face, FontError> {
+    let handle = SYSTEM_SOURCE
+        .with(|source| source.select_by_postscript_name(postscript_name))
+        .map_err(|_| FontError::SystemFontMissing(postscript_name.to_string()))?;
+    let face = SystemFace::load(&handle)
+        .ok_or_else(|| FontError::FontDataUnavailable(postscript_name.to_string()))?;
+    face.face(postscript_name)
+}
+
+#[cfg(target_os = "macos")]
+fn system_font(postscript_name: &str) -> Result<FontArc, FontError> {
+    Ok(system_face(postscript_name)?.font)
+}
+
+/// Size of the grid cells for a font, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellMetrics {
+    /// Width of a cell, the advance of the glyphs of a monospace font.
+    pub advance: f32,
+    /// Distance between the baselines of two rows.
+    pub line_height: f32,
+    /// Height above the baseline.
+    pub ascent: f32,
+    /// Depth below the baseline, it's negative.
+    pub descent: f32,
+}
+
+/// Face `index` has glyphs with their own colors: layers (COLR), bitmaps
+/// (CBDT, sbix) or SVG documents.
+fn has_color_tables(data: &[u8], index: u32) -> bool {
+    let Ok(face) = owned_ttf_parser::RawFace::from_slice(data, index) else {
+        return false;
+    };
+    [b"COLR", b"CBDT", b"sbix", b"SVG "]
+        .iter()
+        .any(|tag| face.table(owned_ttf_parser::Tag::from_bytes(tag)).is_some())
+}
+
+/// Widest advance of face `index`, `advanceWidthMax` of its `hhea` table, in
+/// font units.
+fn max_advance(data: &[u8], index: u32) -> Option<f32> {
+    let face = owned_ttf_parser::RawFace::from_slice(data, index).ok()?;
+    let hhea = face.table(owned_ttf_parser::Tag::from_bytes(b"hhea"))?;
+    Some(u16::from_be_bytes([*hhea.get(10)?, *hhea.get(11)?]) as f32)
+}
+
+/// Family of each style of the text font, `None` is the bundled Cascadia
+/// Mono one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FontFamilies {
+    pub regular: Option<String>,
+    pub bold: Option<String>,
+    pub italic: Option<String>,
+    pub bold_italic: Option<String>,
+}
+
+#[derive(Clone)]
 pub struct Font {
     pub text: ComposedFontArc,
     pub symbol: FontArc,
     pub emojis: FontArc,
+    /// The emojis have their own colors, they aren't tinted by the
+    /// foreground color of their run.
+    pub color_emojis: bool,
     pub unicode: FontArc,
+    /// Tried in order after `unicode` for glyphs no other font has.
+    pub fallback: Vec<FontArc>,
 }
 
 impl Font {
-    // TODO: Refactor multiple unwraps in this code
-    // TODO: Use FontAttributes bold and italic
-    pub fn new(font_name: String) -> Font {
+    /// Loads `font_name`, falling back to the bundled font when it cannot
+    /// be loaded. Errors only come from the symbol and unicode fonts of the
+    /// system, or from `font_name` when fonts aren't bundled. Faces the
+    /// family lacks are made following `synthesis`.
+    pub fn new(font_name: String, synthesis: FontSynthesis) -> Result<Font, FontError> {
+        let text = match Font::load_text(&font_name, synthesis) {
+            Ok(text) => text,
+            Err(error) => {
+                warn!("{error}");
+                ComposedFontArc::bundled()
+                    .ok_or(FontError::NoBundledFallback(font_name))?
+            }
+        };
+
+        Font::with_text(text)
+    }
+
+    /// Loads `font_name` without falling back, so callers can keep their
+    /// current font when the requested one is unavailable.
+    pub fn load(font_name: String, synthesis: FontSynthesis) -> Result<Font, FontError> {
+        Font::with_text(Font::load_text(&font_name, synthesis)?)
+    }
+
+    /// Loads the .ttf, .otf or .ttc file at `path` as the text font. Bold,
+    /// italic and bold italic faces are looked up next to it by file name,
+    /// missing ones are made following `synthesis`.
+    pub fn from_path(path: &Path, synthesis: FontSynthesis) -> Result<Font, FontError> {
+        Font::from_path_indexed(path, 0, synthesis)
+    }
+
+    /// Like `from_path`, with the regular face at `index` of a collection
+    /// (.ttc) that packs several faces, e.g. one per language of a CJK font.
+    pub fn from_path_indexed(
+        path: &Path,
+        index: u32,
+        synthesis: FontSynthesis,
+    ) -> Result<Font, FontError> {
+        let regular = font_file(path, index)?;
+        if !is_monospace(&regular.font) {
+            return Err(FontError::NotMonospace(path.display().to_string()));
+        }
+
+        let (mut bold, mut italic, mut bold_italic) = (None, None, None);
+        for (style, sibling) in sibling_faces(path) {
+            let face = match style.as_str() {
+                "bold" => &mut bold,
+                "italic" | "oblique" => &mut italic,
+                "bolditalic" | "boldoblique" => &mut bold_italic,
+                _ => continue,
+            };
+            match font_file(&sibling, 0) {
+                Ok(font) => *face = Some(font),
+                Err(error) => warn!("{error}"),
+            }
+        }
+
+        Font::with_text(ComposedFontArc::compose(
+            regular,
+            bold,
+            italic,
+            bold_italic,
+            synthesis,
+        ))
+    }
+
+    /// Loads each style from its own family in `families`, e.g. the italic
+    /// of another font. Styles without a family, or whose family can't be
+    /// loaded or lacks that style, are the bundled Cascadia Mono ones. When
+    /// fonts aren't bundled those are made following `synthesis` instead,
+    /// and the regular family can't be missing.
+    pub fn from_families(
+        families: FontFamilies,
+        synthesis: FontSynthesis,
+    ) -> Result<Font, FontError> {
+        let bundled = ComposedFontArc::bundled();
+        let face = |family: &Option<String>,
+                    italic: bool,
+                    bold: bool,
+                    bundled_face: fn(&ComposedFontArc) -> &Face| {
+            let loaded = family
+                .as_deref()
+                .filter(|name| name.to_lowercase() != DEFAULT_FONT_NAME)
+                .and_then(|name| match Font::load_face(name, italic, bold) {
+                    Ok(face) => Some(face),
+                    Err(error) => {
+                        warn!("{error}");
+                        None
+                    }
+                });
+            loaded.or_else(|| {
+                bundled
+                    .as_ref()
+                    .map(|bundled| bundled_face(bundled).clone())
+            })
+        };
+
+        let regular = face(&families.regular, false, false, |text| &text.faces[0])
+            .ok_or_else(|| {
+                FontError::NoBundledFallback(
+                    families
+                        .regular
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_FONT_NAME.to_string()),
+                )
+            })?;
+        let bold = face(&families.bold, false, true, |text| &text.faces[1]);
+        let italic = face(&families.italic, true, false, |text| &text.faces[2]);
+        let bold_italic = face(&families.bold_italic, true, true, |text| &text.faces[3]);
+
+        Font::with_text(ComposedFontArc::compose(
+            regular,
+            bold,
+            italic,
+            bold_italic,
+            synthesis,
+        ))
+    }
+
+    fn with_text(text: ComposedFontArc) -> Result<Font, FontError> {
         let font_arc_unicode;
         let font_arc_symbol;
+        let font_arc_emojis;
+        let color_emojis;
 
         #[cfg(target_os = "macos")]
         {
-            let font_symbols = SystemSource::new()
-                .select_by_postscript_name("Apple Symbols")
-                .unwrap()
-                .load()
-                .unwrap();
-            let copied_font_symbol = font_symbols.copy_font_data();
-            let Some(copied_font_symbol) = copied_font_symbol else { todo!() };
-            let font_vec_symbol =
-                FontVec::try_from_vec_and_index(copied_font_symbol.to_vec(), 1).unwrap();
-            font_arc_symbol = FontArc::new(font_vec_symbol);
-
-            // TODO: Load native emojis
-            // let font_emojis = SystemSource::new()
-            //     .select_by_postscript_name("Apple Color Emoji")
-            //     .unwrap()
-            //     .load()
-            //     .unwrap();
-            // let copied_font_emojis = font_emojis.copy_font_data();
-            // let Some(copied_font_emojis) = copied_font_emojis else { todo!() };
-            // let font_vec_emojis = FontVec::try_from_vec_and_index(copied_font_emojis.to_vec(), 2).unwrap();
-
-            let font_unicode = SystemSource::new()
-                .select_by_postscript_name("Arial Unicode MS")
-                .unwrap()
-                .load()
-                .unwrap();
-            let copied_font_unicode = font_unicode.copy_font_data();
-            let Some(copied_font_unicode) = copied_font_unicode else { todo!() };
-            let font_vec_unicode =
-                FontVec::try_from_vec_and_index(copied_font_unicode.to_vec(), 3).unwrap();
-            font_arc_unicode = FontArc::new(font_vec_unicode);
+            // Without the system font (and the bundled ones) the text face
+            // stands in for it.
+            let system_or_bundled = |postscript_name: &str| {
+                system_font(postscript_name).unwrap_or_else(|error| {
+                    warn!("{error}, using the bundled font instead");
+                    bundled_or!(bundled_font(FONT_DEJAVU_MONO), text.regular.clone())
+                })
+            };
+            font_arc_symbol = system_or_bundled("Apple Symbols");
+
+            // Apple Color Emoji only has sbix bitmaps, until those can be
+            // drawn the bundled emojis are used instead.
+            (font_arc_emojis, color_emojis) = native_emojis()
+                .map(|face| (face.font.clone(), face.has_colors()))
+                .unwrap_or_else(|error| {
+                    warn!("{error}");
+                    let font =
+                        bundled_or!(bundled_font(FONT_EMOJI), font_arc_symbol.clone());
+                    (font, false)
+                });
+
+            font_arc_unicode = system_or_bundled("Arial Unicode MS");
         }
 
+        // Without bundled fonts the text face stands in for the others.
         #[cfg(not(target_os = "macos"))]
         {
-            font_arc_unicode = FontArc::try_from_slice(FONT_DEJAVU_MONO).unwrap();
-            font_arc_symbol = FontArc::try_from_slice(FONT_DEJAVU_MONO).unwrap();
-        }
-
-        let is_default_font = font_name.to_lowercase() == DEFAULT_FONT_NAME;
-        if !is_default_font {
-            if let Ok(system_fonts) =
-                SystemSource::new().select_family_by_name(&font_name)
-            {
-                let fonts = system_fonts.fonts();
-                if !fonts.is_empty() {
-                    let first_font = fonts[0].load();
-                    if let Ok(font) = first_font {
-                        let copied_font = font.copy_font_data();
-                        if copied_font.is_some() {
-                            let Some(copied_font) = copied_font else { todo!() };
-                            let font_vec_system =
-                                FontVec::try_from_vec_and_index(copied_font.to_vec(), 0)
-                                    .unwrap();
-
-                            return Font {
-                                text: ComposedFontArc {
-                                    regular: FontArc::new(font_vec_system),
-                                    bold: FontArc::try_from_slice(FONT_CASCADIAMONO_BOLD)
-                                        .unwrap(),
-                                    italic: FontArc::try_from_slice(
-                                        FONT_CASCADIAMONO_ITALIC,
-                                    )
-                                    .unwrap(),
-                                    bold_italic: FontArc::try_from_slice(
-                                        FONT_CASCADIAMONO_BOLD_ITALIC,
-                                    )
-                                    .unwrap(),
-                                },
-                                symbol: font_arc_symbol,
-                                emojis: FontArc::try_from_slice(FONT_EMOJI).unwrap(),
-                                unicode: font_arc_unicode,
-                            };
-                        }
-                    }
+            font_arc_unicode =
+                bundled_or!(bundled_font(FONT_DEJAVU_MONO), text.regular.clone());
+            font_arc_symbol =
+                bundled_or!(bundled_font(FONT_DEJAVU_MONO), text.regular.clone());
+            font_arc_emojis = bundled_or!(bundled_font(FONT_EMOJI), text.regular.clone());
+            // The bundled emojis are monochrome.
+            color_emojis = false;
+        }
+
+        Ok(Font {
+            text,
+            symbol: font_arc_symbol,
+            emojis: font_arc_emojis,
+            color_emojis,
+            unicode: font_arc_unicode,
+            fallback: vec![],
+        })
+    }
+
+    /// Faces in the order glyphs are looked up in: text, symbol, emojis,
+    /// unicode and then the fallbacks.
+    fn faces(&self) -> impl Iterator<Item = &FontArc> {
+        [
+            &self.text.regular,
+            &self.symbol,
+            &self.emojis,
+            &self.unicode,
+        ]
+        .into_iter()
+        .chain(&self.fallback)
+    }
+
+    /// Turns on the OpenType `features` of the text faces, tags with a value
+    /// as in CSS `font-feature-settings`, e.g. `("zero", 1)` for a slashed
+    /// zero. Only features that replace single glyphs take effect.
+    pub fn set_features(&mut self, features: &[(&str, u32)]) {
+        self.text.set_features(features);
+    }
+
+    /// Size of the grid cells from the regular text face, at a scale of `px`
+    /// pixels from descent to ascent as text is drawn with.
+    pub fn cell_metrics(&self, px: f32) -> CellMetrics {
+        self.text.faces[0].cell_metrics(px)
+    }
+
+    /// Pixels text drawn `px` pixels tall is moved down so it's vertically
+    /// centered in its line, from the ascent and descent of the regular text
+    /// face. It's negative when the text moves up.
+    pub fn baseline_offset(&self, px: f32) -> f32 {
+        self.text.faces[0].baseline_offset(px)
+    }
+
+    /// Some face has a glyph for `c`.
+    pub fn covers(&self, c: char) -> bool {
+        self.faces().any(|face| has_glyph(face, c))
+    }
+
+    /// Like `new`, with `fallbacks` families tried in order for glyphs the
+    /// other fonts don't have. Families that can't be loaded are skipped.
+    pub fn with_fallbacks(
+        primary: String,
+        fallbacks: Vec<String>,
+        synthesis: FontSynthesis,
+    ) -> Result<Font, FontError> {
+        let mut font = Font::new(primary, synthesis)?;
+        font.fallback = fallbacks
+            .iter()
+            .filter_map(|name| match Font::load_regular(name) {
+                Ok(fallback) => Some(fallback.font),
+                Err(error) => {
+                    warn!("{error}");
+                    None
                 }
+            })
+            .collect();
+        Ok(font)
+    }
+
+    fn load_text(
+        font_name: &str,
+        synthesis: FontSynthesis,
+    ) -> Result<ComposedFontArc, FontError> {
+        if font_name.to_lowercase() == DEFAULT_FONT_NAME {
+            if let Some(bundled) = ComposedFontArc::bundled() {
+                return Ok(bundled);
             }
+        }
+
+        let faces = Font::load_family(font_name)?;
+        let properties: Vec<Properties> =
+            faces.iter().map(|face| face.font.properties()).collect();
+        let face = |index: Option<usize>| {
+            index.map(|index| faces[index].face(font_name)).transpose()
+        };
+
+        Ok(ComposedFontArc::compose(
+            Font::regular_of(font_name, &faces)?,
+            face(bold_face(&properties, false))?,
+            face(italic_face(&properties))?,
+            face(bold_face(&properties, true))?,
+            synthesis,
+        ))
+    }
 
-            warn!("failed to load font {font_name}");
+    /// Regular face of a system font family.
+    fn load_regular(font_name: &str) -> Result<Face, FontError> {
+        Font::regular_of(font_name, &Font::load_family(font_name)?)
+    }
+
+    /// Face of a system font family with the given style.
+    fn load_face(font_name: &str, italic: bool, bold: bool) -> Result<Face, FontError> {
+        let faces = Font::load_family(font_name)?;
+        if !italic && !bold {
+            return Font::regular_of(font_name, &faces);
         }
 
-        Font {
-            text: ComposedFontArc {
-                regular: FontArc::try_from_slice(FONT_CASCADIAMONO_REGULAR).unwrap(),
-                bold: FontArc::try_from_slice(FONT_CASCADIAMONO_BOLD).unwrap(),
-                italic: FontArc::try_from_slice(FONT_CASCADIAMONO_ITALIC).unwrap(),
-                bold_italic: FontArc::try_from_slice(FONT_CASCADIAMONO_BOLD_ITALIC)
-                    .unwrap(),
+        let properties: Vec<Properties> =
+            faces.iter().map(|face| face.font.properties()).collect();
+        let Some(index) = closest_face(&properties, italic, bold) else {
+            let style = match (bold, italic) {
+                (true, true) => "bold italic",
+                (true, false) => "bold",
+                _ => "italic",
+            };
+            return Err(FontError::MissingStyle {
+                family: font_name.to_string(),
+                style: style.to_string(),
+            });
+        };
+
+        faces[index].face(font_name)
+    }
+
+    /// Faces of a system font family that could be loaded.
+    fn load_family(font_name: &str) -> Result<Vec<SystemFace>, FontError> {
+        let family = SYSTEM_SOURCE
+            .with(|source| source.select_family_by_name(font_name))
+            .map_err(|_| FontError::SystemFontMissing(font_name.to_string()))?;
+
+        // A loose match can return a family without a regular face, e.g.
+        // only its bold one.
+        let faces: Vec<_> = family.fonts().iter().filter_map(SystemFace::load).collect();
+        if faces.is_empty() {
+            return Err(FontError::FontDataUnavailable(font_name.to_string()));
+        }
+        Ok(faces)
+    }
+
+    /// Regular face among the `faces` of the family `font_name`.
+    fn regular_of(font_name: &str, faces: &[SystemFace]) -> Result<Face, FontError> {
+        let properties: Vec<Properties> =
+            faces.iter().map(|face| face.font.properties()).collect();
+        let Some(index) = regular_face(&properties) else {
+            return Err(FontError::MissingRegularFace {
+                family: font_name.to_string(),
+                found: faces.iter().map(|face| face.font.full_name()).collect(),
+            });
+        };
+
+        faces[index].face(font_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glyph_brush::ab_glyph::{CodepointIdIter, GlyphId, GlyphImage, Outline};
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn load_default_font() {
+        assert!(
+            Font::load(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).is_ok()
+        );
+    }
+
+    #[test]
+    fn load_unknown_font_fails() {
+        assert!(Font::load(
+            "rio-font-that-does-not-exist".to_string(),
+            FontSynthesis::default()
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn unknown_fallbacks_are_skipped() {
+        let font = Font::with_fallbacks(
+            DEFAULT_FONT_NAME.to_string(),
+            vec![String::from("rio-font-that-does-not-exist")],
+            FontSynthesis::default(),
+        )
+        .unwrap();
+        assert!(font.fallback.is_empty());
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn unknown_font_falls_back_to_bundled() {
+        let font = Font::new(
+            "rio-font-that-does-not-exist".to_string(),
+            FontSynthesis::default(),
+        );
+        assert!(font.is_ok());
+    }
+
+    #[cfg(not(feature = "bundled-fonts"))]
+    #[test]
+    fn unknown_font_fails_without_bundled_fonts() {
+        let font = Font::new(
+            "rio-font-that-does-not-exist".to_string(),
+            FontSynthesis::default(),
+        );
+        assert_eq!(
+            font.err(),
+            Some(FontError::NoBundledFallback(
+                "rio-font-that-does-not-exist".to_string()
+            ))
+        );
+
+        // A font file alone in its directory still gets every style, the
+        // regular face stands in for them.
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/font/resources");
+        let directory = std::env::temp_dir().join("rio-test-font-without-bundled");
+        std::fs::create_dir_all(&directory).unwrap();
+        let lonely = directory.join("DejaVuSansMono.ttf");
+        std::fs::copy(resources.join("DejaVuSansMono.ttf"), &lonely).unwrap();
+        let font = Font::from_path(
+            &lonely,
+            FontSynthesis {
+                bold_strength: None,
+                italic_slant: None,
             },
-            symbol: font_arc_symbol,
-            emojis: FontArc::try_from_slice(FONT_EMOJI).unwrap(),
-            unicode: font_arc_unicode,
+        )
+        .unwrap();
+        assert_eq!(
+            font.text.bold.glyph_count(),
+            font.text.regular.glyph_count()
+        );
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn load_font_from_path_with_siblings() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/font/resources");
+        let glyphs = |font: &FontArc| font.glyph_count();
+
+        let font = Font::from_path(
+            &resources.join("DejaVuSansMono.ttf"),
+            FontSynthesis::default(),
+        )
+        .unwrap();
+        let oblique = font_file(&resources.join("DejaVuSansMono-Oblique.ttf"), 0)
+            .unwrap()
+            .font;
+        let bold = font_file(&resources.join("DejaVuSansMono-Bold.ttf"), 0)
+            .unwrap()
+            .font;
+        assert_eq!(glyphs(&font.text.italic), glyphs(&oblique));
+        assert_eq!(glyphs(&font.text.bold), glyphs(&bold));
+
+        // Alone in a directory, the other styles are the bundled ones.
+        let directory = std::env::temp_dir().join("rio-test-font-from-path");
+        std::fs::create_dir_all(&directory).unwrap();
+        let lonely = directory.join("DejaVuSansMono.ttf");
+        std::fs::copy(resources.join("DejaVuSansMono.ttf"), &lonely).unwrap();
+        let bundled = FontSynthesis {
+            bold_strength: None,
+            italic_slant: None,
+        };
+        let font = Font::from_path(&lonely, bundled).unwrap();
+        let cascadia_bold = bundled_font(FONT_CASCADIAMONO_BOLD);
+        assert_eq!(glyphs(&font.text.bold), glyphs(&cascadia_bold));
+        let cascadia_italic = bundled_font(FONT_CASCADIAMONO_ITALIC);
+        assert_eq!(glyphs(&font.text.italic), glyphs(&cascadia_italic));
+
+        // Or they are made from the regular face.
+        let font = Font::from_path(&lonely, FontSynthesis::default()).unwrap();
+        assert_eq!(glyphs(&font.text.bold), glyphs(&font.text.regular));
+        assert_eq!(glyphs(&font.text.italic), glyphs(&font.text.regular));
+        assert_eq!(glyphs(&font.text.bold_italic), glyphs(&font.text.regular));
+
+        let cascadia = resources.join("CascadiaMono/CascadiaMonoPL-Regular.otf");
+        assert!(Font::from_path(&cascadia, FontSynthesis::default()).is_ok());
+        let styles: Vec<String> = sibling_faces(&cascadia)
+            .into_iter()
+            .map(|(style, _)| style)
+            .collect();
+        assert!(styles.contains(&String::from("bolditalic")));
+        assert!(!styles.iter().any(|style| style.starts_with("pl")));
+    }
+
+    /// A .ttc collection of the fonts `files`, in order.
+    fn collection(files: &[Vec<u8>]) -> Vec<u8> {
+        let header = 12 + 4 * files.len();
+        let mut offsets = vec![];
+        let mut faces = vec![];
+        for file in files {
+            let offset = header + faces.len();
+            let mut face = file.clone();
+            // Offsets of the tables are from the start of the collection.
+            let tables = u16::from_be_bytes([face[4], face[5]]) as usize;
+            for record in (12..12 + tables * 16).step_by(16) {
+                let table = &mut face[record + 8..record + 12];
+                let moved = u32::from_be_bytes(table.try_into().unwrap()) + offset as u32;
+                table.copy_from_slice(&moved.to_be_bytes());
+            }
+            face.resize((face.len() + 3) / 4 * 4, 0);
+            offsets.push(offset as u32);
+            faces.extend(face);
+        }
+
+        let mut data = b"ttcf".to_vec();
+        data.extend(0x0001_0000_u32.to_be_bytes());
+        data.extend((files.len() as u32).to_be_bytes());
+        for offset in offsets {
+            data.extend(offset.to_be_bytes());
+        }
+        data.extend(faces);
+        data
+    }
+
+    #[test]
+    fn load_face_of_a_collection() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/font/resources");
+        let read = |name: &str| std::fs::read(resources.join(name)).unwrap();
+        let directory = std::env::temp_dir().join("rio-test-font-collection");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("DejaVuSansMono.ttc");
+        std::fs::write(
+            &path,
+            collection(&[read("DejaVuSansMono.ttf"), read("DejaVuSansMono-Bold.ttf")]),
+        )
+        .unwrap();
+
+        let bold = font_file(&resources.join("DejaVuSansMono-Bold.ttf"), 0)
+            .unwrap()
+            .font;
+        let font = Font::from_path_indexed(&path, 1, FontSynthesis::default()).unwrap();
+        let id = bold.glyph_id('a');
+        assert_eq!(
+            font.text.regular.outline(id).map(|outline| outline.bounds),
+            bold.outline(id).map(|outline| outline.bounds)
+        );
+
+        assert_eq!(
+            Font::from_path_indexed(&path, 2, FontSynthesis::default()).err(),
+            Some(FontError::BadFaceIndex {
+                name: path.display().to_string(),
+                index: 2,
+                faces: 2,
+            })
+        );
+        // System faces are read at the index of their handle.
+        let system = SystemFace::load(&Handle::from_path(path, 1)).unwrap();
+        let face = system.face("DejaVu Sans Mono").unwrap().font;
+        assert_eq!(
+            face.outline(id).map(|outline| outline.bounds),
+            bold.outline(id).map(|outline| outline.bounds)
+        );
+
+        // A font that isn't a collection has its single face at any index.
+        assert!(Font::from_path_indexed(
+            &resources.join("DejaVuSansMono.ttf"),
+            1,
+            FontSynthesis::default()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn load_font_from_path_rejects_proportional_fonts() {
+        let proportional = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/fonts/silkscreen-v1-latin-regular.ttf");
+        assert_eq!(
+            Font::from_path(&proportional, FontSynthesis::default()).err(),
+            Some(FontError::NotMonospace(proportional.display().to_string()))
+        );
+        assert!(matches!(
+            Font::from_path(
+                Path::new("rio-font-that-does-not-exist.ttf"),
+                FontSynthesis::default()
+            ),
+            Err(FontError::FontDataUnavailable(_))
+        ));
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn glyphs_are_covered_by_any_face() {
+        let font =
+            Font::new(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+
+        assert!(font.covers('a'));
+        assert!(font.covers('\u{1F600}'));
+        // Arial Unicode MS has CJK glyphs, the bundled fonts don't.
+        assert_eq!(font.covers('\u{4E2D}'), cfg!(target_os = "macos"));
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn styles_are_loaded_from_their_own_families() {
+        let (Ok(mono), Ok(serif_italic), Ok(serif)) = (
+            Font::load_face("DejaVu Sans Mono", false, false),
+            Font::load_face("DejaVu Serif", true, false),
+            Font::load_face("DejaVu Serif", false, false),
+        ) else {
+            eprintln!("skipped, DejaVu fonts aren't installed");
+            return;
+        };
+        let same = |a: &FontArc, b: &FontArc| {
+            let bounds = |font: &FontArc| {
+                font.outline(font.glyph_id('a'))
+                    .map(|outline| outline.bounds)
+            };
+            a.glyph_count() == b.glyph_count() && bounds(a) == bounds(b)
+        };
+
+        let font = Font::from_families(
+            FontFamilies {
+                regular: Some(String::from("DejaVu Sans Mono")),
+                bold: Some(String::from("rio-font-that-does-not-exist")),
+                italic: Some(String::from("DejaVu Serif")),
+                bold_italic: None,
+            },
+            FontSynthesis::default(),
+        )
+        .unwrap();
+        assert!(same(&font.text.regular, &mono.font));
+        assert!(same(&font.text.italic, &serif_italic.font));
+        assert!(!same(&font.text.italic, &serif.font));
+        assert!(same(&font.text.bold, &bundled_font(FONT_CASCADIAMONO_BOLD)));
+        assert!(same(
+            &font.text.bold_italic,
+            &bundled_font(FONT_CASCADIAMONO_BOLD_ITALIC)
+        ));
+    }
+
+    /// DejaVu Sans Mono with no advances.
+    struct ZeroAdvances(FontArc);
+
+    impl GFont for ZeroAdvances {
+        fn units_per_em(&self) -> Option<f32> {
+            self.0.units_per_em()
+        }
+        fn ascent_unscaled(&self) -> f32 {
+            self.0.ascent_unscaled()
+        }
+        fn descent_unscaled(&self) -> f32 {
+            self.0.descent_unscaled()
+        }
+        fn line_gap_unscaled(&self) -> f32 {
+            self.0.line_gap_unscaled()
+        }
+        fn glyph_id(&self, c: char) -> GlyphId {
+            self.0.glyph_id(c)
+        }
+        fn h_advance_unscaled(&self, _id: GlyphId) -> f32 {
+            0.
+        }
+        fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.h_side_bearing_unscaled(id)
+        }
+        fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.v_advance_unscaled(id)
+        }
+        fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.v_side_bearing_unscaled(id)
+        }
+        fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+            self.0.kern_unscaled(first, second)
         }
+        fn outline(&self, id: GlyphId) -> Option<Outline> {
+            self.0.outline(id)
+        }
+        fn glyph_count(&self) -> usize {
+            self.0.glyph_count()
+        }
+        fn codepoint_ids(&self) -> CodepointIdIter<'_> {
+            self.0.codepoint_ids()
+        }
+        fn glyph_raster_image(&self, id: GlyphId, size: u16) -> Option<GlyphImage> {
+            self.0.glyph_raster_image(id, size)
+        }
+    }
+
+    #[test]
+    fn cell_metrics_of_a_monospace_font() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/font/resources");
+        let font = Font::from_path(
+            &resources.join("DejaVuSansMono.ttf"),
+            FontSynthesis::default(),
+        )
+        .unwrap();
+        let metrics = font.cell_metrics(16.);
+        let scaled = font.text.regular.as_scaled(16.);
+        assert_eq!(metrics.advance, scaled.h_advance(scaled.glyph_id('0')));
+        // The pixel size is the height from descent to ascent, like the
+        // scale of the text.
+        assert_eq!(metrics.ascent - metrics.descent, 16.);
+        assert!(metrics.advance > 0. && metrics.descent < 0.);
+        assert_eq!(
+            metrics.line_height,
+            metrics.ascent - metrics.descent + scaled.line_gap()
+        );
+        assert_eq!(font.cell_metrics(32.).advance, metrics.advance * 2.);
+
+        // Without glyph advances the widest advance of the face gives the
+        // width.
+        let regular = &font.text.faces[0];
+        let zero_width = regular
+            .with_font(FontArc::new(ZeroAdvances(regular.font.clone())))
+            .cell_metrics(16.);
+        assert_eq!(zero_width.advance, metrics.advance);
+        assert_eq!(zero_width.line_height, metrics.line_height);
+    }
+
+    #[test]
+    fn bold_face_is_the_closest_to_bold() {
+        let weight = |weight| Properties {
+            weight,
+            ..Properties::default()
+        };
+        let italic = Properties {
+            weight: Weight::BOLD,
+            style: Style::Italic,
+            ..Properties::default()
+        };
+        assert_eq!(bold_face(&[Properties::default(), italic], false), None);
+        assert_eq!(bold_face(&[Properties::default(), italic], true), Some(1));
+        assert_eq!(
+            bold_face(
+                &[weight(Weight::SEMIBOLD), italic, weight(Weight::BOLD)],
+                false
+            ),
+            Some(2)
+        );
+        assert_eq!(bold_face(&[weight(Weight::BLACK)], false), Some(0));
+
+        let regular_italic = Properties {
+            style: Style::Oblique,
+            ..Properties::default()
+        };
+        assert_eq!(italic_face(&[italic, regular_italic]), Some(1));
+        assert_eq!(italic_face(&[Properties::default()]), None);
+    }
+
+    #[test]
+    fn weights_map_to_the_closest_face() {
+        let weight = |weight| Properties {
+            weight: Weight(weight),
+            ..Properties::default()
+        };
+        // Every weight of a family like Inter.
+        let family: Vec<Properties> = (1..=9).map(|n| weight(n as f32 * 100.)).collect();
+        assert_eq!(regular_face(&family), Some(3));
+        assert_eq!(bold_face(&family, false), Some(6));
+
+        // Weights in between, or at the ends of the range.
+        assert_eq!(regular_face(&[weight(350.), weight(200.)]), Some(0));
+        assert_eq!(regular_face(&[weight(100.)]), Some(0));
+        assert_eq!(bold_face(&[weight(350.), weight(900.)], false), Some(1));
+        assert_eq!(bold_face(&[weight(500.)], false), None);
+        assert_eq!(regular_face(&[weight(550.)]), None);
+        assert_eq!(bold_face(&[weight(550.)], false), Some(0));
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn features_apply_to_every_text_face() {
+        let mut font =
+            Font::load(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+        let zero = |font: &Font| {
+            [
+                &font.text.regular,
+                &font.text.bold,
+                &font.text.italic,
+                &font.text.bold_italic,
+            ]
+            .map(|face| face.glyph_id('0'))
+        };
+        let plain = zero(&font);
+
+        font.set_features(&[("zero", 1)]);
+        let slashed = zero(&font);
+        for (slashed, plain) in slashed.iter().zip(plain) {
+            assert_ne!(*slashed, plain);
+        }
+
+        // Features replace the previous ones instead of adding up.
+        font.set_features(&[]);
+        assert_eq!(zero(&font), plain);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn typographic_center_of_bundled_fonts() {
+        // Cascadia Mono has the same typographic and line metrics.
+        assert_eq!(typographic_center(FONT_CASCADIAMONO_REGULAR, 0), Some(0.));
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/font/resources");
+        let dejavu = std::fs::read(resources.join("DejaVuSansMono.ttf")).unwrap();
+        let center = typographic_center(&dejavu, 0).unwrap();
+        // (1901 - 483 - 1556 + 492) / 2 / (1901 + 483)
+        assert!((center - 0.0742).abs() < 0.001, "{center}");
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn features_are_read_only_when_requested() {
+        let font =
+            Font::load(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+        let face = font.text.faces[0].clone();
+        assert!(face.features.get().is_none());
+
+        face.with_features(&[]);
+        assert!(face.features.get().is_none());
+
+        face.with_features(&[("zero", 1)]);
+        assert!(face.features.get().is_some());
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn synthetic_bold_is_based_on_the_regular_face() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/font/resources");
+        let regular = font_file(&resources.join("DejaVuSansMono.ttf"), 0).unwrap();
+        let bold = ComposedFontArc::compose(
+            regular.clone(),
+            None,
+            None,
+            None,
+            FontSynthesis::default(),
+        )
+        .bold;
+        let regular = regular.font;
+        let id = regular.glyph_id('l');
+        assert_eq!(bold.glyph_id('l'), id);
+        assert_eq!(bold.h_advance_unscaled(id), regular.h_advance_unscaled(id));
+        let cascadia = bundled_font(FONT_CASCADIAMONO_BOLD);
+        assert_ne!(
+            bold.h_advance_unscaled(id) / bold.units_per_em().unwrap(),
+            cascadia.h_advance_unscaled(cascadia.glyph_id('l'))
+                / cascadia.units_per_em().unwrap()
+        );
+
+        // Rendered, the emboldened glyph covers more pixels.
+        let coverage = |font: &FontArc| {
+            let mut coverage = 0.;
+            font.outline_glyph(id.with_scale(32.))
+                .unwrap()
+                .draw(|_, _, alpha| coverage += alpha);
+            coverage
+        };
+        assert!(coverage(&bold) > coverage(&regular) * 1.2);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn bundled_emojis_have_outlines() {
+        assert!(has_emoji_outlines(&bundled_font(FONT_EMOJI)));
+        // They are monochrome, tinted like text.
+        assert!(!bundled_face(FONT_EMOJI).has_colors());
+        // Cascadia has no emojis at all.
+        assert!(!has_emoji_outlines(&bundled_font(
+            FONT_CASCADIAMONO_REGULAR
+        )));
+    }
+
+    #[test]
+    fn font_error_describes_missing_faces() {
+        let error = FontError::MissingRegularFace {
+            family: String::from("Mono"),
+            found: vec![String::from("Mono Bold"), String::from("Mono Italic")],
+        };
+        assert_eq!(
+            error.to_string(),
+            "font Mono has no regular face (found: Mono Bold, Mono Italic)"
+        );
+        let error = FontError::MissingStyle {
+            family: String::from("Mono"),
+            style: String::from("bold italic"),
+        };
+        assert_eq!(error.to_string(), "font Mono has no bold italic face");
+    }
+
+    #[test]
+    fn family_without_regular_face_is_rejected() {
+        let bold = Properties {
+            weight: Weight::BOLD,
+            ..Properties::default()
+        };
+        let italic = Properties {
+            style: Style::Italic,
+            ..Properties::default()
+        };
+        assert_eq!(regular_face(&[bold]), None);
+        assert_eq!(
+            regular_face(&[bold, italic, Properties::default()]),
+            Some(2)
+        );
     }
 }
@@ -0,0 +1,337 @@
+use glyph_brush::ab_glyph::{
+    point, CodepointIdIter, Font, FontArc, GlyphId, GlyphImage, Outline, OutlineCurve,
+    Point, Rect,
+};
+
+/// Outlines of synthetic bold grow by this much on each side, in ems, like
+/// FreeType's emboldening.
+pub const DEFAULT_BOLD_STRENGTH: f32 = 1. / 48.;
+
+/// Slant of synthetic italics, in degrees.
+pub const DEFAULT_ITALIC_SLANT: f32 = 12.;
+
+/// Slants past this many degrees are clamped, glyphs would overlap the next
+/// cell too much.
+pub const MAX_ITALIC_SLANT: f32 = 20.;
+
+/// Implements `Font` for a wrapper of `self.font`, everything but the
+/// outlines comes from the wrapped font so glyphs keep fitting the grid.
+macro_rules! wrap_font {
+    ($wrapper:ty) => {
+        impl Font for $wrapper {
+            fn units_per_em(&self) -> Option<f32> {
+                self.font.units_per_em()
+            }
+
+            fn ascent_unscaled(&self) -> f32 {
+                self.font.ascent_unscaled()
+            }
+
+            fn descent_unscaled(&self) -> f32 {
+                self.font.descent_unscaled()
+            }
+
+            fn line_gap_unscaled(&self) -> f32 {
+                self.font.line_gap_unscaled()
+            }
+
+            fn glyph_id(&self, c: char) -> GlyphId {
+                self.font.glyph_id(c)
+            }
+
+            fn h_advance_unscaled(&self, id: GlyphId) -> f32 {
+                self.font.h_advance_unscaled(id)
+            }
+
+            fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+                self.font.h_side_bearing_unscaled(id)
+            }
+
+            fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+                self.font.v_advance_unscaled(id)
+            }
+
+            fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+                self.font.v_side_bearing_unscaled(id)
+            }
+
+            fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+                self.font.kern_unscaled(first, second)
+            }
+
+            fn outline(&self, id: GlyphId) -> Option<Outline> {
+                self.font.outline(id).map(|outline| self.transform(outline))
+            }
+
+            fn glyph_count(&self) -> usize {
+                self.font.glyph_count()
+            }
+
+            fn codepoint_ids(&self) -> CodepointIdIter<'_> {
+                self.font.codepoint_ids()
+            }
+
+            fn glyph_raster_image(&self, id: GlyphId, size: u16) -> Option<GlyphImage> {
+                self.font.glyph_raster_image(id, size)
+            }
+        }
+    };
+}
+
+/// Regular glyphs with thickened outlines, standing in for a family without
+/// a bold face.
+pub struct SyntheticBold {
+    font: FontArc,
+    strength: f32,
+}
+
+impl SyntheticBold {
+    /// `strength` is how much each side of the strokes grows, in ems.
+    pub fn new(font: FontArc, strength: f32) -> SyntheticBold {
+        SyntheticBold { font, strength }
+    }
+
+    fn transform(&self, outline: Outline) -> Outline {
+        let units_per_em = self.font.units_per_em().unwrap_or(1000.);
+        embolden(outline, self.strength * units_per_em)
+    }
+}
+
+wrap_font!(SyntheticBold);
+
+/// Upright glyphs sheared to the right (a faux oblique), standing in for a
+/// family without an italic face.
+pub struct SyntheticItalic {
+    font: FontArc,
+    slant: f32,
+}
+
+impl SyntheticItalic {
+    /// `slant` is the angle from the vertical in degrees, it's clamped
+    /// between 0 and `MAX_ITALIC_SLANT`.
+    pub fn new(font: FontArc, slant: f32) -> SyntheticItalic {
+        SyntheticItalic {
+            font,
+            slant: slant.clamp(0., MAX_ITALIC_SLANT),
+        }
+    }
+
+    fn transform(&self, outline: Outline) -> Outline {
+        let shear = self.slant.to_radians().tan();
+        map_points(outline, |p| point(p.x + p.y * shear, p.y))
+    }
+}
+
+wrap_font!(SyntheticItalic);
+
+/// Points of a curve, without its end which starts the next curve.
+fn curve_points(curve: &OutlineCurve) -> Vec<Point> {
+    match *curve {
+        OutlineCurve::Line(p0, _) => vec![p0],
+        OutlineCurve::Quad(p0, p1, _) => vec![p0, p1],
+        OutlineCurve::Cubic(p0, p1, p2, _) => vec![p0, p1, p2],
+    }
+}
+
+fn curve_end(curve: &OutlineCurve) -> Point {
+    match *curve {
+        OutlineCurve::Line(_, end)
+        | OutlineCurve::Quad(_, _, end)
+        | OutlineCurve::Cubic(_, _, _, end) => end,
+    }
+}
+
+/// Consecutive curves that form a closed contour.
+fn contours(curves: &[OutlineCurve]) -> Vec<&[OutlineCurve]> {
+    let mut contours = vec![];
+    let mut start = 0;
+    for index in 1..=curves.len() {
+        if index == curves.len()
+            || curve_end(&curves[index - 1]) != curve_points(&curves[index])[0]
+        {
+            contours.push(&curves[start..index]);
+            start = index;
+        }
+    }
+    contours
+}
+
+fn scale(vector: Point, factor: f32) -> Point {
+    point(vector.x * factor, vector.y * factor)
+}
+
+fn normalize(vector: Point) -> Point {
+    scale(
+        vector,
+        1. / (vector.x * vector.x + vector.y * vector.y).sqrt(),
+    )
+}
+
+/// Moves every point of the contours outwards by `strength` font units,
+/// along the bisector of its neighbouring edges.
+fn embolden(outline: Outline, strength: f32) -> Outline {
+    let contours_curves = contours(&outline.curves);
+    let contours: Vec<Vec<Point>> = contours_curves
+        .iter()
+        .map(|curves| curves.iter().flat_map(curve_points).collect())
+        .collect();
+
+    // Outer contours go clockwise in TrueType and counterclockwise in CFF,
+    // holes the other way around, so the sign of the total area tells
+    // which side is outside.
+    let area: f32 = contours
+        .iter()
+        .flat_map(|points| {
+            points
+                .iter()
+                .zip(points.iter().cycle().skip(1))
+                .map(|(current, next)| current.x * next.y - next.x * current.y)
+        })
+        .sum();
+    let outwards = if area > 0. { 1. } else { -1. };
+
+    let mut moved = vec![];
+    for points in &contours {
+        let count = points.len();
+        let distinct = |from: usize, step: usize| {
+            (1..count)
+                .map(|offset| points[(from + offset * step) % count])
+                .find(|point| *point != points[from])
+        };
+
+        for (index, current) in points.iter().enumerate() {
+            let (Some(previous), Some(next)) =
+                (distinct(index, count - 1), distinct(index, 1))
+            else {
+                moved.push(*current);
+                continue;
+            };
+
+            let incoming = normalize(*current - previous);
+            let outgoing = normalize(next - *current);
+            let normal = scale(
+                point(incoming.y + outgoing.y, -(incoming.x + outgoing.x)),
+                outwards,
+            );
+            // Sharp corners would move far away, they are limited to four
+            // times the strength.
+            let cosine = incoming.x * outgoing.x + incoming.y * outgoing.y;
+            let shift = scale(normal, strength / (1. + cosine).max(0.125));
+            moved.push(*current + shift);
+        }
+    }
+
+    let mut curves = Vec::with_capacity(outline.curves.len());
+    let mut offset = 0;
+    for (contour, points) in contours_curves.iter().zip(&contours) {
+        let moved = &moved[offset..offset + points.len()];
+        let mut index = 0;
+        for curve in contour.iter() {
+            let at = |offset: usize| moved[(index + offset) % moved.len()];
+            curves.push(match curve {
+                OutlineCurve::Line(..) => OutlineCurve::Line(at(0), at(1)),
+                OutlineCurve::Quad(..) => OutlineCurve::Quad(at(0), at(1), at(2)),
+                OutlineCurve::Cubic(..) => {
+                    OutlineCurve::Cubic(at(0), at(1), at(2), at(3))
+                }
+            });
+            index += curve_points(curve).len();
+        }
+        offset += points.len();
+    }
+
+    with_bounds(curves)
+}
+
+/// Outline with every point of the curves moved by `map`.
+fn map_points(outline: Outline, map: impl Fn(Point) -> Point) -> Outline {
+    let curves = outline
+        .curves
+        .iter()
+        .map(|curve| match *curve {
+            OutlineCurve::Line(p0, p1) => OutlineCurve::Line(map(p0), map(p1)),
+            OutlineCurve::Quad(p0, p1, p2) => {
+                OutlineCurve::Quad(map(p0), map(p1), map(p2))
+            }
+            OutlineCurve::Cubic(p0, p1, p2, p3) => {
+                OutlineCurve::Cubic(map(p0), map(p1), map(p2), map(p3))
+            }
+        })
+        .collect();
+    with_bounds(curves)
+}
+
+/// Outline of `curves`, which stay inside of their control points. Unscaled
+/// bounds go from the top left to the bottom right, with y going up.
+fn with_bounds(curves: Vec<OutlineCurve>) -> Outline {
+    let mut bounds = Rect {
+        min: point(f32::MAX, f32::MIN),
+        max: point(f32::MIN, f32::MAX),
+    };
+    for curve in &curves {
+        for point in curve_points(curve).into_iter().chain([curve_end(curve)]) {
+            bounds.min.x = bounds.min.x.min(point.x);
+            bounds.min.y = bounds.min.y.max(point.y);
+            bounds.max.x = bounds.max.x.max(point.x);
+            bounds.max.y = bounds.max.y.min(point.y);
+        }
+    }
+
+    Outline { bounds, curves }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(clockwise: bool) -> Outline {
+        let mut corners = vec![
+            point(0., 0.),
+            point(0., 10.),
+            point(10., 10.),
+            point(10., 0.),
+        ];
+        if !clockwise {
+            corners.reverse();
+        }
+        let curves = (0..4)
+            .map(|index| OutlineCurve::Line(corners[index], corners[(index + 1) % 4]))
+            .collect();
+        Outline {
+            bounds: Rect {
+                min: point(0., 10.),
+                max: point(10., 0.),
+            },
+            curves,
+        }
+    }
+
+    #[test]
+    fn embolden_grows_outlines_of_both_windings() {
+        for clockwise in [true, false] {
+            let outline = embolden(square(clockwise), 1.);
+            assert_eq!(outline.curves.len(), 4);
+            assert_eq!(outline.bounds.min, point(-1., 11.));
+            assert_eq!(outline.bounds.max, point(11., -1.));
+            for curve in &outline.curves {
+                let OutlineCurve::Line(start, end) = curve else { unreachable!() };
+                assert!(start.x == end.x || start.y == end.y);
+            }
+        }
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn italic_shears_to_the_right() {
+        let font =
+            FontArc::try_from_slice(crate::font::FONT_CASCADIAMONO_REGULAR).unwrap();
+        let italic = SyntheticItalic::new(font, 45.);
+        let outline = italic.transform(square(true));
+        // Clamped to the maximum slant.
+        let shift = 10. * MAX_ITALIC_SLANT.to_radians().tan();
+        assert_eq!(outline.bounds.min, point(0., 10.));
+        assert_eq!(outline.bounds.max, point(10. + shift, 0.));
+        let OutlineCurve::Line(bottom, top) = outline.curves[0] else { unreachable!() };
+        assert_eq!((bottom, top), (point(0., 0.), point(shift, 10.)));
+    }
+}
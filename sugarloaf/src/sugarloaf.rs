@@ -1,10 +1,16 @@
+use crate::components::blit::BlitBrush;
 use crate::components::rect::{Rect, RectBrush};
-use crate::components::text;
+use crate::components::text::{self, TextRenderer};
 use crate::context::Context;
-use crate::core::{SugarStack, SugarloafStyle};
-use crate::font::Font;
+use crate::core::{Sugar, SugarDecoration, SugarStack, SugarloafStyle};
+use crate::font::{Font, FontSynthesis};
+use crate::tools::thumbnail;
 use glyph_brush::ab_glyph::{self, Font as GFont, FontArc};
-use glyph_brush::{FontId, GlyphCruncher, OwnedSection, OwnedText};
+use glyph_brush::{FontId, OwnedSection, OwnedText};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
 #[cfg(target_arch = "wasm32")]
 use web_sys::{ImageBitmapRenderingContext, OffscreenCanvas};
 
@@ -62,26 +68,356 @@ pub trait Renderable: 'static + Sized {
     );
 }
 
+/// Rounds a position in logical units to the nearest physical pixel.
+#[inline]
+fn snap_to_pixel(value: f32, scale: f32) -> f32 {
+    (value * scale).round() / scale
+}
+
+/// Extra width for a cell background so it overlaps the next one when both
+/// share the same color, hiding seams left by fractional positions.
+#[inline]
+fn background_bleed(sugar: &Sugar, next: Option<&Sugar>, bleed: f32) -> f32 {
+    match next {
+        Some(next) if next.background_color == sugar.background_color => bleed,
+        _ => 0.0,
+    }
+}
+
+/// Adds the `background` of a cell to `rects`. When it has the color of the
+/// previous cell it grows that cell's rect, at `run`, instead, so a row of
+/// same colored cells is a single quad. Backgrounds of the `clear` color
+/// aren't drawn, the frame already has it. Returns where the run is.
+fn push_background(
+    rects: &mut Vec<Rect>,
+    run: Option<usize>,
+    background: Rect,
+    clear: [f32; 4],
+) -> Option<usize> {
+    if background.color == clear {
+        return None;
+    }
+    if let Some(run) = run.filter(|run| rects[*run].color == background.color) {
+        // Quads cover half their size, so the run grows twice the distance
+        // between the cells to end where the last one does.
+        let rect = &mut rects[run];
+        rect.size[0] =
+            2. * (background.position[0] - rect.position[0]) + background.size[0];
+        return Some(run);
+    }
+    rects.push(background);
+    Some(rects.len() - 1)
+}
+
+/// Font, by id, that draws `c`. Characters in `force_text_font_ranges` are
+/// checked against the text font before any fallback, when it doesn't have
+/// the glyph they go through the usual fallback order.
+fn glyph_source(
+    fonts: &[FontArc],
+    c: char,
+    force_text_font_ranges: &[RangeInclusive<u32>],
+) -> usize {
+    let glyph_zero = ab_glyph::GlyphId(0);
+    let covers = |font_id: usize| fonts[font_id].glyph_id(c) != glyph_zero;
+
+    if force_text_font_ranges
+        .iter()
+        .any(|range| range.contains(&(c as u32)))
+        && covers(FONT_ID_REGULAR)
+    {
+        return FONT_ID_REGULAR;
+    }
+
+    [
+        FONT_ID_REGULAR,
+        FONT_ID_SYMBOL,
+        FONT_ID_EMOJIS,
+        FONT_ID_UNICODE,
+    ]
+    .into_iter()
+    .chain(FONT_ID_FALLBACK..fonts.len())
+    .find(|font_id| covers(*font_id))
+    .unwrap_or(FONT_ID_REGULAR)
+}
+
+/// Character drawn for `c`: `replacement` stands in for a char that no font
+/// covers, and nothing is drawn if it isn't covered either. Without a
+/// replacement the missing glyph of the text font is drawn.
+fn displayed_char(font: &Font, c: char, replacement: Option<char>) -> char {
+    let Some(replacement) = replacement else { return c };
+
+    if font.covers(c) {
+        c
+    } else if font.covers(replacement) {
+        replacement
+    } else {
+        ' '
+    }
+}
+
+/// Quad of a decoration of the cell at `cell` with `cell_size`.
+fn decoration_rect(
+    decoration: &SugarDecoration,
+    cell: [f32; 2],
+    cell_size: [f32; 2],
+    mod_size: f32,
+    scale: f32,
+) -> Rect {
+    let [dx, dy] = cell_size;
+    let width = match decoration.pixel_width {
+        // Quads cover half their size plus half a pixel on the screen.
+        Some(pixel_width) => 2. * pixel_width - 1. / scale,
+        None => (dx * decoration.size.0) * mod_size,
+    };
+    let (y, height) = match decoration.pixel_height {
+        // Quads cover their height plus a pixel, starting half a pixel up.
+        Some(pixel_height) => (
+            dy * (decoration.position.1 + decoration.size.1) - pixel_height + 1. / scale,
+            pixel_height - 1. / scale,
+        ),
+        None => (
+            dy * decoration.position.1,
+            (dy * decoration.size.1) * mod_size,
+        ),
+    };
+
+    Rect {
+        position: [cell[0] + dx * decoration.position.0 / scale, cell[1] + y],
+        color: decoration.color,
+        size: [width, height],
+    }
+}
+
 type FontBound = (f32, f32);
 
+/// Section for one row of cells, glyphs follow each other on a single line
+/// so with a monospace font each lands on its column.
+fn row_section(
+    text: Vec<OwnedText>,
+    screen_position: (f32, f32),
+    bounds: (f32, f32),
+) -> OwnedSection {
+    OwnedSection {
+        screen_position,
+        bounds,
+        text,
+        layout: glyph_brush::Layout::default_single_line()
+            .v_align(glyph_brush::VerticalAlign::Bottom),
+    }
+}
+
+/// Whether a frame the surface couldn't give is skipped after configuring
+/// the surface again (`true`) or just skipped. Running out of memory is the
+/// only error rendering can't go on after.
+fn skip_frame(error: wgpu::SurfaceError) -> Result<bool, wgpu::SurfaceError> {
+    match error {
+        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => Ok(true),
+        wgpu::SurfaceError::Timeout => Ok(false),
+        wgpu::SurfaceError::OutOfMemory => Err(error),
+    }
+}
+
+/// Buffer `texture` is copied into to be read back, with the length of its
+/// rows, which have to be aligned.
+fn copy_to_buffer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> (wgpu::Buffer, u32) {
+    let size = texture.size();
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let bytes_per_row = (size.width * 4 + align - 1) / align * align;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("sugarloaf::read_rgba -> Pixels"),
+        size: (bytes_per_row * size.height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    queue.submit(Some(encoder.finish()));
+    (buffer, bytes_per_row)
+}
+
+/// RGBA rows without padding of `texture` from the mapped `buffer` it was
+/// copied into.
+fn mapped_rgba(
+    texture: &wgpu::Texture,
+    buffer: &wgpu::Buffer,
+    bytes_per_row: u32,
+) -> Vec<u8> {
+    let size = texture.size();
+    let unpadded_bytes_per_row = (size.width * 4) as usize;
+    let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * size.height as usize);
+    for row in buffer
+        .slice(..)
+        .get_mapped_range()
+        .chunks(bytes_per_row as usize)
+    {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+    }
+    buffer.unmap();
+
+    if matches!(
+        texture.format(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    pixels
+}
+
+/// Pixels of `texture` as RGBA rows without padding, it waits for the gpu
+/// to finish. `None` when the texture can't be read.
+fn read_rgba(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Option<Vec<u8>> {
+    let (buffer, bytes_per_row) = copy_to_buffer(device, queue, texture);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    device.poll(wgpu::Maintain::Wait);
+    if !matches!(receiver.recv(), Ok(Ok(()))) {
+        return None;
+    }
+    Some(mapped_rgba(texture, &buffer, bytes_per_row))
+}
+
+/// Like `read_rgba`, but the gpu is polled each time the future is instead
+/// of waiting for it, so the executor isn't blocked.
+async fn read_rgba_async(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Option<Vec<u8>> {
+    let (buffer, bytes_per_row) = copy_to_buffer(device, queue, texture);
+    let mapped = Arc::new(Mutex::new(None));
+    let result = mapped.clone();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |map_result| {
+            *result.lock().unwrap() = Some(map_result);
+        });
+    std::future::poll_fn(|context| {
+        device.poll(wgpu::Maintain::Poll);
+        match mapped.lock().unwrap().take() {
+            Some(map_result) => Poll::Ready(map_result.ok()),
+            None => {
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    })
+    .await?;
+    Some(mapped_rgba(texture, &buffer, bytes_per_row))
+}
+
+/// Receives the RGBA pixels, width and height of every presented frame.
+type FrameCallback = Box<dyn FnMut(&[u8], u32, u32)>;
+
 #[derive(Default)]
 struct FontBounds {
+    // Text scale used to calculate the bounds
+    text_scale: f32,
     default: FontBound,
     symbols: FontBound,
     emojis: FontBound,
     unicode: FontBound,
+    /// Physical pixels glyphs are moved down inside their line.
+    baseline_offset: f32,
+}
+
+/// Bounds measured for each font and text scale, so going back to a scale
+/// (e.g. after zooming) doesn't lay out glyphs again. It has to be cleared
+/// when the fonts change.
+#[derive(Default)]
+struct MetricsCache {
+    bounds: HashMap<(usize, u32), FontBound>,
+    #[cfg(debug_assertions)]
+    hits: usize,
+}
+
+impl MetricsCache {
+    fn get(&mut self, font_id: usize, scale: f32) -> Option<FontBound> {
+        let bound = self.bounds.get(&(font_id, scale.to_bits())).copied();
+        #[cfg(debug_assertions)]
+        if bound.is_some() {
+            self.hits += 1;
+        }
+        bound
+    }
+
+    fn insert(&mut self, font_id: usize, scale: f32, bound: FontBound) {
+        self.bounds.insert((font_id, scale.to_bits()), bound);
+    }
+
+    fn clear(&mut self) {
+        self.bounds.clear();
+    }
+}
+
+/// Tracks the first presented frame and runs its one-shot callback.
+#[derive(Default)]
+struct FirstFrame {
+    rendered: bool,
+    callback: Option<Box<dyn FnOnce()>>,
+}
+
+impl FirstFrame {
+    fn presented(&mut self) {
+        self.rendered = true;
+        if let Some(callback) = self.callback.take() {
+            callback();
+        }
+    }
 }
 
 pub struct Sugarloaf {
     pub ctx: Context,
-    text_brush: text::GlyphBrush<()>,
+    text_brush: Box<dyn TextRenderer>,
     rect_brush: RectBrush,
     rects: Vec<Rect>,
     acc_line: f32,
     acc_line_y: f32,
     initial_scale: f32,
     font_bounds: FontBounds,
+    metrics: MetricsCache,
     background_color: wgpu::Color,
+    baseline_offset: Option<f32>,
+    snap_to_pixel: bool,
+    first_frame: FirstFrame,
+    background_bleed: f32,
+    overlay: Option<[f32; 4]>,
+    force_text_font_ranges: Vec<RangeInclusive<u32>>,
+    synthesis: FontSynthesis,
+    font: Font,
+    font_features: Vec<(String, u32)>,
+    missing_glyph_replacement: Option<char>,
+    smooth_resize: bool,
+    last_frame: Option<wgpu::Texture>,
+    frame_callback: Option<FrameCallback>,
+    last_frame_draw_calls: u32,
+    blit_brush: BlitBrush,
 }
 
 const FONT_ID_REGULAR: usize = 0;
@@ -91,6 +427,14 @@ const FONT_ID_UNICODE: usize = 3;
 const FONT_ID_BOLD: usize = 4;
 const FONT_ID_ITALIC: usize = 5;
 const FONT_ID_BOLD_ITALIC: usize = 6;
+const FONT_ID_FALLBACK: usize = 7;
+
+/// Color emojis aren't tinted by the foreground color of their run, only its
+/// opacity applies. Glyphs are drawn from their coverage, so the color of an
+/// emoji is the untinted (white) glyph.
+fn emoji_color(foreground_color: [f32; 4]) -> [f32; 4] {
+    [1.0, 1.0, 1.0, foreground_color[3]]
+}
 
 impl Sugarloaf {
     pub async fn new(
@@ -98,21 +442,43 @@ impl Sugarloaf {
         power_preference: wgpu::PowerPreference,
         font_name: String,
     ) -> Result<Sugarloaf, String> {
+        let font = Font::new(font_name, FontSynthesis::default())
+            .map_err(|error| error.to_string())?;
+        Ok(Self::with_font(winit_window, power_preference, font).await)
+    }
+
+    /// Like `new`, with a font that is already loaded (e.g. with fallbacks or
+    /// synthesized faces). Its regular, bold, italic and bold italic faces
+    /// are the ones bold and italic sugars are drawn with.
+    pub async fn with_font(
+        winit_window: &winit::window::Window,
+        power_preference: wgpu::PowerPreference,
+        font: Font,
+    ) -> Sugarloaf {
         let ctx = Context::new(winit_window, power_preference).await;
+        Self::from_context(ctx, font)
+    }
 
-        let font = Font::new(font_name);
-        let text_brush = text::GlyphBrushBuilder::using_fonts(vec![
-            font.text.regular,
-            font.symbol,
-            font.emojis,
-            font.unicode,
-            font.text.bold,
-            font.text.italic,
-            font.text.bold_italic,
-        ])
-        .build(&ctx.device, ctx.format);
+    /// A sugarloaf without a window, frames of `width` x `height` physical
+    /// pixels are drawn offscreen and read with `last_frame_rgba`. It's for
+    /// rendering in tests.
+    pub async fn headless(
+        width: u32,
+        height: u32,
+        scale: f32,
+        font: Font,
+    ) -> Result<Sugarloaf, String> {
+        let size = winit::dpi::PhysicalSize::new(width, height);
+        let ctx =
+            Context::headless(size, scale, wgpu::PowerPreference::default()).await?;
+        Ok(Self::from_context(ctx, font))
+    }
+
+    fn from_context(ctx: Context, font: Font) -> Sugarloaf {
+        let text_brush = Self::build_text_brush(&ctx, font.clone());
         let rect_brush = RectBrush::init(&ctx);
-        Ok(Sugarloaf {
+        let blit_brush = BlitBrush::new(&ctx.device, ctx.format);
+        Sugarloaf {
             initial_scale: ctx.scale,
             ctx,
             rect_brush,
@@ -121,52 +487,398 @@ impl Sugarloaf {
             acc_line: 0.0,
             acc_line_y: 0.0,
             font_bounds: FontBounds::default(),
+            metrics: MetricsCache::default(),
             background_color: wgpu::Color::BLACK,
-        })
+            baseline_offset: None,
+            snap_to_pixel: true,
+            first_frame: FirstFrame::default(),
+            background_bleed: 0.5,
+            overlay: None,
+            force_text_font_ranges: vec![],
+            synthesis: FontSynthesis::default(),
+            font,
+            font_features: vec![],
+            missing_glyph_replacement: None,
+            smooth_resize: false,
+            last_frame: None,
+            frame_callback: None,
+            last_frame_draw_calls: 0,
+            blit_brush,
+        }
+    }
+
+    fn build_text_brush(ctx: &Context, font: Font) -> Box<dyn TextRenderer> {
+        Self::glyph_brush(&ctx.device, ctx.format, Self::brush_fonts(font))
+    }
+
+    /// Fonts of `font` in the order the renderer expects them.
+    fn brush_fonts(font: Font) -> Vec<FontArc> {
+        let mut fonts = vec![
+            font.text.regular,
+            font.symbol,
+            font.emojis,
+            font.unicode,
+            font.text.bold,
+            font.text.italic,
+            font.text.bold_italic,
+        ];
+        fonts.extend(font.fallback);
+        fonts
+    }
+
+    fn glyph_brush(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        fonts: Vec<FontArc>,
+    ) -> Box<dyn TextRenderer> {
+        Box::new(text::GlyphBrushBuilder::using_fonts(fonts).build(device, format))
+    }
+
+    /// The graphics device was lost (e.g. the driver reset or the machine
+    /// resumed from sleep), frames aren't drawn until `recover` is called.
+    pub fn is_device_lost(&self) -> bool {
+        self.ctx.is_device_lost()
+    }
+
+    /// The window has no area (e.g. it's minimized), frames are skipped
+    /// until it's resized again.
+    pub fn is_minimized(&self) -> bool {
+        self.ctx.is_minimized()
+    }
+
+    /// Recreates the device and everything on it: pipelines, glyph cache and
+    /// the last frame. Fonts are kept, a custom text renderer is replaced by
+    /// a `GlyphBrush` with its fonts. What was drawn has to be stacked again.
+    pub async fn recover(&mut self) -> Result<(), String> {
+        self.ctx.recreate_device().await?;
+        let fonts = self.text_brush.fonts().to_vec();
+        self.text_brush = Self::glyph_brush(&self.ctx.device, self.ctx.format, fonts);
+        self.rect_brush = RectBrush::init(&self.ctx);
+        self.blit_brush = BlitBrush::new(&self.ctx.device, self.ctx.format);
+        self.last_frame = None;
+        self.rects = vec![];
+        self.font_bounds = FontBounds::default();
+        Ok(())
+    }
+
+    /// Present modes the window supports on the current adapter, e.g. for a
+    /// settings UI to offer only those.
+    pub fn supported_present_modes(&self) -> Vec<wgpu::PresentMode> {
+        self.ctx.supported_present_modes()
+    }
+
+    /// Presents frames with `mode`, one missing from
+    /// `supported_present_modes` falls back to `AutoVsync`. Returns the mode
+    /// in use.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        self.ctx.set_present_mode(mode)
     }
 
+    /// Replaces the text renderer, by default text is drawn with `GlyphBrush`.
+    /// Fonts must follow the same order as the default one (regular, symbol,
+    /// emojis, unicode, bold, italic and bold italic), followed by the
+    /// fallbacks.
     #[allow(unused)]
-    pub fn clear(&mut self) {
-        match self.ctx.surface.get_current_texture() {
-            Ok(frame) => {
-                let mut encoder = self.ctx.device.create_command_encoder(
-                    &wgpu::CommandEncoderDescriptor { label: None },
-                );
-
-                let view = &frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
-
-                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("sugarloaf::init -> Clear frame"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(self.background_color),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
-                self.ctx.staging_belt.finish();
-                self.ctx.queue.submit(Some(encoder.finish()));
-                frame.present();
-                self.ctx.staging_belt.recall();
-            }
+    pub fn set_text_renderer(&mut self, renderer: Box<dyn TextRenderer>) -> &mut Self {
+        self.text_brush = renderer;
+        // Bounds are recalculated on next stack
+        self.font_bounds = FontBounds::default();
+        self.metrics.clear();
+        self
+    }
+
+    /// Replaces the current font, with `fallbacks` families tried in order
+    /// for glyphs no other font has. Unlike `set_font`, an unavailable font
+    /// is replaced by the bundled one.
+    pub fn set_font_with_fallbacks(
+        &mut self,
+        font_name: String,
+        fallbacks: Vec<String>,
+    ) -> Result<(), String> {
+        let font = Font::with_fallbacks(font_name, fallbacks, self.synthesis)
+            .map_err(|error| error.to_string())?;
+        self.replace_font(font);
+        Ok(())
+    }
+
+    /// Replaces the current font with an already loaded one, e.g. from
+    /// `Font::from_path` or `Font::from_families`, and goes back to the
+    /// default `GlyphBrush` renderer.
+    ///
+    /// The brush is rebuilt instead of growing the old one with `add_font`:
+    /// glyph_brush can't remove fonts, so the previous faces and their cached
+    /// glyphs would stay for the whole session. What was stacked since the
+    /// last render goes away with the old brush and has to be stacked again.
+    /// The OpenType features from `set_font_features` are turned on for it.
+    pub fn replace_font(&mut self, mut font: Font) {
+        let features: Vec<(&str, u32)> = self
+            .font_features
+            .iter()
+            .map(|(tag, value)| (tag.as_str(), *value))
+            .collect();
+        font.set_features(&features);
+        self.text_brush = Self::build_text_brush(&self.ctx, font.clone());
+        self.skip_stacked();
+        self.font = font;
+        // Bounds are recalculated on next stack
+        self.font_bounds = FontBounds::default();
+        self.metrics.clear();
+    }
+
+    /// Turns on the OpenType `features` of the text font, tags with a value
+    /// as in CSS `font-feature-settings`: `("zero", 1)` for a slashed zero,
+    /// `("ss02", 1)` for a stylistic set or `("zero", 0)` to turn one off.
+    /// Only features that replace single glyphs take effect, ligatures and
+    /// contextual alternates need shaping. The glyph cache starts over.
+    pub fn set_font_features(&mut self, features: &[(&str, u32)]) {
+        self.font_features = features
+            .iter()
+            .map(|(tag, value)| (tag.to_string(), *value))
+            .collect();
+        self.replace_font(self.font.clone());
+    }
+
+    /// Replaces the current font, the fallbacks of the current one are kept.
+    /// The new font is fully loaded before anything is swapped, so on error
+    /// the previous font stays in use. It always goes back to the default
+    /// `GlyphBrush` renderer.
+    #[allow(unused)]
+    pub fn set_font(&mut self, font_name: String) -> Result<(), String> {
+        let mut font =
+            Font::load(font_name, self.synthesis).map_err(|error| error.to_string())?;
+        font.fallback = self.font.fallback.clone();
+        self.replace_font(font);
+        Ok(())
+    }
+
+    /// Next frame of the window, `None` when it has to be skipped. A lost or
+    /// outdated surface is configured again so the next one works.
+    fn next_frame(&mut self) -> Result<Option<wgpu::SurfaceTexture>, wgpu::SurfaceError> {
+        let Some(surface) = &self.ctx.surface else {
+            return Ok(None);
+        };
+        match surface.get_current_texture() {
+            Ok(frame) => Ok(Some(frame)),
             Err(error) => {
-                if error == wgpu::SurfaceError::OutOfMemory {
-                    panic!("Swapchain error: {error}. Rendering cannot continue.")
+                if skip_frame(error.clone())? {
+                    log::warn!("{error}, configuring the surface again");
+                    self.ctx.resize(self.ctx.size.width, self.ctx.size.height);
                 }
+                Ok(None)
             }
         }
     }
 
+    /// Clears the window to the background color. Only running out of memory
+    /// is an error, like for `render`.
+    #[allow(unused)]
+    pub fn clear(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if let Some(frame) = self.next_frame()? {
+            let mut encoder = self
+                .ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+            let view = &frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("sugarloaf::init -> Clear frame"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.background_color),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.ctx.staging_belt.finish();
+            self.ctx.queue.submit(Some(encoder.finish()));
+            frame.present();
+            self.ctx.staging_belt.recall();
+        }
+        Ok(())
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) -> &mut Self {
         self.ctx.resize(width, height);
+        if self.smooth_resize && !self.ctx.is_minimized() {
+            self.stretch_last_frame();
+        }
+        self
+    }
+
+    /// Presents the last frame scaled to the new window size, so the window
+    /// doesn't show a cleared (or garbage) frame until the content is
+    /// reflowed and drawn again.
+    fn stretch_last_frame(&mut self) {
+        if self.last_frame.is_none() {
+            return;
+        }
+        let Ok(Some(frame)) = self.next_frame() else {
+            return;
+        };
+        let Some(last_frame) = &self.last_frame else {
+            return;
+        };
+
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.blit_brush.render(
+            &self.ctx.device,
+            &mut encoder,
+            &last_frame.create_view(&wgpu::TextureViewDescriptor::default()),
+            &frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        );
+        self.ctx.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    /// View of the texture frames are drawn into before being copied to the
+    /// window, it's recreated when the window size changes.
+    fn last_frame_view(&mut self, width: u32, height: u32) -> wgpu::TextureView {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        match &self.last_frame {
+            Some(texture) if texture.size() == size => {}
+            _ => {
+                self.last_frame =
+                    Some(self.ctx.device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("sugarloaf::render -> Last frame"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: self.ctx.format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::TEXTURE_BINDING
+                            | wgpu::TextureUsages::COPY_SRC,
+                        view_formats: &[],
+                    }));
+            }
+        }
+
+        self.last_frame
+            .as_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+            .unwrap()
+    }
+
+    /// On resize the last frame is shown scaled to the new size until the
+    /// content is drawn again, instead of a cleared frame. Frames are then
+    /// drawn offscreen and copied to the window.
+    pub fn set_smooth_resize(&mut self, smooth_resize: bool) -> &mut Self {
+        self.smooth_resize = smooth_resize;
+        if !smooth_resize {
+            self.last_frame = None;
+        }
+        self
+    }
+
+    /// Calls `callback` with the RGBA pixels, width and height of every
+    /// presented frame, e.g. to record a video. Frames are then drawn
+    /// offscreen and read back, each one waits for the gpu to finish which
+    /// costs about a frame of latency. Nothing is read without a callback.
+    pub fn set_frame_callback(
+        &mut self,
+        callback: impl FnMut(&[u8], u32, u32) + 'static,
+    ) -> &mut Self {
+        self.frame_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Stops calling the frame callback.
+    pub fn remove_frame_callback(&mut self) -> &mut Self {
+        self.frame_callback = None;
+        if !self.smooth_resize {
+            self.last_frame = None;
+        }
         self
     }
 
+    /// Nudges glyphs vertically inside their cell by `offset` logical pixels,
+    /// positive values move them down. Backgrounds, cursor and decorations
+    /// keep the same cell box. With `None` the offset comes from the ascent
+    /// and descent of the font, see `Font::baseline_offset`.
+    pub fn set_baseline_offset(&mut self, offset: Option<f32>) -> &mut Self {
+        self.baseline_offset = offset;
+        self.font_bounds = FontBounds::default();
+        self
+    }
+
+    /// Rounds cell origins to whole physical pixels, quads and glyphs placed
+    /// at fractional positions are antialiased and look blurry.
+    pub fn set_snap_to_pixel(&mut self, snap_to_pixel: bool) -> &mut Self {
+        self.snap_to_pixel = snap_to_pixel;
+        self
+    }
+
+    /// Faces made from the regular one for fonts that lack them, instead of
+    /// using the bundled ones. It applies to the fonts loaded next.
+    pub fn set_font_synthesis(&mut self, synthesis: FontSynthesis) -> &mut Self {
+        self.synthesis = synthesis;
+        self
+    }
+
+    /// Character drawn instead of the missing glyph box for characters that
+    /// no font covers (e.g. '␦' or a space).
+    pub fn set_missing_glyph_replacement(
+        &mut self,
+        replacement: Option<char>,
+    ) -> &mut Self {
+        self.missing_glyph_replacement = replacement;
+        self
+    }
+
+    /// Overlap added between adjacent backgrounds of the same color, cells
+    /// with different colors never overlap.
+    pub fn set_background_bleed(&mut self, bleed: f32) -> &mut Self {
+        self.background_bleed = bleed;
+        self
+    }
+
+    /// Color the frame is cleared with, `init` sets it too.
+    pub fn set_background_color(&mut self, color: wgpu::Color) -> &mut Self {
+        self.background_color = color;
+        self
+    }
+
+    /// Color blended over the whole frame, text included, e.g. for a visual
+    /// bell. It stays until it's replaced or removed with `None`.
+    pub fn set_overlay(&mut self, color: Option<[f32; 4]>) -> &mut Self {
+        self.overlay = color;
+        self
+    }
+
+    /// Codepoint ranges drawn with the text font whenever it has the glyph,
+    /// even if a fallback font covers them too.
+    pub fn set_force_text_font_ranges(
+        &mut self,
+        ranges: Vec<RangeInclusive<u32>>,
+    ) -> &mut Self {
+        self.force_text_font_ranges = ranges;
+        self
+    }
+
+    #[inline]
+    fn snap(&self, value: f32, scale: f32) -> f32 {
+        if self.snap_to_pixel {
+            snap_to_pixel(value, scale)
+        } else {
+            value
+        }
+    }
+
     pub fn rescale(&mut self, scale: f32) -> &mut Self {
         self.ctx.scale = scale;
         self
@@ -178,6 +890,10 @@ impl Sugarloaf {
         let mut x = 0.;
         let mut mod_size = 1.0;
 
+        if self.font_bounds.text_scale != style.text_scale {
+            self.update_font_bounds(style);
+        }
+
         if self.acc_line_y == 0.0 {
             self.acc_line_y =
                 (style.screen_position.1 - style.text_scale) / self.ctx.scale;
@@ -191,30 +907,29 @@ impl Sugarloaf {
         }
 
         let fonts = self.text_brush.fonts();
-        let system: &FontArc = &fonts[0];
-        let symbols: &FontArc = &fonts[1];
-        let emojis: &FontArc = &fonts[2];
-        let unicode: &FontArc = &fonts[3];
-        let glyph_zero = ab_glyph::GlyphId(0);
-
-        for sugar in stack.iter() {
-            let mut add_pos_x = self.font_bounds.default.0;
-
-            let mut font_id: FontId = if system.glyph_id(sugar.content) != glyph_zero {
-                FontId(FONT_ID_REGULAR)
-            } else if symbols.glyph_id(sugar.content) != glyph_zero {
-                add_pos_x = self.font_bounds.symbols.0;
-                FontId(FONT_ID_SYMBOL)
-            } else if emojis.glyph_id(sugar.content) != glyph_zero {
-                add_pos_x = self.font_bounds.emojis.0;
-                FontId(FONT_ID_EMOJIS)
-            } else if unicode.glyph_id(sugar.content) != glyph_zero {
-                add_pos_x = self.font_bounds.unicode.0;
-                FontId(FONT_ID_UNICODE)
-            } else {
-                FontId(FONT_ID_REGULAR)
+        let clear = [
+            self.background_color.r as f32,
+            self.background_color.g as f32,
+            self.background_color.b as f32,
+            self.background_color.a as f32,
+        ];
+        let mut run = None;
+
+        for (index, sugar) in stack.iter().enumerate() {
+            let content =
+                displayed_char(&self.font, sugar.content, self.missing_glyph_replacement);
+            let source = glyph_source(fonts, content, &self.force_text_font_ranges);
+            let add_pos_x = match source {
+                FONT_ID_SYMBOL => self.font_bounds.symbols.0,
+                FONT_ID_EMOJIS => self.font_bounds.emojis.0,
+                FONT_ID_UNICODE => self.font_bounds.unicode.0,
+                // Fallbacks fill in for the unicode font.
+                source if source >= FONT_ID_FALLBACK => self.font_bounds.unicode.0,
+                _ => self.font_bounds.default.0,
             };
 
+            let mut font_id = FontId(source);
+
             if font_id == FontId(FONT_ID_REGULAR) {
                 if let Some(style) = &sugar.style {
                     if style.is_bold_italic {
@@ -227,53 +942,66 @@ impl Sugarloaf {
                 }
             }
 
+            // Monochrome emojis take the color of their run like text.
+            let color = if source == FONT_ID_EMOJIS && self.font.color_emojis {
+                emoji_color(sugar.foreground_color)
+            } else {
+                sugar.foreground_color
+            };
             text.push(
-                OwnedText::new(sugar.content.to_owned())
+                OwnedText::new(content.to_owned())
                     .with_font_id(font_id)
-                    .with_color(sugar.foreground_color)
+                    .with_color(color)
                     .with_scale(style.text_scale),
             );
 
-            self.rects.push(Rect {
-                position: [
-                    (style.screen_position.0 / self.ctx.scale) + x,
-                    self.acc_line_y,
-                ],
+            let cell_x = self.snap(
+                (style.screen_position.0 / self.ctx.scale) + x,
+                self.ctx.scale,
+            );
+            let cell_y = self.snap(self.acc_line_y, self.ctx.scale);
+
+            let background = Rect {
+                position: [cell_x, cell_y],
                 color: sugar.background_color,
-                size: [add_pos_x * mod_size, self.font_bounds.default.0 * mod_size],
-            });
+                size: [
+                    add_pos_x * mod_size
+                        + background_bleed(
+                            sugar,
+                            stack.get(index + 1),
+                            self.background_bleed,
+                        ),
+                    self.font_bounds.default.0 * mod_size,
+                ],
+            };
+            run = push_background(&mut self.rects, run, background, clear);
 
             if let Some(decoration) = &sugar.decoration {
-                let dx = add_pos_x;
-                let dy = self.font_bounds.default.1 / self.ctx.scale;
-                self.rects.push(Rect {
-                    position: [
-                        (style.screen_position.0 / self.ctx.scale)
-                            + x
-                            + dx * decoration.position.0 / self.ctx.scale,
-                        self.acc_line_y + dy * decoration.position.1,
-                    ],
-                    color: decoration.color,
-                    size: [
-                        (dx * decoration.size.0) * mod_size,
-                        (dy * decoration.size.1) * mod_size,
-                    ],
-                });
+                self.rects.push(decoration_rect(
+                    decoration,
+                    [cell_x, cell_y],
+                    [add_pos_x, self.font_bounds.default.1 / self.ctx.scale],
+                    mod_size,
+                    self.ctx.scale,
+                ));
             }
 
             x += add_pos_x / self.initial_scale;
         }
 
-        let section = &OwnedSection {
-            screen_position: (
-                style.screen_position.0,
-                style.screen_position.1 + self.acc_line,
-            ),
-            bounds: style.bounds,
+        let section = &row_section(
             text,
-            layout: glyph_brush::Layout::default_single_line()
-                .v_align(glyph_brush::VerticalAlign::Bottom),
-        };
+            (
+                self.snap(style.screen_position.0, 1.0),
+                self.snap(
+                    style.screen_position.1
+                        + self.acc_line
+                        + self.font_bounds.baseline_offset,
+                    1.0,
+                ),
+            ),
+            style.bounds,
+        );
 
         self.text_brush.queue(section);
 
@@ -281,6 +1009,29 @@ impl Sugarloaf {
         self.acc_line += style.text_scale;
     }
 
+    /// Draw calls issued for the last frame, it doesn't grow with the number
+    /// of cells.
+    #[allow(unused)]
+    pub fn last_frame_draw_calls(&self) -> u32 {
+        self.last_frame_draw_calls
+    }
+
+    /// Whether `render` already presented a frame.
+    pub fn has_rendered(&self) -> bool {
+        self.first_frame.rendered
+    }
+
+    /// Runs `callback` once the first frame is presented by `render`, right
+    /// away if that already happened.
+    #[allow(unused)]
+    pub fn on_first_frame(&mut self, callback: impl FnOnce() + 'static) {
+        if self.first_frame.rendered {
+            callback();
+        } else {
+            self.first_frame.callback = Some(Box::new(callback));
+        }
+    }
+
     pub fn get_context(&self) -> &Context {
         &self.ctx
     }
@@ -301,81 +1052,97 @@ impl Sugarloaf {
             .with_color([0., 0., 0., 0.])
             .with_scale(style.text_scale)];
 
-        let section = &OwnedSection {
-            screen_position: (
+        let section = &row_section(
+            text,
+            (
                 style.screen_position.0,
                 style.screen_position.1 + self.acc_line,
             ),
-            bounds: style.bounds,
-            text,
-            layout: glyph_brush::Layout::default_single_line()
-                .v_align(glyph_brush::VerticalAlign::Bottom),
-        };
+            style.bounds,
+        );
 
         self.text_brush.queue(section);
-
-        if let Some(rect) = self.text_brush.glyph_bounds(section) {
-            let width = rect.max.x - rect.min.x;
-            let height = rect.max.y - rect.min.y;
-            return (width, height);
-        }
-
-        (0., 0.)
+        self.text_brush.font_bound(section)
     }
 
+    /// Clears the window to `color` and measures the font for `style`. Only
+    /// running out of memory is an error, like for `render`.
     #[inline]
-    pub fn init(&mut self, color: wgpu::Color, style: SugarloafStyle) {
+    pub fn init(
+        &mut self,
+        color: wgpu::Color,
+        style: SugarloafStyle,
+    ) -> Result<(), wgpu::SurfaceError> {
         self.reset_state();
         self.rects = vec![];
         self.background_color = color;
 
-        match self.ctx.surface.get_current_texture() {
-            Ok(frame) => {
-                let mut encoder = self.ctx.device.create_command_encoder(
-                    &wgpu::CommandEncoderDescriptor { label: None },
-                );
+        if let Some(frame) = self.next_frame()? {
+            let mut encoder = self
+                .ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-                let view = &frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
+            let view = &frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
 
-                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("sugarloaf::init -> Clear frame"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(color),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
-
-                if self.font_bounds.default == (0., 0.) {
-                    // Bounds are defined in runtime
-                    self.font_bounds.default =
-                        self.get_font_bounds(' ', FontId(0), style);
-                    self.font_bounds.symbols =
-                        // U+2AF9 => \u{2AF9} => ⫹
-                        self.get_font_bounds('\u{2AF9}', FontId(1), style);
-                    self.font_bounds.emojis =
-                        // U+1F947 => \u{1F947} => 🥇
-                        self.get_font_bounds('\u{1F947}', FontId(2), style);
-                    self.font_bounds.unicode =
-                        // U+33D1 => \u{33D1} => ㏑
-                        self.get_font_bounds('\u{33D1}', FontId(3), style);
-                }
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("sugarloaf::init -> Clear frame"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(color),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
 
-                self.ctx.queue.submit(Some(encoder.finish()));
-                frame.present();
-            }
-            Err(error) => {
-                if error == wgpu::SurfaceError::OutOfMemory {
-                    panic!("Swapchain error: {error}. Rendering cannot continue.")
-                }
+            if self.font_bounds.text_scale != style.text_scale {
+                self.update_font_bounds(style);
             }
+
+            self.ctx.queue.submit(Some(encoder.finish()));
+            frame.present();
         }
+        Ok(())
+    }
+
+    // Bounds are defined in runtime
+    fn update_font_bounds(&mut self, style: SugarloafStyle) {
+        self.font_bounds.text_scale = style.text_scale;
+        self.font_bounds.default = self.measure(' ', FONT_ID_REGULAR, style);
+        self.font_bounds.symbols =
+            // U+2AF9 => \u{2AF9} => ⫹
+            self.measure('\u{2AF9}', FONT_ID_SYMBOL, style);
+        self.font_bounds.emojis =
+            // U+1F947 => \u{1F947} => 🥇
+            self.measure('\u{1F947}', FONT_ID_EMOJIS, style);
+        self.font_bounds.unicode =
+            // U+33D1 => \u{33D1} => ㏑
+            self.measure('\u{33D1}', FONT_ID_UNICODE, style);
+        self.font_bounds.baseline_offset = match self.baseline_offset {
+            Some(offset) => offset * self.ctx.scale,
+            None => self.font.baseline_offset(style.text_scale),
+        };
+    }
+
+    /// Bounds of `content` in the font, measured once per text scale.
+    fn measure(
+        &mut self,
+        content: char,
+        font_id: usize,
+        style: SugarloafStyle,
+    ) -> FontBound {
+        if let Some(bound) = self.metrics.get(font_id, style.text_scale) {
+            return bound;
+        }
+
+        let bound = self.get_font_bounds(content, FontId(font_id), style);
+        self.metrics.insert(font_id, style.text_scale, bound);
+        bound
     }
 
     fn reset_state(&mut self) {
@@ -383,66 +1150,994 @@ impl Sugarloaf {
         self.acc_line_y = 0.0;
     }
 
+    /// Drops what was stacked for a frame that isn't drawn, the next one is
+    /// stacked from the top again.
+    fn skip_stacked(&mut self) {
+        self.reset_state();
+        self.rects = vec![];
+    }
+
     pub fn pile_rect(&mut self, mut instances: Vec<Rect>) -> &mut Self {
         self.rects.append(&mut instances);
         self
     }
 
-    #[inline]
-    pub fn render(&mut self) {
+    /// Records the passes that draw everything queued since the last frame
+    /// onto `view`, a target of `target_size` physical pixels, without
+    /// submitting them. It's for embedding the terminal in another render
+    /// graph, `render` is the same but for the window surface.
+    ///
+    /// Text is uploaded through `ctx.staging_belt`, so the caller has to call
+    /// `ctx.staging_belt.finish()` before submitting `encoder` and
+    /// `ctx.staging_belt.recall()` after it, before drawing again.
+    pub fn draw_into(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        target_size: (u32, u32),
+    ) {
         self.reset_state();
+        let (width, height) = target_size;
 
-        match self.ctx.surface.get_current_texture() {
-            Ok(frame) => {
-                let mut encoder = self.ctx.device.create_command_encoder(
-                    &wgpu::CommandEncoderDescriptor { label: None },
-                );
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("sugarloaf::render -> Clear frame"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.background_color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
 
-                let view = &frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
+        // Backgrounds, selections and decorations of every cell are drawn at
+        // once, whatever the size of the screen.
+        self.rect_brush.render(
+            encoder,
+            view,
+            orthographic_projection(width, height),
+            &self.rects,
+            &mut self.ctx,
+        );
+        let mut draw_calls = self.rect_brush.draw_calls();
 
-                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("sugarloaf::render -> Clear frame"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(self.background_color),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
-
-                self.rect_brush.render(
-                    &mut encoder,
-                    view,
-                    orthographic_projection(self.ctx.size.width, self.ctx.size.height),
-                    &self.rects,
-                    &mut self.ctx,
-                );
+        self.rects = vec![];
 
-                self.rects = vec![];
+        draw_calls += self
+            .text_brush
+            .draw_queued(
+                &self.ctx.device,
+                &mut self.ctx.staging_belt,
+                encoder,
+                view,
+                target_size,
+            )
+            .unwrap_or(0);
 
-                let _ = self.text_brush.draw_queued(
-                    &self.ctx.device,
-                    &mut self.ctx.staging_belt,
-                    &mut encoder,
-                    view,
-                    (self.ctx.size.width, self.ctx.size.height),
-                );
+        if let Some(color) = self.overlay {
+            let overlay = Rect {
+                position: [0., 0.],
+                color,
+                // The quad of a rect only spans half of its width.
+                size: [
+                    2.0 * width as f32 / self.ctx.scale,
+                    height as f32 / self.ctx.scale,
+                ],
+            };
+            self.rect_brush.render(
+                encoder,
+                view,
+                orthographic_projection(width, height),
+                &[overlay],
+                &mut self.ctx,
+            );
+            draw_calls += self.rect_brush.draw_calls();
+        }
+
+        self.last_frame_draw_calls = draw_calls;
+    }
+
+    /// Draws what was stacked since the last render on the window. Frames
+    /// the surface can't give (e.g. it was lost or is outdated after a
+    /// monitor change) are skipped, only running out of memory is an error.
+    #[inline]
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if self.is_device_lost() || self.ctx.is_minimized() {
+            self.skip_stacked();
+            return Ok(());
+        }
 
-                self.ctx.staging_belt.finish();
-                self.ctx.queue.submit(Some(encoder.finish()));
-                frame.present();
-                self.ctx.staging_belt.recall();
+        // A headless sugarloaf has no window frame, it only draws offscreen.
+        let frame = match self.next_frame()? {
+            Some(frame) => Some(frame),
+            None if self.ctx.surface.is_some() => {
+                self.skip_stacked();
+                return Ok(());
             }
-            Err(error) => {
-                if error == wgpu::SurfaceError::OutOfMemory {
-                    panic!("Swapchain error: {error}. Rendering cannot continue.")
+            None => None,
+        };
+        let view = frame.as_ref().map(|frame| {
+            frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let (width, height) = (self.ctx.size.width, self.ctx.size.height);
+        match &view {
+            Some(view) if !self.smooth_resize && self.frame_callback.is_none() => {
+                self.draw_into(&mut encoder, view, (width, height));
+            }
+            _ => {
+                // Kept to be stretched over the window on the next resize,
+                // or read back for the frame callback.
+                let last_frame = self.last_frame_view(width, height);
+                self.draw_into(&mut encoder, &last_frame, (width, height));
+                if let Some(view) = &view {
+                    self.blit_brush.render(
+                        &self.ctx.device,
+                        &mut encoder,
+                        &last_frame,
+                        view,
+                    );
+                    self.last_frame_draw_calls += 1;
+                }
+            }
+        }
+
+        self.ctx.staging_belt.finish();
+        self.ctx.queue.submit(Some(encoder.finish()));
+        if let Some(frame) = frame {
+            frame.present();
+        }
+        self.ctx.staging_belt.recall();
+        self.first_frame.presented();
+
+        if self.frame_callback.is_some() {
+            if let Some(pixels) = self.last_frame_rgba() {
+                if let Some(callback) = &mut self.frame_callback {
+                    callback(&pixels, width, height);
                 }
             }
         }
+        Ok(())
+    }
+
+    /// RGBA pixels of the last frame drawn offscreen, `None` when frames go
+    /// straight to the window. A headless sugarloaf always draws offscreen,
+    /// a windowed one only with smooth resize or a frame callback. It waits
+    /// for the gpu to finish.
+    pub fn last_frame_rgba(&self) -> Option<Vec<u8>> {
+        read_rgba(&self.ctx.device, &self.ctx.queue, self.last_frame.as_ref()?)
+    }
+
+    /// Renders what is queued into an offscreen texture instead of the window
+    /// and returns it as RGBA pixels, downscaled to fit within `max_width` x
+    /// `max_height`. Meant for previews of any sugarloaf, headless or not.
+    pub async fn render_thumbnail(
+        &mut self,
+        max_width: u32,
+        max_height: u32,
+    ) -> (Vec<u8>, u32, u32) {
+        let (width, height) = (self.ctx.size.width, self.ctx.size.height);
+        let texture = self.ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("sugarloaf::render_thumbnail -> Frame"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.ctx.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.draw_into(&mut encoder, &view, (width, height));
+        self.ctx.staging_belt.finish();
+        self.ctx.queue.submit(Some(encoder.finish()));
+        self.ctx.staging_belt.recall();
+
+        let Some(pixels) =
+            read_rgba_async(&self.ctx.device, &self.ctx.queue, &texture).await
+        else {
+            return (vec![], 0, 0);
+        };
+
+        let (thumbnail_width, thumbnail_height) =
+            thumbnail::fit(width, height, max_width, max_height);
+        let thumbnail = thumbnail::downscale(
+            &pixels,
+            width,
+            height,
+            thumbnail_width,
+            thumbnail_height,
+        );
+        (thumbnail, thumbnail_width, thumbnail_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "bundled-fonts")]
+    use crate::font::{FeatureFont, DEFAULT_FONT_NAME, FONT_CASCADIAMONO_REGULAR};
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn test_glyphs_are_placed_on_the_cell_grid() {
+        use glyph_brush::ab_glyph::ScaleFont;
+        use glyph_brush::GlyphPositioner;
+
+        let fonts = vec![FontArc::try_from_slice(FONT_CASCADIAMONO_REGULAR).unwrap()];
+        let text_scale = 36.0;
+        let cell_width = fonts[0]
+            .as_scaled(text_scale)
+            .h_advance(fonts[0].glyph_id(' '));
+        let origin = (24.0, 120.0);
+        let rows = ["fn main() {", "    grid[1][2]", "}"];
+        let baseline = |row: usize| {
+            origin.1 + row as f32 * text_scale + fonts[0].as_scaled(text_scale).descent()
+        };
+
+        for (row, content) in rows.iter().enumerate() {
+            // Like `stack`, every row is one line lower.
+            let line_y = origin.1 + row as f32 * text_scale;
+            let text = content
+                .chars()
+                .map(|c| OwnedText::new(c.to_string()).with_scale(text_scale))
+                .collect();
+            let section = row_section(text, (origin.0, line_y), (f32::INFINITY, 10000.));
+            let section = section.to_borrowed();
+            let glyphs = section.layout.calculate_glyphs(
+                &fonts,
+                &glyph_brush::SectionGeometry::from(&section),
+                &section.text,
+            );
+
+            assert_eq!(glyphs.len(), content.chars().count());
+            for (column, glyph) in glyphs.iter().enumerate() {
+                assert_eq!(glyph.section_index, column);
+                let position = glyph.glyph.position;
+                let cell_x = origin.0 + column as f32 * cell_width;
+                assert!((position.x - cell_x).abs() < 0.01, "{column}: {position:?}");
+                // The baseline is the descent above the bottom of the row.
+                assert_eq!(position.y, baseline(row));
+            }
+        }
+    }
+
+    #[test]
+    fn test_snap_to_pixel() {
+        assert_eq!(snap_to_pixel(10.3, 1.0), 10.0);
+        assert_eq!(snap_to_pixel(10.3, 2.0), 10.5);
+        assert_eq!(snap_to_pixel(10.6, 2.0), 10.5);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_snapped_cells_have_sharper_edges() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        // A full block in white at a fractional position, its blurry edges
+        // are the pixels it only partially covers.
+        let style = SugarloafStyle {
+            screen_position: (10.3, 24.3),
+            ..ROW_STYLE
+        };
+        let mut partially_covered_pixels = |snap_to_pixel| {
+            sugarloaf.set_snap_to_pixel(snap_to_pixel);
+            sugarloaf.stack(row_of("\u{2588}", [1.0, 1.0, 1.0, 1.0]), style);
+            sugarloaf.render().unwrap();
+            let frame = sugarloaf.last_frame_rgba().unwrap();
+            frame
+                .chunks(4)
+                .filter(|pixel| pixel[0] > 2 && pixel[0] < 253)
+                .count()
+        };
+
+        let snapped = partially_covered_pixels(true);
+        let unsnapped = partially_covered_pixels(false);
+        assert!(snapped < unsnapped, "{snapped} >= {unsnapped}");
+    }
+
+    #[test]
+    fn test_first_frame_callback_runs_once() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let calls = Rc::new(Cell::new(0));
+        let mut first_frame = FirstFrame::default();
+        let counter = calls.clone();
+        first_frame.callback = Some(Box::new(move || counter.set(counter.get() + 1)));
+        assert!(!first_frame.rendered);
+
+        first_frame.presented();
+        assert!(first_frame.rendered);
+        first_frame.presented();
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_background_bleed_covers_seams_of_same_color() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let (red, blue) = ([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]);
+        let cells = |backgrounds: &[[f32; 4]]| -> SugarStack {
+            backgrounds
+                .iter()
+                .map(|background_color| Sugar {
+                    content: ' ',
+                    foreground_color: [1.0; 4],
+                    background_color: *background_color,
+                    style: None,
+                    decoration: None,
+                })
+                .collect()
+        };
+        // Cells at fractional positions, on a clear black frame.
+        let style = SugarloafStyle {
+            screen_position: (10.3, 24.),
+            ..ROW_STYLE
+        };
+        // Pixels of the rows the cells are drawn on, without the black ones
+        // around them.
+        let rows_of =
+            |sugarloaf: &mut Sugarloaf, backgrounds: &[[f32; 4]]| -> Vec<Vec<[u8; 4]>> {
+                sugarloaf.stack(cells(backgrounds), style);
+                sugarloaf.render().unwrap();
+                let frame = sugarloaf.last_frame_rgba().unwrap();
+                frame
+                    .chunks(64 * 4)
+                    .filter_map(|row| {
+                        let pixels: Vec<[u8; 4]> = row
+                            .chunks(4)
+                            .map(|pixel| pixel.try_into().unwrap())
+                            .collect();
+                        let colored = |pixel: &[u8; 4]| pixel[..3] != [0, 0, 0];
+                        let first = pixels.iter().position(colored)?;
+                        let last = pixels.iter().rposition(colored)?;
+                        Some(pixels[first..=last].to_vec())
+                    })
+                    .collect()
+            };
+
+        // No clear pixels between the red cells.
+        let rows = rows_of(&mut sugarloaf, &[red, red, red]);
+        assert!(!rows.is_empty());
+        for row in rows {
+            assert!(
+                row.iter().all(|pixel| *pixel == [255, 0, 0, 255]),
+                "{row:?}"
+            );
+        }
+
+        // A red cell doesn't grow past the blue one next to it, however
+        // large the bleed is.
+        sugarloaf.set_background_bleed(60.);
+        for row in rows_of(&mut sugarloaf, &[red, blue]) {
+            assert_eq!(row.last(), Some(&[0, 0, 255, 255]), "{row:?}");
+        }
+    }
+
+    #[test]
+    fn test_backgrounds_of_a_row_are_batched() {
+        let (red, blue, clear) = (
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0],
+        );
+        // Cells 8 wide, drawn from quads twice their size.
+        let cell = |column: f32, color| Rect {
+            position: [column * 8.0, 16.0],
+            color,
+            size: [16.0, 16.0],
+        };
+
+        let mut rects = vec![];
+        let mut run = None;
+        for (column, color) in [red, red, red, clear, blue, red].into_iter().enumerate() {
+            run = push_background(&mut rects, run, cell(column as f32, color), clear);
+        }
+
+        // The red cell under a glyph at the first column is covered, the
+        // cell of the clear color isn't drawn.
+        assert_eq!(rects.len(), 3);
+        assert_eq!(rects[0].color, red);
+        assert_eq!(rects[0].position, [0.0, 16.0]);
+        assert_eq!(rects[0].size, [48.0, 16.0]);
+        assert_eq!(rects[1].color, blue);
+        assert_eq!(rects[1].position, [32.0, 16.0]);
+        assert_eq!(rects[2].size, [16.0, 16.0]);
+    }
+
+    #[tokio::test]
+    async fn test_background_run_covers_its_cells() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let cell = |column: f32| Rect {
+            position: [1.0 + column * 4.0, 1.0],
+            color: red,
+            size: [8.0, 8.0],
+        };
+
+        for scale in [1.0, 2.0] {
+            let (single, _) = headless_or_skip!(lit_size(cell(0.0), scale).await);
+            let mut rects = vec![];
+            let mut run = None;
+            for column in 0..3 {
+                run = push_background(&mut rects, run, cell(column as f32), [0.0; 4]);
+            }
+            assert_eq!(rects.len(), 1);
+
+            // As wide as the three cells side by side, quads light half a
+            // pixel more than their size.
+            let (lit, _) = lit_size(rects[0], scale).await.unwrap();
+            assert_eq!(lit, single + (2.0 * 4.0 * scale) as usize, "at {scale}x");
+        }
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_measure_hits_the_cache_for_the_same_font_and_scale() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let larger = SugarloafStyle {
+            text_scale: 32.,
+            ..ROW_STYLE
+        };
+
+        let bound = sugarloaf.measure(' ', FONT_ID_REGULAR, ROW_STYLE);
+        assert_eq!(
+            bound,
+            sugarloaf.get_font_bounds(' ', FontId(FONT_ID_REGULAR), ROW_STYLE)
+        );
+        #[cfg(debug_assertions)]
+        let hits = sugarloaf.metrics.hits;
+        assert_eq!(sugarloaf.measure(' ', FONT_ID_REGULAR, ROW_STYLE), bound);
+        #[cfg(debug_assertions)]
+        assert_eq!(sugarloaf.metrics.hits, hits + 1);
+
+        // Another scale or font is measured.
+        assert_ne!(sugarloaf.measure(' ', FONT_ID_REGULAR, larger), bound);
+        sugarloaf.measure('\u{1F947}', FONT_ID_EMOJIS, ROW_STYLE);
+        #[cfg(debug_assertions)]
+        assert_eq!(sugarloaf.metrics.hits, hits + 1);
+
+        // And everything again once the fonts change.
+        sugarloaf.set_font_features(&[("zero", 1)]);
+        assert_eq!(sugarloaf.measure(' ', FONT_ID_REGULAR, ROW_STYLE), bound);
+        #[cfg(debug_assertions)]
+        assert_eq!(sugarloaf.metrics.hits, hits + 1);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn test_forced_ranges_route_to_text_font() {
+        let font = Font::new(
+            crate::font::DEFAULT_FONT_NAME.to_string(),
+            FontSynthesis::default(),
+        )
+        .unwrap();
+        let fonts = vec![font.text.regular, font.symbol, font.emojis, font.unicode];
+        let arrows = [0x2190..=0x21FF];
+
+        assert_eq!(glyph_source(&fonts, '\u{2192}', &arrows), FONT_ID_REGULAR);
+        // Not in the text font, so the fallback still draws it.
+        assert_eq!(glyph_source(&fonts, '\u{21CC}', &arrows), FONT_ID_SYMBOL);
+        assert_eq!(glyph_source(&fonts, '\u{21CC}', &[]), FONT_ID_SYMBOL);
+    }
+
+    #[test]
+    #[cfg(feature = "bundled-fonts")]
+    fn test_fallback_chain_is_walked_in_order() {
+        use crate::font::{FONT_CASCADIAMONO_REGULAR, FONT_DEJAVU_MONO, FONT_EMOJI};
+
+        let cascadia = FontArc::try_from_slice(FONT_CASCADIAMONO_REGULAR).unwrap();
+        let emoji = FontArc::try_from_slice(FONT_EMOJI).unwrap();
+        let dejavu = FontArc::try_from_slice(FONT_DEJAVU_MONO).unwrap();
+        // Only the second fallback has the arrow, no bundled font has CJK.
+        let mut fonts = vec![cascadia; FONT_ID_FALLBACK];
+        fonts.push(emoji);
+        fonts.push(dejavu);
+
+        assert_eq!(glyph_source(&fonts, '\u{21CC}', &[]), FONT_ID_FALLBACK + 1);
+        assert_eq!(glyph_source(&fonts, 'a', &[]), FONT_ID_REGULAR);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[test]
+    fn test_missing_glyph_replacement() {
+        let font =
+            Font::new(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+        // No font has private use characters.
+        let missing = '\u{10FFFD}';
+        assert_eq!(displayed_char(&font, missing, None), missing);
+        assert_eq!(displayed_char(&font, missing, Some('?')), '?');
+        assert_eq!(displayed_char(&font, 'a', Some('?')), 'a');
+        // A replacement that isn't covered either draws nothing.
+        assert_eq!(displayed_char(&font, missing, Some('\u{10FFFC}')), ' ');
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_text_is_drawn_the_same_on_a_recreated_device() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let before = render_row(&mut sugarloaf, "rio", color);
+        assert!(before.iter().any(|&value| value != 0 && value != 255));
+
+        // The glyph cache, pipelines and last frame are rebuilt from the
+        // fonts of the old renderer on a new device.
+        sugarloaf.recover().await.unwrap();
+        assert!(!sugarloaf.is_device_lost());
+        assert_eq!(render_row(&mut sugarloaf, "rio", color), before);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_overlay_tints_the_whole_frame() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let plain = render_row(&mut sugarloaf, "", [1.0; 4]);
+        sugarloaf.set_overlay(Some([1.0, 0.0, 0.0, 0.5]));
+        let tinted = render_row(&mut sugarloaf, "", [1.0; 4]);
+
+        // Every pixel of every column is redder than without the overlay.
+        for (row, (plain, tinted)) in
+            plain.chunks(64 * 4).zip(tinted.chunks(64 * 4)).enumerate()
+        {
+            for (column, (plain, tinted)) in
+                plain.chunks(4).zip(tinted.chunks(4)).enumerate()
+            {
+                assert!(tinted[0] > plain[0], "pixel {column},{row} isn't tinted");
+            }
+        }
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_thumbnail_is_the_stack_rendered_and_downscaled() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        // Nothing was drawn offscreen yet, like on a window.
+        assert!(sugarloaf.last_frame.is_none());
+        let color = [1.0, 1.0, 1.0, 1.0];
+        sugarloaf.stack(row_of("rio", color), ROW_STYLE);
+
+        // The 64x32 frame keeps its aspect ratio within the bounds.
+        let (thumbnail, width, height) = sugarloaf.render_thumbnail(20, 20).await;
+        assert_eq!((width, height), (20, 10));
+        assert!(sugarloaf.last_frame.is_none());
+        let frame = render_row(&mut sugarloaf, "rio", color);
+        assert_eq!(thumbnail, thumbnail::downscale(&frame, 64, 32, 20, 10));
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_font_swap_between_draws() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/font/resources/DejaVuSansMono.ttf");
+        let dejavu = Font::from_path(&path, FontSynthesis::default()).unwrap();
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let before = render_row(&mut sugarloaf, "rio", color);
+
+        // Stacked with the old font, it goes away with its brush.
+        sugarloaf.stack(row_of("old", color), ROW_STYLE);
+        sugarloaf.replace_font(dejavu.clone());
+        let after = render_row(&mut sugarloaf, "rio", color);
+        assert_ne!(after, before);
+
+        // Drawn like by a sugarloaf made with the new font, nothing of the
+        // old one is left. Some GL drivers can't have two devices at once.
+        drop(sugarloaf);
+        let mut fresh = Sugarloaf::headless(64, 32, 1.0, dejavu).await.unwrap();
+        assert_eq!(render_row(&mut fresh, "rio", color), after);
+    }
+
+    /// A headless sugarloaf of 64x32 pixels with the bundled font, `None`
+    /// when the machine has no graphics adapter.
+    #[cfg(feature = "bundled-fonts")]
+    async fn headless_sugarloaf() -> Option<Sugarloaf> {
+        let font =
+            Font::new(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+        Sugarloaf::headless(64, 32, 1.0, font).await.ok()
+    }
+
+    /// A row of 24 px text at the top left of the frame.
+    #[cfg(feature = "bundled-fonts")]
+    const ROW_STYLE: SugarloafStyle = SugarloafStyle {
+        screen_position: (0., 24.),
+        bounds: (64., 32.),
+        text_scale: 24.,
+    };
+
+    /// Sugars of `content` in `color` on black.
+    #[cfg(feature = "bundled-fonts")]
+    fn row_of(content: &str, color: [f32; 4]) -> SugarStack {
+        content
+            .chars()
+            .map(|content| Sugar {
+                content,
+                foreground_color: color,
+                background_color: [0., 0., 0., 1.],
+                style: None,
+                decoration: None,
+            })
+            .collect()
+    }
+
+    /// Pixels of a frame with `content` drawn in `color` on black.
+    #[cfg(feature = "bundled-fonts")]
+    fn render_row(sugarloaf: &mut Sugarloaf, content: &str, color: [f32; 4]) -> Vec<u8> {
+        sugarloaf.stack(row_of(content, color), ROW_STYLE);
+        sugarloaf.render().unwrap();
+        sugarloaf.last_frame_rgba().unwrap()
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_render_draws_like_draw_into() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let color = [0.2, 0.8, 0.4, 1.0];
+        let rendered = render_row(&mut sugarloaf, "rio", color);
+        assert!(rendered.iter().any(|&value| value != 0 && value != 255));
+        // Stacked again from the top, not below the last frame.
+        assert_eq!(render_row(&mut sugarloaf, "rio", color), rendered);
+
+        sugarloaf.stack(row_of("rio", color), ROW_STYLE);
+        let texture = sugarloaf
+            .ctx
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: 64,
+                    height: 32,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: sugarloaf.ctx.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = sugarloaf
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        sugarloaf.draw_into(&mut encoder, &view, (64, 32));
+        sugarloaf.ctx.staging_belt.finish();
+        sugarloaf.ctx.queue.submit(Some(encoder.finish()));
+        sugarloaf.ctx.staging_belt.recall();
+
+        let drawn = read_rgba(&sugarloaf.ctx.device, &sugarloaf.ctx.queue, &texture);
+        assert_eq!(drawn.unwrap(), rendered);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_default_baseline_offset_centers_text() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/font/resources/DejaVuSansMono.ttf");
+        let font = Font::from_path(&path, FontSynthesis::default()).unwrap();
+        // DejaVu Sans Mono has more room above its glyphs than below.
+        let offset = font.baseline_offset(ROW_STYLE.text_scale);
+        assert!(offset < -1.0, "{offset}");
+        let mut sugarloaf =
+            headless_or_skip!(Sugarloaf::headless(64, 32, 1.0, font).await.ok());
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let top_row = |frame: Vec<u8>| {
+            frame
+                .chunks(64 * 4)
+                .position(|row| row.chunks(4).any(|pixel| pixel[0] > 128))
+        };
+
+        sugarloaf.set_baseline_offset(Some(0.0));
+        let unset = top_row(render_row(&mut sugarloaf, "H", white)).unwrap();
+        sugarloaf.set_baseline_offset(None);
+        let centered = top_row(render_row(&mut sugarloaf, "H", white)).unwrap();
+        assert_eq!(centered as f32 - unset as f32, offset.round());
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_set_font_keeps_the_fallbacks() {
+        let mut font =
+            Font::new(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+        // Draws an 'H' for every character the other fonts don't have.
+        let text = font.text.regular.clone();
+        let h = text.glyph_id('H');
+        let substitutions = HashMap::from([(ab_glyph::GlyphId(0), h)]);
+        font.fallback = vec![FontArc::new(FeatureFont::new(text, substitutions))];
+        let mut sugarloaf =
+            headless_or_skip!(Sugarloaf::headless(64, 32, 1.0, font).await.ok());
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let missing = "\u{10FFFD}";
+        let fallback = render_row(&mut sugarloaf, missing, white);
+        assert_eq!(render_row(&mut sugarloaf, "H", white), fallback);
+
+        sugarloaf.set_font(DEFAULT_FONT_NAME.to_string()).unwrap();
+        assert_eq!(render_row(&mut sugarloaf, missing, white), fallback);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_failed_set_font_keeps_the_old_font() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let frame = render_row(&mut sugarloaf, "rio", color);
+
+        // What was stacked before the failed load is still drawn.
+        sugarloaf.stack(row_of("rio", color), ROW_STYLE);
+        assert!(sugarloaf
+            .set_font(String::from("rio-font-that-does-not-exist"))
+            .is_err());
+        sugarloaf.render().unwrap();
+        assert_eq!(sugarloaf.last_frame_rgba().unwrap(), frame);
+        assert_eq!(render_row(&mut sugarloaf, "rio", color), frame);
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_minimized_frames_are_skipped() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let frame = render_row(&mut sugarloaf, "rio", color);
+
+        // The last size is kept while minimized, nothing is drawn.
+        sugarloaf.resize(0, 0);
+        assert!(sugarloaf.is_minimized());
+        assert_eq!(render_row(&mut sugarloaf, "gpu", color), frame);
+
+        sugarloaf.resize(64, 32);
+        assert!(!sugarloaf.is_minimized());
+        assert_ne!(render_row(&mut sugarloaf, "gpu", color), frame);
+        assert_eq!(render_row(&mut sugarloaf, "rio", color), frame);
+    }
+
+    /// Pixels of a frame with `content` drawn in red that aren't black.
+    #[cfg(feature = "bundled-fonts")]
+    fn lit_in_red(sugarloaf: &mut Sugarloaf, content: &str) -> Vec<[u8; 4]> {
+        render_row(sugarloaf, content, [1.0, 0.0, 0.0, 1.0])
+            .chunks(4)
+            .filter(|pixel| pixel[..3] != [0, 0, 0])
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect()
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_color_emojis_are_not_tinted_by_the_foreground() {
+        let mut font =
+            Font::new(DEFAULT_FONT_NAME.to_string(), FontSynthesis::default()).unwrap();
+        font.color_emojis = true;
+        let mut sugarloaf =
+            headless_or_skip!(Sugarloaf::headless(64, 32, 1.0, font).await.ok());
+
+        let text = lit_in_red(&mut sugarloaf, "a");
+        assert!(!text.is_empty());
+        assert!(text.iter().all(|pixel| pixel[1] == 0 && pixel[2] == 0));
+
+        // The emoji keeps its own (untinted) colors.
+        let emoji = lit_in_red(&mut sugarloaf, "\u{1F947}");
+        assert!(!emoji.is_empty());
+        assert!(emoji
+            .iter()
+            .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]));
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_monochrome_emojis_are_tinted_by_the_foreground() {
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        assert!(!sugarloaf.font.color_emojis);
+
+        let emoji = lit_in_red(&mut sugarloaf, "\u{1F947}");
+        assert!(!emoji.is_empty());
+        assert!(emoji.iter().all(|pixel| pixel[1] == 0 && pixel[2] == 0));
+    }
+
+    #[cfg(feature = "bundled-fonts")]
+    #[tokio::test]
+    async fn test_frame_callback_receives_rendered_frames() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut sugarloaf = headless_or_skip!(headless_sugarloaf().await);
+        let frames = Rc::new(RefCell::new(vec![]));
+        let received = frames.clone();
+        sugarloaf.set_frame_callback(move |pixels, width, height| {
+            received.borrow_mut().push((pixels.to_vec(), width, height));
+        });
+
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let rio = render_row(&mut sugarloaf, "rio", color);
+        let gpu = render_row(&mut sugarloaf, "gpu", color);
+        assert_ne!(rio, gpu);
+        assert_eq!(rio.len(), 64 * 32 * 4);
+        assert_eq!(*frames.borrow(), vec![(rio, 64, 32), (gpu, 64, 32)]);
+
+        sugarloaf.remove_frame_callback();
+        render_row(&mut sugarloaf, "rio", color);
+        assert_eq!(frames.borrow().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_frames_are_read_back_as_rgba() {
+        let (device, queue) =
+            headless_or_skip!(crate::components::headless_device().await);
+        // Rows of 3 pixels are padded in the copy, the frame isn't.
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 3,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Bgra8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        queue.submit(Some(encoder.finish()));
+
+        let pixels = read_rgba(&device, &queue, &texture).unwrap();
+        assert_eq!(pixels.len(), 3 * 2 * 4);
+        assert!(pixels.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_only_out_of_memory_stops_rendering() {
+        use wgpu::SurfaceError::*;
+        assert_eq!(skip_frame(Lost), Ok(true));
+        assert_eq!(skip_frame(Outdated), Ok(true));
+        assert_eq!(skip_frame(Timeout), Ok(false));
+        assert_eq!(skip_frame(OutOfMemory), Err(OutOfMemory));
+    }
+
+    /// Columns and rows of a 32x32 frame lit by `rect` at `scale`.
+    async fn lit_size(rect: Rect, scale: f32) -> Option<(usize, usize)> {
+        let (device, queue) = crate::components::headless_device().await?;
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let size = wgpu::Extent3d {
+            width: 32,
+            height: 32,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (wgpu::COPY_BYTES_PER_ROW_ALIGNMENT * 32) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut staging_belt = wgpu::util::StagingBelt::new(1024);
+        let mut encoder = device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        RectBrush::new(&device, format, scale).draw(
+            &device,
+            &mut staging_belt,
+            scale,
+            &mut encoder,
+            &view,
+            orthographic_projection(32, 32),
+            &[rect],
+        );
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        staging_belt.finish();
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let pixels = slice.get_mapped_range();
+        let lit = |x: usize, y: usize| {
+            pixels[y * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize + x * 4] > 0
+        };
+        let columns = (0..32).filter(|&x| (0..32).any(|y| lit(x, y))).count();
+        let rows = (0..32).filter(|&y| (0..32).any(|x| lit(x, y))).count();
+        Some((columns, rows))
+    }
+
+    #[tokio::test]
+    async fn test_bar_cursor_width_is_in_pixels() {
+        let bar = |pixel_width| SugarDecoration {
+            position: (0.0, 0.0),
+            size: (0.1, 1.0),
+            pixel_width: Some(pixel_width),
+            pixel_height: None,
+            color: [1.0, 1.0, 1.0, 1.0],
+        };
+
+        for scale in [1.0, 2.0] {
+            for width in [1.0, 2.0, 3.0] {
+                let rect = decoration_rect(&bar(width), [4., 0.], [8., 1.], 2.0, scale);
+                let (lit, _) = headless_or_skip!(lit_size(rect, scale).await);
+                assert_eq!(lit, (width * scale) as usize, "{width}px at {scale}x");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_underline_cursor_height_is_in_pixels() {
+        let underline = |pixel_height| SugarDecoration {
+            position: (0.0, 0.95),
+            size: (1.0, 0.05),
+            pixel_width: None,
+            pixel_height: Some(pixel_height),
+            color: [1.0, 1.0, 1.0, 1.0],
+        };
+
+        for scale in [1.0, 2.0] {
+            for height in [1.0, 2.0, 4.0] {
+                let rect =
+                    decoration_rect(&underline(height), [0., 0.], [8., 16.], 2.0, scale);
+                let (_, lit) = headless_or_skip!(lit_size(rect, scale).await);
+                assert_eq!(lit, (height * scale) as usize, "{height}px at {scale}x");
+            }
+        }
     }
 }
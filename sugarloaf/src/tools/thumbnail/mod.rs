@@ -0,0 +1,87 @@
+/// Largest size within `max_width` x `max_height` that keeps the aspect
+/// ratio of `width` x `height`, frames are never scaled up.
+pub fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    if width == 0 || height == 0 {
+        return (0, 0);
+    }
+
+    let scale = f64::min(
+        1.0,
+        f64::min(
+            max_width as f64 / width as f64,
+            max_height as f64 / height as f64,
+        ),
+    );
+    // At least one pixel on each side, unless the bounds are empty.
+    let fit_width = ((width as f64 * scale) as u32).max(1);
+    let fit_height = ((height as f64 * scale) as u32).max(1);
+    (fit_width.min(max_width), fit_height.min(max_height))
+}
+
+/// Downscales RGBA pixels by averaging the source pixels covered by each
+/// pixel of the thumbnail.
+pub fn downscale(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    thumbnail_width: u32,
+    thumbnail_height: u32,
+) -> Vec<u8> {
+    let mut thumbnail =
+        Vec::with_capacity((thumbnail_width * thumbnail_height * 4) as usize);
+
+    for y in 0..thumbnail_height {
+        let y_start = y * height / thumbnail_height;
+        let y_end = ((y + 1) * height / thumbnail_height).max(y_start + 1);
+        for x in 0..thumbnail_width {
+            let x_start = x * width / thumbnail_width;
+            let x_end = ((x + 1) * width / thumbnail_width).max(x_start + 1);
+
+            let mut sum = [0u32; 4];
+            for source_y in y_start..y_end {
+                for source_x in x_start..x_end {
+                    let index = ((source_y * width + source_x) * 4) as usize;
+                    for (channel, value) in sum.iter_mut().enumerate() {
+                        *value += pixels[index + channel] as u32;
+                    }
+                }
+            }
+
+            let count = (y_end - y_start) * (x_end - x_start);
+            thumbnail.extend(sum.iter().map(|value| (value / count) as u8));
+        }
+    }
+
+    thumbnail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_fits_within_bounds() {
+        for (width, height) in [(1280, 800), (800, 1280), (662, 438), (100, 50)] {
+            for (max_width, max_height) in [(320, 200), (64, 64), (1, 300), (2000, 2000)]
+            {
+                let (fit_width, fit_height) = fit(width, height, max_width, max_height);
+                assert!(fit_width <= max_width && fit_height <= max_height);
+                assert!(fit_width <= width && fit_height <= height);
+
+                let pixels = vec![255; (width * height * 4) as usize];
+                let thumbnail = downscale(&pixels, width, height, fit_width, fit_height);
+                assert_eq!(thumbnail.len(), (fit_width * fit_height * 4) as usize);
+            }
+        }
+
+        // Aspect ratio is kept.
+        assert_eq!(fit(1280, 800, 320, 320), (320, 200));
+    }
+
+    #[test]
+    fn test_downscale_averages_pixels() {
+        // 2x1 pixels, black and white.
+        let pixels = [0, 0, 0, 255, 255, 255, 255, 255];
+        assert_eq!(downscale(&pixels, 2, 1, 1, 1), vec![127, 127, 127, 255]);
+    }
+}
@@ -82,7 +82,8 @@ pub async fn main() {
                                 size: [200.0, 200.0],
                             },
                         ])
-                        .render();
+                        .render()
+                        .expect("Render frame");
                 }
                 _ => (),
             },
@@ -115,7 +116,8 @@ pub async fn main() {
                             size: [200.0, 200.0],
                         },
                     ])
-                    .render();
+                    .render()
+                    .expect("Render frame");
             }
             _ => {
                 *control_flow = winit::event_loop::ControlFlow::Wait;